@@ -0,0 +1,451 @@
+//! Loading and caching of images, sounds, fonts and shaders, with hot reloading.
+//!
+//! An [`Assets`] cache loads each file only once and hands out a [`Handle`] for it,
+//! which is a small `Copy` value that can be stored anywhere in the game state and
+//! turned back into the asset with [`Assets::get`].
+//!
+//! During development, [`Assets::update`] reloads assets whose files were modified,
+//! so edited images, sounds, fonts and shaders show up without restarting the game.
+//! This only works for files in physical directories, like the `resources/` directory,
+//! not for files in zip archives.
+//!
+//! To keep the game responsive while loading many or large assets, a [`Loader`] can
+//! decode them on worker threads and report its progress, for example to a loading screen.
+//!
+//! ```rust,no_run
+//! # use ggez::{assets::{Assets, Handle}, graphics::{self, Color, Image}, Context, GameResult};
+//! struct MainState {
+//!     assets: Assets,
+//!     player: Handle<Image>,
+//! }
+//!
+//! impl MainState {
+//!     fn new(ctx: &mut Context) -> GameResult<Self> {
+//!         let mut assets = Assets::new();
+//!         let player = assets.load(ctx, "/player.png")?;
+//!         Ok(MainState { assets, player })
+//!     }
+//!
+//!     fn update(&mut self, ctx: &mut Context) -> GameResult {
+//!         self.assets.update(ctx);
+//!         Ok(())
+//!     }
+//!
+//!     fn draw(&mut self, ctx: &mut Context) -> GameResult {
+//!         let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
+//!         canvas.draw(self.assets.get(self.player), [100., 100.]);
+//!         canvas.finish(ctx)
+//!     }
+//! }
+//! ```
+
+#[cfg(feature = "audio")]
+use crate::audio::SoundData;
+use crate::{
+    graphics::{FontData, Image, Shader, ShaderBuilder},
+    Context, GameError, GameResult,
+};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt, hash,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+mod loader;
+pub use loader::*;
+
+/// Something that can be loaded from a file and cached in [`Assets`].
+///
+/// This is implemented for [`Image`], [`FontData`], [`Shader`] and, with the `audio` feature,
+/// [`SoundData`], and can be implemented for the game's own file formats, such as levels.
+pub trait Asset: Sized + 'static {
+    /// Loads the asset from the given path in the [`Filesystem`](crate::filesystem::Filesystem).
+    fn load(ctx: &mut Context, path: &Path) -> GameResult<Self>;
+}
+
+impl Asset for Image {
+    fn load(ctx: &mut Context, path: &Path) -> GameResult<Self> {
+        Image::from_path(ctx, path)
+    }
+}
+
+/// Fonts are also added to the graphics context, named after their path, so that
+/// they can be used with [`TextFragment::font`](crate::graphics::TextFragment::font) right away.
+impl Asset for FontData {
+    fn load(ctx: &mut Context, path: &Path) -> GameResult<Self> {
+        let font = FontData::from_path(ctx, path)?;
+        Ok(add_font(ctx, path, font))
+    }
+}
+
+fn add_font(ctx: &mut Context, path: &Path, font: FontData) -> FontData {
+    ctx.gfx.add_font(&path.to_string_lossy(), font.clone());
+    font
+}
+
+/// Loads a shader with [`ShaderBuilder::from_path`]. Unlike `ShaderBuilder::build`,
+/// invalid WGSL code results in an error instead of a panic.
+impl Asset for Shader {
+    fn load(ctx: &mut Context, path: &Path) -> GameResult<Self> {
+        let path = path.to_str().ok_or_else(|| {
+            GameError::FilesystemError(format!("Invalid path format for resource: {path:?}"))
+        })?;
+        let device = &ctx.gfx.wgpu.device;
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = ShaderBuilder::from_path(path).build(&ctx.gfx);
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(e) => Err(GameError::RenderError(format!(
+                "invalid shader {path}: {e}"
+            ))),
+            None => shader,
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+impl Asset for SoundData {
+    fn load(ctx: &mut Context, path: &Path) -> GameResult<Self> {
+        SoundData::new(ctx, path)
+    }
+}
+
+/// A reference to an asset in an [`Assets`] cache.
+///
+/// Handles are cheap to copy, and stay valid when the asset is reloaded.
+pub struct Handle<T> {
+    index: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    fn new(index: usize) -> Self {
+        Handle {
+            index,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> hash::Hash for Handle<T> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle<{}>({})", std::any::type_name::<T>(), self.index)
+    }
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    path: PathBuf,
+    asset: T,
+    modified: Option<SystemTime>,
+}
+
+/// The assets of a single type.
+#[derive(Debug)]
+struct Store<T> {
+    entries: Vec<Entry<T>>,
+    by_path: HashMap<PathBuf, usize>,
+}
+
+trait AnyStore: Any {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// Reloads the assets whose files were modified, appending their paths to `reloaded`.
+    fn reload_modified(&mut self, ctx: &mut Context, reloaded: &mut Vec<PathBuf>);
+}
+
+impl<T: Asset> AnyStore for Store<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn reload_modified(&mut self, ctx: &mut Context, reloaded: &mut Vec<PathBuf>) {
+        for entry in &mut self.entries {
+            let modified = ctx.fs.modified(&entry.path);
+            if modified.is_none() || modified == entry.modified {
+                continue;
+            }
+            // remember the new time even if loading fails, so a broken file
+            // is only reported once, and retried once it is saved again
+            entry.modified = modified;
+            match T::load(ctx, &entry.path) {
+                Ok(asset) => {
+                    entry.asset = asset;
+                    reloaded.push(entry.path.clone());
+                }
+                Err(e) => warn!("Could not reload {}: {e:?}", entry.path.display()),
+            }
+        }
+    }
+}
+
+/// A cache of loaded assets, see the [module documentation](self).
+pub struct Assets {
+    stores: HashMap<TypeId, Box<dyn AnyStore>>,
+    hot_reload: bool,
+    poll_interval: Duration,
+    last_poll: Instant,
+}
+
+impl Assets {
+    /// Creates an empty cache.
+    ///
+    /// Hot reloading is enabled in debug builds, checking for modified files twice per second.
+    pub fn new() -> Self {
+        Assets {
+            stores: HashMap::new(),
+            hot_reload: cfg!(debug_assertions),
+            poll_interval: Duration::from_millis(500),
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Loads the asset at the given path, or returns the handle of the
+    /// already loaded asset if it was loaded before.
+    pub fn load<T: Asset>(
+        &mut self,
+        ctx: &mut Context,
+        path: impl AsRef<Path>,
+    ) -> GameResult<Handle<T>> {
+        let path = path.as_ref();
+        if let Some(&index) = self.store::<T>().and_then(|s| s.by_path.get(path)) {
+            return Ok(Handle::new(index));
+        }
+
+        let modified = ctx.fs.modified(path);
+        let asset = T::load(ctx, path)?;
+        Ok(self.insert(path.to_path_buf(), asset, modified))
+    }
+
+    /// Adds an asset loaded elsewhere, replacing the asset at the same path if there is one.
+    pub(crate) fn insert<T: Asset>(
+        &mut self,
+        path: PathBuf,
+        asset: T,
+        modified: Option<SystemTime>,
+    ) -> Handle<T> {
+        let store = self.store_mut::<T>();
+        if let Some(&index) = store.by_path.get(&path) {
+            let entry = &mut store.entries[index];
+            entry.asset = asset;
+            entry.modified = modified;
+            return Handle::new(index);
+        }
+        let index = store.entries.len();
+        let _ = store.by_path.insert(path.clone(), index);
+        store.entries.push(Entry {
+            path,
+            asset,
+            modified,
+        });
+        Handle::new(index)
+    }
+
+    /// Returns the handle of the asset at the given path, if it was loaded.
+    pub fn handle<T: Asset>(&self, path: impl AsRef<Path>) -> Option<Handle<T>> {
+        self.store::<T>()
+            .and_then(|s| s.by_path.get(path.as_ref()))
+            .map(|&index| Handle::new(index))
+    }
+
+    /// Returns the asset the handle refers to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the handle was returned by a different `Assets`.
+    pub fn get<T: Asset>(&self, handle: Handle<T>) -> &T {
+        &self.entry(handle).asset
+    }
+
+    /// Returns the path the asset was loaded from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the handle was returned by a different `Assets`.
+    pub fn path<T: Asset>(&self, handle: Handle<T>) -> &Path {
+        &self.entry(handle).path
+    }
+
+    /// Loads the asset again, whether or not its file was modified.
+    ///
+    /// If loading fails, the previously loaded asset is kept.
+    pub fn reload<T: Asset>(&mut self, ctx: &mut Context, handle: Handle<T>) -> GameResult {
+        let path = self.path(handle).to_path_buf();
+        let modified = ctx.fs.modified(&path);
+        let asset = T::load(ctx, &path)?;
+        let entry = &mut self.store_mut::<T>().entries[handle.index];
+        entry.asset = asset;
+        entry.modified = modified;
+        Ok(())
+    }
+
+    /// Returns whether modified files are reloaded by [`Assets::update`].
+    #[inline]
+    pub fn hot_reload(&self) -> bool {
+        self.hot_reload
+    }
+
+    /// Sets whether modified files are reloaded by [`Assets::update`].
+    pub fn set_hot_reload(&mut self, hot_reload: bool) {
+        self.hot_reload = hot_reload;
+    }
+
+    /// Sets how often [`Assets::update`] checks whether files were modified.
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Reloads the assets whose files were modified since they were loaded,
+    /// if hot reloading is enabled. Call this once per `update`.
+    ///
+    /// Files are only checked every so often, see [`Assets::set_poll_interval`].
+    /// If a file can't be loaded, for example because it is only partially written,
+    /// a warning is logged and the previously loaded asset is kept.
+    ///
+    /// Returns the paths of the reloaded assets.
+    pub fn update(&mut self, ctx: &mut Context) -> Vec<PathBuf> {
+        let mut reloaded = Vec::new();
+        if !self.hot_reload || self.last_poll.elapsed() < self.poll_interval {
+            return reloaded;
+        }
+        self.last_poll = Instant::now();
+        for store in self.stores.values_mut() {
+            store.reload_modified(ctx, &mut reloaded);
+        }
+        reloaded
+    }
+
+    fn store<T: Asset>(&self) -> Option<&Store<T>> {
+        self.stores
+            .get(&TypeId::of::<T>())
+            .and_then(|s| s.as_any().downcast_ref())
+    }
+
+    fn store_mut<T: Asset>(&mut self) -> &mut Store<T> {
+        self.stores
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                Box::new(Store::<T> {
+                    entries: Vec::new(),
+                    by_path: HashMap::new(),
+                })
+            })
+            .as_any_mut()
+            .downcast_mut()
+            .expect("asset store of the wrong type")
+    }
+
+    fn entry<T: Asset>(&self, handle: Handle<T>) -> &Entry<T> {
+        self.store::<T>()
+            .and_then(|s| s.entries.get(handle.index))
+            .expect("asset handle from a different `Assets`")
+    }
+}
+
+impl Default for Assets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Assets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Assets")
+            .field("types", &self.stores.len())
+            .field("hot_reload", &self.hot_reload)
+            .field("poll_interval", &self.poll_interval)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::test_context;
+    use std::io::{Read, Write};
+
+    #[derive(Debug)]
+    struct Level(String);
+
+    impl Asset for Level {
+        fn load(ctx: &mut Context, path: &Path) -> GameResult<Self> {
+            let mut s = String::new();
+            let _ = ctx.fs.open(path)?.read_to_string(&mut s)?;
+            Ok(Level(s))
+        }
+    }
+
+    #[test]
+    fn dedupe_and_hot_reload() {
+        let mut ctx = test_context("ggez_assets_reload_test");
+        let write = |ctx: &Context, s: &str| {
+            ctx.fs
+                .create("/level.txt")
+                .unwrap()
+                .write_all(s.as_bytes())
+                .unwrap();
+        };
+        write(&ctx, "one");
+
+        let mut assets = Assets::new();
+        assets.set_hot_reload(true);
+        assets.set_poll_interval(Duration::ZERO);
+        let a = assets.load::<Level>(&mut ctx, "/level.txt").unwrap();
+        let b = assets.load::<Level>(&mut ctx, "/level.txt").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(assets.handle("/level.txt"), Some(a));
+        assert_eq!(assets.get(a).0, "one");
+        assert!(assets.update(&mut ctx).is_empty());
+
+        // pretend the file was loaded long ago, rather than waiting for its modification time
+        // to change, which may only have a resolution of seconds
+        write(&ctx, "two");
+        assets.store_mut::<Level>().entries[a.index].modified = Some(SystemTime::UNIX_EPOCH);
+        assert_eq!(assets.update(&mut ctx), [PathBuf::from("/level.txt")]);
+        assert_eq!(assets.get(a).0, "two");
+        assert!(assets.update(&mut ctx).is_empty());
+
+        ctx.fs.delete("/level.txt").unwrap();
+    }
+
+    #[test]
+    fn invalid_shader() {
+        let mut ctx = test_context("ggez_assets_shader_test");
+        ctx.fs
+            .create("/broken.wgsl")
+            .unwrap()
+            .write_all(b"fn main( {")
+            .unwrap();
+        let mut assets = Assets::new();
+        assert!(assets.load::<Shader>(&mut ctx, "/broken.wgsl").is_err());
+        ctx.fs.delete("/broken.wgsl").unwrap();
+    }
+}
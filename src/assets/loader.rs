@@ -0,0 +1,362 @@
+use super::{add_font, Asset, Assets};
+#[cfg(feature = "audio")]
+use crate::audio::SoundData;
+use crate::{
+    graphics::{FontData, Image, ImageFormat},
+    Context, GameError, GameResult,
+};
+use std::{
+    any::TypeId,
+    collections::{HashSet, VecDeque},
+    fmt,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// An [`Asset`] that can be decoded on a worker thread by a [`Loader`].
+///
+/// Loading is split in two steps: [`decode`](AsyncAsset::decode) turns the file contents into
+/// something that only needs to be handed to the GPU or the audio system, which
+/// [`finish`](AsyncAsset::finish) then does on the main thread.
+pub trait AsyncAsset: Asset {
+    /// The result of decoding.
+    type Decoded: Send + 'static;
+
+    /// Decodes the contents of a file. Called on a worker thread.
+    fn decode(bytes: Vec<u8>) -> GameResult<Self::Decoded>;
+
+    /// Creates the asset from the decoded data. Called on the main thread.
+    fn finish(ctx: &mut Context, path: &Path, decoded: Self::Decoded) -> GameResult<Self>;
+}
+
+impl AsyncAsset for Image {
+    type Decoded = image::RgbaImage;
+
+    fn decode(bytes: Vec<u8>) -> GameResult<Self::Decoded> {
+        Ok(image::load_from_memory(&bytes)?.into_rgba8())
+    }
+
+    fn finish(ctx: &mut Context, _path: &Path, decoded: Self::Decoded) -> GameResult<Self> {
+        Ok(Image::from_pixels(
+            ctx,
+            decoded.as_raw(),
+            ImageFormat::Rgba8UnormSrgb,
+            decoded.width(),
+            decoded.height(),
+        ))
+    }
+}
+
+/// Fonts are added to the graphics context when finished, like when loaded by [`Assets::load`].
+impl AsyncAsset for FontData {
+    type Decoded = FontData;
+
+    fn decode(bytes: Vec<u8>) -> GameResult<Self::Decoded> {
+        FontData::from_vec(bytes)
+    }
+
+    fn finish(ctx: &mut Context, path: &Path, decoded: Self::Decoded) -> GameResult<Self> {
+        Ok(add_font(ctx, path, decoded))
+    }
+}
+
+/// Sounds are only checked to be playable, which decodes their header; the samples are
+/// decoded while playing.
+#[cfg(feature = "audio")]
+impl AsyncAsset for SoundData {
+    type Decoded = SoundData;
+
+    fn decode(bytes: Vec<u8>) -> GameResult<Self::Decoded> {
+        let sound = SoundData::from(bytes);
+        if sound.can_play() {
+            Ok(sound)
+        } else {
+            Err(GameError::AudioError(String::from(
+                "could not decode the given audio data",
+            )))
+        }
+    }
+
+    fn finish(_ctx: &mut Context, _path: &Path, decoded: Self::Decoded) -> GameResult<Self> {
+        Ok(decoded)
+    }
+}
+
+/// Adds a decoded asset to the cache, on the main thread.
+type Finish = Box<dyn FnOnce(&mut Context, &mut Assets) -> GameResult + Send>;
+/// Decodes an asset, on a worker thread.
+type Job = Box<dyn FnOnce() -> GameResult<Finish> + Send>;
+
+/// Starts a job for a queued asset, on the main thread.
+struct Queued {
+    path: PathBuf,
+    start: fn(&mut Context, &Assets, PathBuf) -> GameResult<Option<Job>>,
+}
+
+fn start<T: AsyncAsset>(
+    ctx: &mut Context,
+    assets: &Assets,
+    path: PathBuf,
+) -> GameResult<Option<Job>> {
+    if assets.handle::<T>(&path).is_some() {
+        return Ok(None);
+    }
+    let modified = ctx.fs.modified(&path);
+    // the filesystem can only be used here, but reading is left to the worker thread
+    let mut file = ctx.fs.open(&path)?;
+    Ok(Some(Box::new(move || {
+        let mut bytes = Vec::new();
+        let _ = file.read_to_end(&mut bytes)?;
+        let decoded = T::decode(bytes)?;
+        Ok(Box::new(move |ctx: &mut Context, assets: &mut Assets| {
+            let asset = T::finish(ctx, &path, decoded)?;
+            let _ = assets.insert(path, asset, modified);
+            Ok(())
+        }) as Finish)
+    })))
+}
+
+/// Loads assets into an [`Assets`] cache in the background, for example while showing a loading screen.
+///
+/// Files are read and decoded on worker threads. The decoded assets are uploaded to the GPU
+/// on the main thread, during [`Loader::update`], which only spends a limited amount of time
+/// per call. Files are opened on the main thread though, which for files in zip archives means
+/// decompressing them.
+///
+/// Sounds are only checked to be playable while loading, as they're decoded while playing; the
+/// [progress](Loader::progress) counts them as done once checked.
+///
+/// ```rust,no_run
+/// # use ggez::{assets::{Assets, Loader}, graphics::{self, Color, FontData, Image, Text}, Context, GameResult};
+/// # fn t(ctx: &mut Context, assets: &mut Assets) -> GameResult {
+/// let mut loader = Loader::new();
+/// loader.load::<Image>("/background.png");
+/// loader.load::<FontData>("/LiberationMono-Regular.ttf");
+///
+/// // once per frame, until done:
+/// loader.update(ctx, assets)?;
+/// let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
+/// let progress = format!("Loading... {:.0}%", loader.progress() * 100.);
+/// canvas.draw(&Text::new(progress), [10., 10.]);
+/// canvas.finish(ctx)?;
+///
+/// // once done, the assets are in the cache:
+/// if loader.is_done() {
+///     let background = assets.load::<Image>(ctx, "/background.png")?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Loader {
+    queued: VecDeque<Queued>,
+    seen: HashSet<(TypeId, PathBuf)>,
+    jobs: mpsc::Sender<Job>,
+    finished: mpsc::Receiver<GameResult<Finish>>,
+    total: usize,
+    done: usize,
+    budget: Duration,
+}
+
+impl Loader {
+    /// Creates a new loader with one worker thread per CPU core.
+    pub fn new() -> Self {
+        let workers = thread::available_parallelism().map_or(1, |n| n.get());
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (finished_sender, finished) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        for i in 0..workers {
+            let jobs = job_receiver.clone();
+            let finished = finished_sender.clone();
+            let _ = thread::Builder::new()
+                .name(format!("ggez asset loader {i}"))
+                .spawn(move || loop {
+                    // the lock is released before running the job
+                    let job = match jobs.lock() {
+                        Ok(jobs) => jobs.recv(),
+                        Err(_) => return,
+                    };
+                    match job {
+                        // the loader was dropped if sending fails
+                        Ok(job) => {
+                            if finished.send(job()).is_err() {
+                                return;
+                            }
+                        }
+                        Err(_) => return,
+                    }
+                });
+        }
+
+        Loader {
+            queued: VecDeque::new(),
+            seen: HashSet::new(),
+            jobs,
+            finished,
+            total: 0,
+            done: 0,
+            budget: Duration::from_millis(8),
+        }
+    }
+
+    /// Queues the asset at the given path for loading.
+    ///
+    /// Assets that are already in the cache when their turn comes are skipped,
+    /// and queuing the same asset twice has no effect.
+    pub fn load<T: AsyncAsset>(&mut self, path: impl AsRef<Path>) {
+        let path = path.as_ref().to_path_buf();
+        if self.seen.insert((TypeId::of::<T>(), path.clone())) {
+            self.queued.push_back(Queued {
+                path,
+                start: start::<T>,
+            });
+            self.total += 1;
+        }
+    }
+
+    /// Sets how much time [`Loader::update`] may spend on the main thread per call.
+    ///
+    /// Each call makes some progress regardless. The default is 8 milliseconds.
+    pub fn set_budget(&mut self, budget: Duration) {
+        self.budget = budget;
+    }
+
+    /// Reads queued files and hands them to the worker threads, and adds the assets
+    /// the workers decoded to the cache. Call this once per frame until [`Loader::is_done`].
+    ///
+    /// If an asset fails to load, its error is returned; the other assets keep loading
+    /// in later calls.
+    pub fn update(&mut self, ctx: &mut Context, assets: &mut Assets) -> GameResult {
+        let started = Instant::now();
+        let mut result = Ok(());
+
+        // finish decoded assets first, so their memory can be freed
+        loop {
+            let finish = match self.finished.try_recv() {
+                Ok(finish) => finish,
+                Err(_) => break,
+            };
+            self.done += 1;
+            if let Err(e) = finish.and_then(|finish| finish(ctx, assets)) {
+                result = result.and(Err(e));
+            }
+            if started.elapsed() >= self.budget {
+                return result;
+            }
+        }
+
+        while let Some(queued) = self.queued.pop_front() {
+            let path = queued.path.clone();
+            match (queued.start)(ctx, assets, queued.path) {
+                Ok(Some(job)) => {
+                    if self.jobs.send(job).is_err() {
+                        self.done += 1;
+                        let msg = format!("no asset loader thread left to load {path:?}");
+                        result = result.and(Err(GameError::ResourceLoadError(msg)));
+                    }
+                }
+                Ok(None) => self.done += 1,
+                Err(e) => {
+                    self.done += 1;
+                    result = result.and(Err(e));
+                }
+            }
+            if started.elapsed() >= self.budget {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Returns the fraction of queued assets that are loaded (or failed to load), from 0.0 to 1.0.
+    ///
+    /// If nothing is queued, this is 1.0.
+    pub fn progress(&self) -> f32 {
+        if self.total == 0 {
+            1.
+        } else {
+            self.done as f32 / self.total as f32
+        }
+    }
+
+    /// Returns how many assets were queued in total.
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Returns how many queued assets are loaded, or failed to load.
+    #[inline]
+    pub fn done(&self) -> usize {
+        self.done
+    }
+
+    /// Returns whether all queued assets are loaded, or failed to load.
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.done == self.total
+    }
+}
+
+impl Default for Loader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Loader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Loader")
+            .field("total", &self.total)
+            .field("done", &self.done)
+            .field("budget", &self.budget)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::test_context;
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn background_loading() {
+        let mut ctx = test_context("ggez_loader_test");
+        let mut png = Vec::new();
+        image::RgbaImage::new(3, 2)
+            .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+        ctx.fs
+            .create("/loader.png")
+            .unwrap()
+            .write_all(&png)
+            .unwrap();
+
+        let mut assets = Assets::new();
+        let mut loader = Loader::new();
+        loader.load::<Image>("/loader.png");
+        loader.load::<Image>("/loader.png");
+        loader.load::<Image>("/missing.png");
+        assert_eq!(loader.total(), 2);
+        assert_eq!(loader.progress(), 0.);
+
+        let mut errors = 0;
+        let started = Instant::now();
+        while !loader.is_done() && started.elapsed() < Duration::from_secs(10) {
+            if loader.update(&mut ctx, &mut assets).is_err() {
+                errors += 1;
+            }
+        }
+        assert!(loader.is_done());
+        assert_eq!(loader.progress(), 1.);
+        assert_eq!(errors, 1);
+        let image = assets.handle::<Image>("/loader.png").unwrap();
+        assert_eq!(assets.get(image).width(), 3);
+        assert!(assets.handle::<Image>("/missing.png").is_none());
+
+        ctx.fs.delete("/loader.png").unwrap();
+    }
+}
@@ -3,6 +3,11 @@
 //! It consists of two main types: [`SoundData`](struct.SoundData.html)
 //! is just an array of raw sound data bytes, and a [`Source`](struct.Source.html) is a
 //! `SoundData` connected to a particular sound channel ready to be played.
+//!
+//! Sources can be grouped into the buses of the [`Mixer`], to change their volume together.
+//! Besides playing files, they can synthesize sound with a [`Generator`], such as the built-in
+//! [`Oscillator`]s. The sound of sources and buses can be processed with [`Effect`]s, such as
+//! filters, echo and reverb.
 #![cfg(feature = "audio")]
 
 use std::fmt;
@@ -18,8 +23,22 @@ use std::sync::Arc;
 use crate::context::Has;
 use crate::error::GameError;
 use crate::error::GameResult;
-use crate::filesystem::Filesystem;
 use crate::filesystem::InternalClone;
+use crate::filesystem::{File, Filesystem};
+
+mod effects;
+mod fade;
+mod generator;
+mod mixer;
+mod stream;
+pub(crate) use effects::Effected;
+pub use effects::*;
+pub use fade::crossfade;
+pub(crate) use fade::{Faded, Fader};
+pub use generator::*;
+pub(crate) use generator::{Generated, GeneratorTemplate};
+pub use mixer::*;
+pub(crate) use stream::{LoopPoints, LoopingDecoder, SourceData};
 
 /// A struct that contains all information for tracking sound info.
 ///
@@ -27,8 +46,9 @@ use crate::filesystem::InternalClone;
 /// of your `Context` object.
 pub struct AudioContext {
     fs: Filesystem,
-    _stream: rodio::OutputStream,
-    stream_handle: rodio::OutputStreamHandle,
+    // `None` in headless contexts on machines without an audio device
+    output: Option<(rodio::OutputStream, rodio::OutputStreamHandle)>,
+    mixer: Mixer,
 }
 
 impl AudioContext {
@@ -41,16 +61,53 @@ impl AudioContext {
         })?;
         Ok(Self {
             fs: InternalClone::clone(fs),
-            _stream: stream,
-            stream_handle,
+            output: Some((stream, stream_handle)),
+            mixer: Mixer::new(),
         })
     }
+
+    /// Creates an `AudioContext` without an audio device, for headless contexts on machines
+    /// without one. Sources can't be created in it.
+    pub(crate) fn without_device(fs: &Filesystem) -> Self {
+        Self {
+            fs: InternalClone::clone(fs),
+            output: None,
+            mixer: Mixer::new(),
+        }
+    }
 }
 
 impl AudioContext {
-    /// Returns the audio device.
-    pub fn device(&self) -> &rodio::OutputStreamHandle {
-        &self.stream_handle
+    /// Returns the audio device, or `None` if there is none, which can only happen in a
+    /// headless context.
+    pub fn device(&self) -> Option<&rodio::OutputStreamHandle> {
+        self.output.as_ref().map(|(_, handle)| handle)
+    }
+
+    fn output(&self) -> GameResult<&rodio::OutputStreamHandle> {
+        self.device().ok_or_else(|| {
+            GameError::AudioError(String::from("There is no audio device to play sounds on"))
+        })
+    }
+
+    /// Returns the mixer, holding the buses that sources can be assigned to.
+    pub fn mixer(&self) -> &Mixer {
+        &self.mixer
+    }
+
+    /// Returns the mixer mutably, to add buses to it.
+    pub fn mixer_mut(&mut self) -> &mut Mixer {
+        &mut self.mixer
+    }
+
+    /// Returns the master volume, `1.0` by default.
+    pub fn master_volume(&self) -> f32 {
+        self.mixer.master().volume()
+    }
+
+    /// Sets the master volume, which multiplies the volume of all sources.
+    pub fn set_master_volume(&self, volume: f32) {
+        self.mixer.master().set_volume(volume)
     }
 }
 
@@ -143,9 +200,38 @@ pub trait SoundSource {
     /// Sets the source to repeat playback infinitely on next [`play()`](#method.play)
     fn set_repeat(&mut self, repeat: bool);
 
+    /// Sets the part of the sound that's repeated when the source is set to repeat, from `start`
+    /// up to `end`, or the end of the sound if it's `None`; by default the whole sound.
+    ///
+    /// The first time, the sound plays from its beginning (or [`set_start()`](#tymethod.set_start))
+    /// up to `end`, so it can have an intro that isn't repeated. Without repeating, it plays up to
+    /// `end` once. Takes effect on the next [`play()`](#method.play).
+    ///
+    /// Generated sounds ignore loop points, and repeat as a whole.
+    fn set_loop_points(&mut self, start: time::Duration, end: Option<time::Duration>);
+
     /// Sets the fade-in time of the source
     fn set_fade_in(&mut self, dur: time::Duration);
 
+    /// Fades the volume of the source to `volume` over the given duration, on the audio thread.
+    ///
+    /// This fade volume multiplies the volume set with [`set_volume()`](#tymethod.set_volume),
+    /// starts at `1.0` and stays as faded to, also when playing again, except after
+    /// [`fade_out()`](#tymethod.fade_out). A duration of zero changes it immediately.
+    fn fade_to(&mut self, volume: f32, dur: time::Duration);
+
+    /// Fades the source out over the given duration and then stops it, rather than stopping it
+    /// abruptly, which can click audibly.
+    ///
+    /// Once faded out, and any echoes or reverb of its effects have died away, the source is
+    /// [`stopped()`](#tymethod.stopped), and the fade volume returns to `1.0` the next time it's
+    /// played. See also [`crossfade`].
+    fn fade_out(&mut self, dur: time::Duration);
+
+    /// Returns the current fade volume, as changed by [`fade_to()`](#tymethod.fade_to) and
+    /// [`fade_out()`](#tymethod.fade_out).
+    fn fade_volume(&self) -> f32;
+
     /// Sets the time from which playback begins, skipping audio up to that point.
     ///
     /// Calls to [`elapsed()`](#tymethod.elapsed) will measure from this point, ignoring skipped time.
@@ -198,31 +284,63 @@ pub trait SoundSource {
     ///
     /// This parameter determines the precision of the time measured by [`elapsed()`](#method.elapsed).
     fn set_query_interval(&mut self, t: time::Duration);
+
+    /// Assigns the source to a [`Bus`] of the [`Mixer`], or to none but the master bus with
+    /// `None`, which is the default.
+    ///
+    /// This takes effect the next time the source is played.
+    fn set_bus(&mut self, bus: Option<&Bus>);
+
+    /// Returns the bus the source is assigned to, if any.
+    fn bus(&self) -> Option<&Bus>;
+
+    /// Adds an effect to the sound of the source, after the effects before it and before those of
+    /// its bus.
+    ///
+    /// This takes effect the next time the source is played, while changes to the parameters of
+    /// the effect apply immediately.
+    fn add_effect(&mut self, effect: impl Into<Effect>);
+
+    /// Removes all effects from the source, the next time it's played.
+    fn clear_effects(&mut self);
+
+    /// Returns the effects of the source.
+    fn effects(&self) -> &[Effect];
 }
 
 /// Internal state used by audio sources.
 #[derive(Debug)]
 pub(crate) struct SourceState {
-    data: io::Cursor<SoundData>,
+    data: SourceData,
     repeat: bool,
+    loop_points: LoopPoints,
     fade_in: time::Duration,
     skip_duration: time::Duration,
     speed: f32,
     query_interval: time::Duration,
     play_time: Arc<AtomicUsize>,
+    master: Bus,
+    bus: Option<Bus>,
+    fader: Fader,
+    effects: Vec<Effect>,
 }
 
 impl SourceState {
-    /// Create a new `SourceState` based around the given `SoundData`
-    pub fn new(cursor: io::Cursor<SoundData>) -> Self {
+    /// Create a new `SourceState` based around the given `SourceData`
+    pub fn new(data: SourceData, master: Bus) -> Self {
         SourceState {
-            data: cursor,
+            data,
             repeat: false,
+            loop_points: LoopPoints::default(),
             fade_in: time::Duration::from_millis(0),
             skip_duration: time::Duration::from_millis(0),
             speed: 1.0,
             query_interval: time::Duration::from_millis(100),
             play_time: Arc::new(AtomicUsize::new(0)),
+            master,
+            bus: None,
+            fader: Fader::new(),
+            effects: Vec::new(),
         }
     }
     /// Sets the source to repeat playback infinitely on next [`play()`](#method.play)
@@ -230,6 +348,10 @@ impl SourceState {
         self.repeat = repeat;
     }
 
+    pub fn set_loop_points(&mut self, start: time::Duration, end: Option<time::Duration>) {
+        self.loop_points = LoopPoints { start, end };
+    }
+
     /// Sets the fade-in time of the source.
     pub fn set_fade_in(&mut self, dur: time::Duration) {
         self.fade_in = dur;
@@ -264,21 +386,86 @@ impl SourceState {
     pub fn set_query_interval(&mut self, t: time::Duration) {
         self.query_interval = t;
     }
+
+    pub fn set_bus(&mut self, bus: Option<&Bus>) {
+        self.bus = bus.cloned();
+    }
+
+    pub fn bus(&self) -> Option<&Bus> {
+        self.bus.as_ref()
+    }
+
+    pub fn fade_to(&mut self, volume: f32, dur: time::Duration) {
+        self.fader.fade_to(volume, dur, false)
+    }
+
+    pub fn fade_out(&mut self, dur: time::Duration) {
+        self.fader.fade_to(0., dur, true)
+    }
+
+    pub fn fade_volume(&self) -> f32 {
+        self.fader.volume()
+    }
+
+    pub fn add_effect(&mut self, effect: Effect) {
+        self.effects.push(effect);
+    }
+
+    pub fn clear_effects(&mut self) {
+        self.effects.clear();
+    }
+
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    /// Creates the sound to play with the settings of the source, through its effects and buses.
+    pub fn sound(&self) -> GameResult<impl rodio::Source<Item = f32> + Send> {
+        // Creating a new Decoder each time seems a little messy,
+        // since it may do checking and data-type detection that is
+        // redundant, but it's not super expensive.
+        // See https://github.com/ggez/ggez/issues/98 for discussion
+        use rodio::Source;
+
+        let counter = self.play_time.clone();
+        let period_mus = self.query_interval.as_secs() as usize * 1_000_000
+            + self.query_interval.subsec_micros() as usize;
+
+        let sound: Box<dyn rodio::Source<Item = f32> + Send> = match &self.data {
+            SourceData::Generator(template) => {
+                Box::new(Generated::new(template.clone(), self.repeat))
+            }
+            data => Box::new(
+                LoopingDecoder::new(data.clone(), self.repeat, self.loop_points)?.convert_samples(),
+            ),
+        };
+        let sound = sound
+            .skip_duration(self.skip_duration)
+            .speed(self.speed)
+            .fade_in(self.fade_in)
+            .periodic_access(self.query_interval, move |_| {
+                let _ = counter.fetch_add(period_mus, Ordering::SeqCst);
+            });
+
+        self.fader.reset_stopped();
+        let sound = Effected::new(
+            Faded::new(sound, self.fader.clone()),
+            self.effects.clone(),
+            self.master.clone(),
+            self.bus.clone(),
+        );
+        Ok(Mixed::new(sound, self.master.clone(), self.bus.clone()))
+    }
 }
 
 /// A source of audio data that is connected to an output
 /// channel and ready to play.  It will stop playing when
 /// dropped.
+///
+/// The sound is either a [`SoundData`] in memory, streamed from a file with
+/// [`Source::new_streaming`] or [`Source::from_file`], which suits long music tracks, or
+/// synthesized by a [`Generator`] with [`Source::from_generator`].
 // TODO LATER: Check and see if this matches Love2d's semantics!
-// Eventually it might read from a streaming decoder of some kind,
-// but for now it is just an in-memory SoundData structure.
-// The source of a rodio decoder must be Send, which something
-// that contains a reference to a ZipFile is not, so we are going
-// to just slurp all the data into memory for now.
-// There's really a lot of work that needs to be done here, since
-// rodio has gotten better (if still somewhat arcane) and our filesystem
-// code has done the data-slurping-from-zip's for us
-// but for now it works.
 pub struct Source {
     sink: rodio::Sink,
     state: SourceState,
@@ -301,49 +488,79 @@ impl Source {
                 "Could not decode the given audio data".to_string(),
             ));
         }
-        let sink = rodio::Sink::try_new(audio.device())?;
-        let cursor = io::Cursor::new(data);
+        let sink = rodio::Sink::try_new(audio.output()?)?;
         Ok(Source {
             sink,
-            state: SourceState::new(cursor),
+            state: SourceState::new(SourceData::Memory(data), audio.mixer.master().clone()),
+        })
+    }
+
+    /// Creates a new `Source` streaming the file at the given path; see [`Source::from_file`].
+    pub fn new_streaming<P: AsRef<path::Path>>(
+        audio: &impl Has<AudioContext>,
+        path: P,
+    ) -> GameResult<Self> {
+        let audio = audio.retrieve();
+        let file = audio.fs.open(path)?;
+        Source::from_file(audio, file)
+    }
+
+    /// Creates a new `Source` that streams the given file, decoding it while it plays rather than
+    /// reading it into memory first, which saves a lot of memory for long music tracks.
+    ///
+    /// Files in zip archives are the exception: they're decompressed into memory when they're
+    /// opened, and only decoded while playing.
+    pub fn from_file(audio: &impl Has<AudioContext>, file: File) -> GameResult<Self> {
+        let audio = audio.retrieve();
+        let data = SourceData::stream(file);
+        let _ = data.decoder()?;
+        let sink = rodio::Sink::try_new(audio.output()?)?;
+        Ok(Source {
+            sink,
+            state: SourceState::new(data, audio.mixer.master().clone()),
+        })
+    }
+
+    /// Creates a new `Source` playing the samples of the given [`Generator`].
+    ///
+    /// The generator is cloned each time the source plays, so every play starts over from the
+    /// generator as it was given here. Use [`Source::from_generator_fn`] for generators that
+    /// can't be cloned.
+    pub fn from_generator<G>(audio: &impl Has<AudioContext>, generator: G) -> GameResult<Self>
+    where
+        G: Generator + Clone + 'static,
+    {
+        Source::from_template(audio, GeneratorTemplate::from_clone(generator)?)
+    }
+
+    /// Creates a new `Source` playing the samples of the generators created by the given function,
+    /// which is called each time the source plays.
+    pub fn from_generator_fn<F>(audio: &impl Has<AudioContext>, generator: F) -> GameResult<Self>
+    where
+        F: Fn() -> Box<dyn Generator> + Send + Sync + 'static,
+    {
+        Source::from_template(audio, GeneratorTemplate::new(generator)?)
+    }
+
+    fn from_template(
+        audio: &impl Has<AudioContext>,
+        template: GeneratorTemplate,
+    ) -> GameResult<Self> {
+        let audio = audio.retrieve();
+        let sink = rodio::Sink::try_new(audio.output()?)?;
+        Ok(Source {
+            sink,
+            state: SourceState::new(
+                SourceData::Generator(template),
+                audio.mixer.master().clone(),
+            ),
         })
     }
 }
 
 impl SoundSource for Source {
     fn play_later(&self) -> GameResult {
-        // Creating a new Decoder each time seems a little messy,
-        // since it may do checking and data-type detection that is
-        // redundant, but it's not super expensive.
-        // See https://github.com/ggez/ggez/issues/98 for discussion
-        use rodio::Source;
-        let cursor = self.state.data.clone();
-
-        let counter = self.state.play_time.clone();
-        let period_mus = self.state.query_interval.as_secs() as usize * 1_000_000
-            + self.state.query_interval.subsec_micros() as usize;
-
-        if self.state.repeat {
-            let sound = rodio::Decoder::new(cursor)?
-                .repeat_infinite()
-                .skip_duration(self.state.skip_duration)
-                .speed(self.state.speed)
-                .fade_in(self.state.fade_in)
-                .periodic_access(self.state.query_interval, move |_| {
-                    let _ = counter.fetch_add(period_mus, Ordering::SeqCst);
-                });
-            self.sink.append(sound);
-        } else {
-            let sound = rodio::Decoder::new(cursor)?
-                .skip_duration(self.state.skip_duration)
-                .speed(self.state.speed)
-                .fade_in(self.state.fade_in)
-                .periodic_access(self.state.query_interval, move |_| {
-                    let _ = counter.fetch_add(period_mus, Ordering::SeqCst);
-                });
-            self.sink.append(sound);
-        }
-
+        self.sink.append(self.state.sound()?);
         Ok(())
     }
 
@@ -352,7 +569,7 @@ impl SoundSource for Source {
         self.stop(audio)?;
         self.play_later()?;
 
-        let new_sink = rodio::Sink::try_new(audio.device())?;
+        let new_sink = rodio::Sink::try_new(audio.output()?)?;
         let old_sink = mem::replace(&mut self.sink, new_sink);
         old_sink.detach();
 
@@ -362,6 +579,9 @@ impl SoundSource for Source {
     fn set_repeat(&mut self, repeat: bool) {
         self.state.set_repeat(repeat)
     }
+    fn set_loop_points(&mut self, start: time::Duration, end: Option<time::Duration>) {
+        self.state.set_loop_points(start, end)
+    }
     fn set_fade_in(&mut self, dur: time::Duration) {
         self.state.set_fade_in(dur)
     }
@@ -397,7 +617,7 @@ impl SoundSource for Source {
         // We also need to carry over information from the previous sink.
         let volume = self.volume();
 
-        let device = audio.device();
+        let device = audio.output()?;
         self.sink = rodio::Sink::try_new(device)?;
         self.state.play_time.store(0, Ordering::SeqCst);
 
@@ -433,6 +653,38 @@ impl SoundSource for Source {
     fn set_query_interval(&mut self, t: time::Duration) {
         self.state.set_query_interval(t)
     }
+
+    fn set_bus(&mut self, bus: Option<&Bus>) {
+        self.state.set_bus(bus)
+    }
+
+    fn bus(&self) -> Option<&Bus> {
+        self.state.bus()
+    }
+
+    fn fade_to(&mut self, volume: f32, dur: time::Duration) {
+        self.state.fade_to(volume, dur)
+    }
+
+    fn fade_out(&mut self, dur: time::Duration) {
+        self.state.fade_out(dur)
+    }
+
+    fn fade_volume(&self) -> f32 {
+        self.state.fade_volume()
+    }
+
+    fn add_effect(&mut self, effect: impl Into<Effect>) {
+        self.state.add_effect(effect.into())
+    }
+
+    fn clear_effects(&mut self) {
+        self.state.clear_effects()
+    }
+
+    fn effects(&self) -> &[Effect] {
+        self.state.effects()
+    }
 }
 
 impl fmt::Debug for Source {
@@ -465,24 +717,53 @@ impl SpatialSource {
 
     /// Creates a new `SpatialSource` using the given `SoundData` object.
     pub fn from_data(audio: &impl Has<AudioContext>, data: SoundData) -> GameResult<Self> {
-        let audio = audio.retrieve();
         if !data.can_play() {
             return Err(GameError::AudioError(
                 "Could not decode the given audio data".to_string(),
             ));
         }
+        SpatialSource::from_source_data(audio, SourceData::Memory(data))
+    }
+
+    /// Creates a new `SpatialSource` that streams the given file; see [`Source::from_file`].
+    pub fn from_file(audio: &impl Has<AudioContext>, file: File) -> GameResult<Self> {
+        let data = SourceData::stream(file);
+        let _ = data.decoder()?;
+        SpatialSource::from_source_data(audio, data)
+    }
+
+    /// Creates a new `SpatialSource` playing the samples of the given [`Generator`]; see
+    /// [`Source::from_generator`].
+    pub fn from_generator<G>(audio: &impl Has<AudioContext>, generator: G) -> GameResult<Self>
+    where
+        G: Generator + Clone + 'static,
+    {
+        let data = SourceData::Generator(GeneratorTemplate::from_clone(generator)?);
+        SpatialSource::from_source_data(audio, data)
+    }
+
+    /// Creates a new `SpatialSource` playing the samples of the generators created by the given
+    /// function; see [`Source::from_generator_fn`].
+    pub fn from_generator_fn<F>(audio: &impl Has<AudioContext>, generator: F) -> GameResult<Self>
+    where
+        F: Fn() -> Box<dyn Generator> + Send + Sync + 'static,
+    {
+        let data = SourceData::Generator(GeneratorTemplate::new(generator)?);
+        SpatialSource::from_source_data(audio, data)
+    }
+
+    fn from_source_data(audio: &impl Has<AudioContext>, data: SourceData) -> GameResult<Self> {
+        let audio = audio.retrieve();
         let sink = rodio::SpatialSink::try_new(
-            audio.device(),
+            audio.output()?,
             [0.0, 0.0, 0.0],
             [-1.0, 0.0, 0.0],
             [1.0, 0.0, 0.0],
         )?;
 
-        let cursor = io::Cursor::new(data);
-
         Ok(SpatialSource {
             sink,
-            state: SourceState::new(cursor),
+            state: SourceState::new(data, audio.mixer.master().clone()),
             left_ear: [-1.0, 0.0, 0.0].into(),
             right_ear: [1.0, 0.0, 0.0].into(),
             emitter_position: [0.0, 0.0, 0.0].into(),
@@ -493,38 +774,7 @@ impl SpatialSource {
 impl SoundSource for SpatialSource {
     /// Plays the `SpatialSource`; waits until done if the sound is currently playing.
     fn play_later(&self) -> GameResult {
-        // Creating a new Decoder each time seems a little messy,
-        // since it may do checking and data-type detection that is
-        // redundant, but it's not super expensive.
-        // See https://github.com/ggez/ggez/issues/98 for discussion
-        use rodio::Source;
-        let cursor = self.state.data.clone();
-
-        let counter = self.state.play_time.clone();
-        let period_mus = self.state.query_interval.as_secs() as usize * 1_000_000
-            + self.state.query_interval.subsec_micros() as usize;
-
-        if self.state.repeat {
-            let sound = rodio::Decoder::new(cursor)?
-                .repeat_infinite()
-                .skip_duration(self.state.skip_duration)
-                .speed(self.state.speed)
-                .fade_in(self.state.fade_in)
-                .periodic_access(self.state.query_interval, move |_| {
-                    let _ = counter.fetch_add(period_mus, Ordering::SeqCst);
-                });
-            self.sink.append(sound);
-        } else {
-            let sound = rodio::Decoder::new(cursor)?
-                .skip_duration(self.state.skip_duration)
-                .speed(self.state.speed)
-                .fade_in(self.state.fade_in)
-                .periodic_access(self.state.query_interval, move |_| {
-                    let _ = counter.fetch_add(period_mus, Ordering::SeqCst);
-                });
-            self.sink.append(sound);
-        }
-
+        self.sink.append(self.state.sound()?);
         Ok(())
     }
 
@@ -533,7 +783,7 @@ impl SoundSource for SpatialSource {
         self.stop(audio)?;
         self.play_later()?;
 
-        let device = audio.device();
+        let device = audio.output()?;
         let new_sink = rodio::SpatialSink::try_new(
             device,
             self.emitter_position.into(),
@@ -550,6 +800,10 @@ impl SoundSource for SpatialSource {
         self.state.set_repeat(repeat)
     }
 
+    fn set_loop_points(&mut self, start: time::Duration, end: Option<time::Duration>) {
+        self.state.set_loop_points(start, end)
+    }
+
     fn set_fade_in(&mut self, dur: time::Duration) {
         self.state.set_fade_in(dur)
     }
@@ -590,7 +844,7 @@ impl SoundSource for SpatialSource {
         // We also need to carry over information from the previous sink.
         let volume = self.volume();
 
-        let device = audio.device();
+        let device = audio.output()?;
         self.sink = rodio::SpatialSink::try_new(
             device,
             self.emitter_position.into(),
@@ -631,6 +885,38 @@ impl SoundSource for SpatialSource {
     fn set_query_interval(&mut self, t: time::Duration) {
         self.state.set_query_interval(t)
     }
+
+    fn set_bus(&mut self, bus: Option<&Bus>) {
+        self.state.set_bus(bus)
+    }
+
+    fn bus(&self) -> Option<&Bus> {
+        self.state.bus()
+    }
+
+    fn fade_to(&mut self, volume: f32, dur: time::Duration) {
+        self.state.fade_to(volume, dur)
+    }
+
+    fn fade_out(&mut self, dur: time::Duration) {
+        self.state.fade_out(dur)
+    }
+
+    fn fade_volume(&self) -> f32 {
+        self.state.fade_volume()
+    }
+
+    fn add_effect(&mut self, effect: impl Into<Effect>) {
+        self.state.add_effect(effect.into())
+    }
+
+    fn clear_effects(&mut self) {
+        self.state.clear_effects()
+    }
+
+    fn effects(&self) -> &[Effect] {
+        self.state.effects()
+    }
 }
 
 impl SpatialSource {
@@ -660,3 +946,19 @@ impl fmt::Debug for SpatialSource {
         write!(f, "<Spatial audio source: {self:p}>")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::test_context;
+
+    #[test]
+    fn without_device() {
+        let ctx = test_context("ggez_audio_test");
+        let audio = AudioContext::without_device(&ctx.fs);
+        assert!(audio.device().is_none());
+        let tone = Oscillator::sine(440.);
+        assert!(Source::from_generator(&audio, tone).is_err());
+        assert!(SpatialSource::from_generator(&audio, tone).is_err());
+    }
+}
@@ -0,0 +1,965 @@
+use super::Bus;
+use std::{
+    f32::consts::{FRAC_1_SQRT_2, TAU},
+    fmt,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+// longest tail played after a sound ends, however long its echoes would ring
+const MAX_TAIL: Duration = Duration::from_secs(10);
+// level at which echoes and reverb count as silent
+const SILENCE: f32 = 0.001;
+// samples between checks whether a tail has become silent
+const SILENCE_CHECK: u64 = 1024;
+
+/// A parameter of an effect, shared with the audio thread.
+struct Param(AtomicU32);
+
+impl Param {
+    fn new(value: f32) -> Self {
+        Param(AtomicU32::new(value.to_bits()))
+    }
+
+    fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    fn set(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed)
+    }
+}
+
+/// An audio effect processing the sound of a source, added with
+/// [`SoundSource::add_effect`](super::SoundSource::add_effect), or of all sources of a bus, added
+/// with [`Bus::add_effect`].
+///
+/// Effects are cheap handles to parameters shared with the audio thread, so changing them affects
+/// sounds that are already playing, and cloning one returns another handle to the same
+/// parameters. Each effect blends its output with its input by its mix, from `0.0` for only the
+/// input, which bypasses it, to `1.0` for only its output.
+///
+/// ```rust,no_run
+/// # use ggez::{audio::{Filter, Mixer}, Context};
+/// # fn t(ctx: &mut Context, underwater: bool) {
+/// // muffles all sound effects while the player is underwater
+/// let muffle = Filter::low_pass(600.);
+/// ctx.audio.mixer().bus(Mixer::SFX).unwrap().add_effect(muffle.clone());
+///
+/// // when going in or out of the water
+/// muffle.set_mix(if underwater { 1. } else { 0. });
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Effect {
+    /// A biquad filter.
+    Filter(Filter),
+    /// A delay, for echoes.
+    Delay(Delay),
+    /// A reverb.
+    Reverb(Reverb),
+    /// A distortion.
+    Distortion(Distortion),
+}
+
+impl Effect {
+    /// Returns how much of the output of the effect is heard.
+    pub fn mix(&self) -> f32 {
+        match self {
+            Effect::Filter(filter) => filter.mix(),
+            Effect::Delay(delay) => delay.mix(),
+            Effect::Reverb(reverb) => reverb.mix(),
+            Effect::Distortion(distortion) => distortion.mix(),
+        }
+    }
+
+    /// Sets how much of the output of the effect is heard, from `0.0` to `1.0`.
+    pub fn set_mix(&self, mix: f32) {
+        match self {
+            Effect::Filter(filter) => filter.set_mix(mix),
+            Effect::Delay(delay) => delay.set_mix(mix),
+            Effect::Reverb(reverb) => reverb.set_mix(mix),
+            Effect::Distortion(distortion) => distortion.set_mix(mix),
+        }
+    }
+
+    fn processor(&self, channels: u16, sample_rate: u32) -> Processor {
+        let channels = usize::from(channels.max(1));
+        let sample_rate = sample_rate.max(1) as f32;
+        let process: Box<dyn Process> = match self {
+            Effect::Filter(filter) => Box::new(FilterProcess {
+                filter: filter.clone(),
+                sample_rate,
+                params: (0., 0.),
+                coefficients: [0.; 5],
+                history: vec![[0.; 4]; channels],
+            }),
+            Effect::Delay(delay) => Box::new(DelayProcess {
+                delay: delay.clone(),
+                sample_rate,
+                channels,
+                buffer: Vec::new(),
+                frames: 0,
+                frame: 0,
+                length: 0,
+                feedback: 0.,
+            }),
+            Effect::Reverb(reverb) => {
+                Box::new(ReverbProcess::new(reverb.clone(), channels, sample_rate))
+            }
+            Effect::Distortion(distortion) => Box::new(DistortionProcess {
+                distortion: distortion.clone(),
+                drive: 0.,
+            }),
+        };
+        Processor {
+            effect: self.clone(),
+            mix: 0.,
+            process,
+        }
+    }
+}
+
+impl From<Filter> for Effect {
+    fn from(filter: Filter) -> Self {
+        Effect::Filter(filter)
+    }
+}
+
+impl From<Delay> for Effect {
+    fn from(delay: Delay) -> Self {
+        Effect::Delay(delay)
+    }
+}
+
+impl From<Reverb> for Effect {
+    fn from(reverb: Reverb) -> Self {
+        Effect::Reverb(reverb)
+    }
+}
+
+impl From<Distortion> for Effect {
+    fn from(distortion: Distortion) -> Self {
+        Effect::Distortion(distortion)
+    }
+}
+
+/// The frequencies a [`Filter`] lets through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterKind {
+    /// Lets frequencies below the cutoff through, which sounds muffled, such as underwater or
+    /// behind a wall.
+    LowPass,
+    /// Lets frequencies above the cutoff through, which sounds thin, such as over a radio.
+    HighPass,
+    /// Lets frequencies around the cutoff through.
+    BandPass,
+}
+
+/// A biquad filter [`Effect`], letting through some frequencies of a sound and cutting others.
+#[derive(Clone)]
+pub struct Filter(Arc<FilterState>);
+
+struct FilterState {
+    kind: FilterKind,
+    cutoff: Param,
+    q: Param,
+    mix: Param,
+}
+
+impl Filter {
+    /// Creates a filter of the given kind, with its cutoff frequency in Hz, and its resonance
+    /// `q`, where higher values emphasize the cutoff frequency more.
+    pub fn new(kind: FilterKind, cutoff: f32, q: f32) -> Self {
+        Filter(Arc::new(FilterState {
+            kind,
+            cutoff: Param::new(cutoff),
+            q: Param::new(q),
+            mix: Param::new(1.),
+        }))
+    }
+
+    /// Creates a low-pass filter, without resonance.
+    pub fn low_pass(cutoff: f32) -> Self {
+        Filter::new(FilterKind::LowPass, cutoff, FRAC_1_SQRT_2)
+    }
+
+    /// Creates a high-pass filter, without resonance.
+    pub fn high_pass(cutoff: f32) -> Self {
+        Filter::new(FilterKind::HighPass, cutoff, FRAC_1_SQRT_2)
+    }
+
+    /// Creates a band-pass filter around the given frequency.
+    pub fn band_pass(frequency: f32) -> Self {
+        Filter::new(FilterKind::BandPass, frequency, FRAC_1_SQRT_2)
+    }
+
+    /// Returns the kind of the filter.
+    pub fn kind(&self) -> FilterKind {
+        self.0.kind
+    }
+
+    /// Returns the cutoff frequency in Hz.
+    pub fn cutoff(&self) -> f32 {
+        self.0.cutoff.get()
+    }
+
+    /// Sets the cutoff frequency in Hz.
+    pub fn set_cutoff(&self, cutoff: f32) {
+        self.0.cutoff.set(cutoff)
+    }
+
+    /// Returns the resonance.
+    pub fn q(&self) -> f32 {
+        self.0.q.get()
+    }
+
+    /// Sets the resonance.
+    pub fn set_q(&self, q: f32) {
+        self.0.q.set(q)
+    }
+
+    /// Returns how much of the filtered sound is heard, `1.0` by default.
+    pub fn mix(&self) -> f32 {
+        self.0.mix.get()
+    }
+
+    /// Sets how much of the filtered sound is heard, from `0.0` to `1.0`.
+    pub fn set_mix(&self, mix: f32) {
+        self.0.mix.set(mix.clamp(0., 1.))
+    }
+}
+
+impl PartialEq for Filter {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Filter")
+            .field("kind", &self.kind())
+            .field("cutoff", &self.cutoff())
+            .field("q", &self.q())
+            .field("mix", &self.mix())
+            .finish()
+    }
+}
+
+/// A delay [`Effect`], repeating a sound as echoes that fade away.
+#[derive(Clone)]
+pub struct Delay(Arc<DelayState>);
+
+struct DelayState {
+    // in seconds
+    time: Param,
+    feedback: Param,
+    mix: Param,
+}
+
+impl Delay {
+    /// Creates a delay with the given time between echoes, and the feedback, from `0.0` to below
+    /// `1.0`, that each echo is quieter than the one before by.
+    pub fn new(time: Duration, feedback: f32) -> Self {
+        let delay = Delay(Arc::new(DelayState {
+            time: Param::new(0.),
+            feedback: Param::new(0.),
+            mix: Param::new(0.5),
+        }));
+        delay.set_time(time);
+        delay.set_feedback(feedback);
+        delay
+    }
+
+    /// Returns the time between echoes.
+    pub fn time(&self) -> Duration {
+        Duration::from_secs_f32(self.0.time.get())
+    }
+
+    /// Sets the time between echoes.
+    pub fn set_time(&self, time: Duration) {
+        self.0.time.set(time.as_secs_f32())
+    }
+
+    /// Returns the feedback.
+    pub fn feedback(&self) -> f32 {
+        self.0.feedback.get()
+    }
+
+    /// Sets the feedback, from `0.0` for a single echo to below `1.0`.
+    pub fn set_feedback(&self, feedback: f32) {
+        self.0.feedback.set(feedback.clamp(0., 0.99))
+    }
+
+    /// Returns how much of the echoes is heard, `0.5` by default.
+    pub fn mix(&self) -> f32 {
+        self.0.mix.get()
+    }
+
+    /// Sets how much of the echoes is heard, from `0.0` to `1.0`.
+    pub fn set_mix(&self, mix: f32) {
+        self.0.mix.set(mix.clamp(0., 1.))
+    }
+}
+
+impl PartialEq for Delay {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for Delay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Delay")
+            .field("time", &self.time())
+            .field("feedback", &self.feedback())
+            .field("mix", &self.mix())
+            .finish()
+    }
+}
+
+/// A reverb [`Effect`], making a sound ring like in a room, hall or cave.
+#[derive(Clone)]
+pub struct Reverb(Arc<ReverbState>);
+
+struct ReverbState {
+    room_size: Param,
+    damping: Param,
+    mix: Param,
+}
+
+impl Reverb {
+    /// Creates a reverb with the given room size and damping, both from `0.0` to `1.0`.
+    pub fn new(room_size: f32, damping: f32) -> Self {
+        let reverb = Reverb(Arc::new(ReverbState {
+            room_size: Param::new(0.),
+            damping: Param::new(0.),
+            mix: Param::new(0.3),
+        }));
+        reverb.set_room_size(room_size);
+        reverb.set_damping(damping);
+        reverb
+    }
+
+    /// Returns the room size.
+    pub fn room_size(&self) -> f32 {
+        self.0.room_size.get()
+    }
+
+    /// Sets the room size, from `0.0` to `1.0`; larger rooms ring longer.
+    pub fn set_room_size(&self, room_size: f32) {
+        self.0.room_size.set(room_size.clamp(0., 1.))
+    }
+
+    /// Returns the damping.
+    pub fn damping(&self) -> f32 {
+        self.0.damping.get()
+    }
+
+    /// Sets the damping, from `0.0` to `1.0`; more damping makes the high frequencies of the
+    /// reverb fade faster, as with soft walls.
+    pub fn set_damping(&self, damping: f32) {
+        self.0.damping.set(damping.clamp(0., 1.))
+    }
+
+    /// Returns how much of the reverb is heard, `0.3` by default.
+    pub fn mix(&self) -> f32 {
+        self.0.mix.get()
+    }
+
+    /// Sets how much of the reverb is heard, from `0.0` to `1.0`.
+    pub fn set_mix(&self, mix: f32) {
+        self.0.mix.set(mix.clamp(0., 1.))
+    }
+}
+
+impl PartialEq for Reverb {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for Reverb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Reverb")
+            .field("room_size", &self.room_size())
+            .field("damping", &self.damping())
+            .field("mix", &self.mix())
+            .finish()
+    }
+}
+
+/// A distortion [`Effect`], amplifying a sound and clipping it softly.
+#[derive(Clone)]
+pub struct Distortion(Arc<DistortionState>);
+
+struct DistortionState {
+    drive: Param,
+    mix: Param,
+}
+
+impl Distortion {
+    /// Creates a distortion with the given drive, the amplification before clipping; `1.0` and
+    /// above distort noticeably.
+    pub fn new(drive: f32) -> Self {
+        let distortion = Distortion(Arc::new(DistortionState {
+            drive: Param::new(0.),
+            mix: Param::new(1.),
+        }));
+        distortion.set_drive(drive);
+        distortion
+    }
+
+    /// Returns the drive.
+    pub fn drive(&self) -> f32 {
+        self.0.drive.get()
+    }
+
+    /// Sets the drive.
+    pub fn set_drive(&self, drive: f32) {
+        self.0.drive.set(drive.max(0.))
+    }
+
+    /// Returns how much of the distorted sound is heard, `1.0` by default.
+    pub fn mix(&self) -> f32 {
+        self.0.mix.get()
+    }
+
+    /// Sets how much of the distorted sound is heard, from `0.0` to `1.0`.
+    pub fn set_mix(&self, mix: f32) {
+        self.0.mix.set(mix.clamp(0., 1.))
+    }
+}
+
+impl PartialEq for Distortion {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for Distortion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Distortion")
+            .field("drive", &self.drive())
+            .field("mix", &self.mix())
+            .finish()
+    }
+}
+
+/// The state of an effect while processing a sound.
+trait Process: Send {
+    /// Reads the parameters of the effect, at the start of each frame.
+    fn update(&mut self);
+
+    /// Returns the output of the effect for a sample of the given channel.
+    fn process(&mut self, sample: f32, channel: usize) -> f32;
+
+    /// Returns how long the effect keeps sounding after its input ends.
+    fn tail(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Returns whether the effect has nothing but silence left to play if its input ends.
+    fn is_silent(&self) -> bool {
+        true
+    }
+}
+
+struct Processor {
+    effect: Effect,
+    mix: f32,
+    process: Box<dyn Process>,
+}
+
+impl Processor {
+    fn update(&mut self) {
+        self.mix = self.effect.mix();
+        self.process.update();
+    }
+
+    fn process(&mut self, sample: f32, channel: usize) -> f32 {
+        let output = self.process.process(sample, channel);
+        sample + (output - sample) * self.mix
+    }
+}
+
+struct FilterProcess {
+    filter: Filter,
+    sample_rate: f32,
+    // cutoff and q the coefficients are for
+    params: (f32, f32),
+    // b0, b1, b2, a1 and a2, divided by a0
+    coefficients: [f32; 5],
+    // the last two inputs and outputs of each channel
+    history: Vec<[f32; 4]>,
+}
+
+impl Process for FilterProcess {
+    fn update(&mut self) {
+        let params = (self.filter.cutoff(), self.filter.q());
+        if params == self.params {
+            return;
+        }
+        self.params = params;
+
+        // from the Audio EQ Cookbook
+        let max = self.sample_rate * 0.49;
+        let cutoff = params.0.clamp(10f32.min(max), max);
+        let w0 = TAU * cutoff / self.sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2. * params.1.max(0.1));
+        let [b0, b1, b2] = match self.filter.kind() {
+            FilterKind::LowPass => [(1. - cos) / 2., 1. - cos, (1. - cos) / 2.],
+            FilterKind::HighPass => [(1. + cos) / 2., -(1. + cos), (1. + cos) / 2.],
+            FilterKind::BandPass => [alpha, 0., -alpha],
+        };
+        let a0 = 1. + alpha;
+        self.coefficients = [b0 / a0, b1 / a0, b2 / a0, -2. * cos / a0, (1. - alpha) / a0];
+    }
+
+    fn process(&mut self, sample: f32, channel: usize) -> f32 {
+        let [b0, b1, b2, a1, a2] = self.coefficients;
+        let [x1, x2, y1, y2] = &mut self.history[channel];
+        let output = b0 * sample + b1 * *x1 + b2 * *x2 - a1 * *y1 - a2 * *y2;
+        *x2 = *x1;
+        *x1 = sample;
+        *y2 = *y1;
+        *y1 = output;
+        output
+    }
+}
+
+struct DelayProcess {
+    delay: Delay,
+    sample_rate: f32,
+    channels: usize,
+    // a ring of frames, each written after being read `length` frames later
+    buffer: Vec<f32>,
+    frames: usize,
+    frame: usize,
+    length: usize,
+    feedback: f32,
+}
+
+impl Process for DelayProcess {
+    fn update(&mut self) {
+        self.feedback = self.delay.feedback();
+        self.length = ((self.delay.time().as_secs_f32() * self.sample_rate) as usize).max(1);
+        if self.length >= self.frames {
+            // grows the buffer, forgetting the echoes in it
+            self.frames = self.length + 1;
+            self.buffer = vec![0.; self.frames * self.channels];
+            self.frame = 0;
+        } else {
+            self.frame = (self.frame + 1) % self.frames;
+        }
+    }
+
+    fn process(&mut self, sample: f32, channel: usize) -> f32 {
+        let read = (self.frame + self.frames - self.length) % self.frames;
+        let echo = self.buffer[read * self.channels + channel];
+        self.buffer[self.frame * self.channels + channel] = sample + echo * self.feedback;
+        echo
+    }
+
+    fn tail(&self) -> Duration {
+        let echoes = if self.feedback > 0. {
+            SILENCE.ln() / self.feedback.ln()
+        } else {
+            1.
+        };
+        Duration::from_secs_f32((self.length as f32 * echoes / self.sample_rate).min(1e6))
+    }
+
+    fn is_silent(&self) -> bool {
+        self.buffer.iter().all(|sample| sample.abs() < SILENCE)
+    }
+}
+
+// comb and allpass lengths of Freeverb at 44.1 kHz, and the offset of the other channels
+const COMB_LENGTHS: [usize; 4] = [1116, 1188, 1277, 1356];
+const ALLPASS_LENGTHS: [usize; 2] = [556, 441];
+const STEREO_SPREAD: usize = 23;
+const REVERB_GAIN: f32 = 0.09;
+
+struct DelayLine {
+    buffer: Vec<f32>,
+    index: usize,
+    // state of the low-pass filter of a comb
+    filtered: f32,
+}
+
+impl DelayLine {
+    fn new(length: usize) -> Self {
+        DelayLine {
+            buffer: vec![0.; length.max(1)],
+            index: 0,
+            filtered: 0.,
+        }
+    }
+
+    fn comb(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.filtered = output * (1. - damping) + self.filtered * damping;
+        self.buffer[self.index] = input + self.filtered * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+
+    fn allpass(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = input + delayed * 0.5;
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+}
+
+struct ReverbProcess {
+    reverb: Reverb,
+    sample_rate: f32,
+    feedback: f32,
+    damping: f32,
+    // the combs and allpasses of each channel
+    combs: Vec<Vec<DelayLine>>,
+    allpasses: Vec<Vec<DelayLine>>,
+}
+
+impl ReverbProcess {
+    fn new(reverb: Reverb, channels: usize, sample_rate: f32) -> Self {
+        let scale = sample_rate / 44_100.;
+        let lines = |lengths: &[usize], channel: usize| {
+            lengths
+                .iter()
+                .map(|length| {
+                    DelayLine::new(((length + channel * STEREO_SPREAD) as f32 * scale) as usize)
+                })
+                .collect()
+        };
+        ReverbProcess {
+            reverb,
+            sample_rate,
+            feedback: 0.,
+            damping: 0.,
+            combs: (0..channels)
+                .map(|channel| lines(&COMB_LENGTHS, channel))
+                .collect(),
+            allpasses: (0..channels)
+                .map(|channel| lines(&ALLPASS_LENGTHS, channel))
+                .collect(),
+        }
+    }
+}
+
+impl Process for ReverbProcess {
+    fn update(&mut self) {
+        self.feedback = 0.7 + 0.28 * self.reverb.room_size();
+        self.damping = 0.4 * self.reverb.damping();
+    }
+
+    fn process(&mut self, sample: f32, channel: usize) -> f32 {
+        let input = sample * REVERB_GAIN;
+        let (feedback, damping) = (self.feedback, self.damping);
+        let combs = self.combs[channel]
+            .iter_mut()
+            .map(|comb| comb.comb(input, feedback, damping))
+            .sum();
+        self.allpasses[channel]
+            .iter_mut()
+            .fold(combs, |output, allpass| allpass.allpass(output))
+    }
+
+    fn tail(&self) -> Duration {
+        let longest = self.combs[0].iter().map(|comb| comb.buffer.len()).max();
+        let decays = SILENCE.ln() / self.feedback.ln();
+        Duration::from_secs_f32(longest.unwrap_or(0) as f32 * decays / self.sample_rate)
+    }
+
+    fn is_silent(&self) -> bool {
+        self.combs
+            .iter()
+            .chain(&self.allpasses)
+            .flatten()
+            .flat_map(|line| &line.buffer)
+            .all(|sample| sample.abs() < SILENCE)
+    }
+}
+
+struct DistortionProcess {
+    distortion: Distortion,
+    drive: f32,
+}
+
+impl Process for DistortionProcess {
+    fn update(&mut self) {
+        self.drive = self.distortion.drive();
+    }
+
+    fn process(&mut self, sample: f32, _channel: usize) -> f32 {
+        (sample * self.drive).tanh()
+    }
+}
+
+/// The effects of a source or of a bus, processing a sound in order.
+struct Chain {
+    effects: Vec<Effect>,
+    // for the effects of a bus, which may change while playing
+    bus: Option<(Bus, usize)>,
+    processors: Vec<Processor>,
+}
+
+impl Chain {
+    fn new(effects: Vec<Effect>) -> Self {
+        Chain {
+            effects,
+            bus: None,
+            processors: Vec::new(),
+        }
+    }
+
+    fn bus(bus: Bus) -> Self {
+        Chain {
+            effects: bus.effects(),
+            bus: Some((bus.clone(), bus.effects_version())),
+            processors: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, reformat: bool, channels: u16, sample_rate: u32) {
+        let mut changed = reformat;
+        if let Some((bus, version)) = &mut self.bus {
+            if bus.effects_version() != *version {
+                *version = bus.effects_version();
+                self.effects = bus.effects();
+                changed = true;
+            }
+        }
+        if changed {
+            self.processors = self
+                .effects
+                .iter()
+                .map(|effect| effect.processor(channels, sample_rate))
+                .collect();
+        }
+        for processor in &mut self.processors {
+            processor.update();
+        }
+    }
+}
+
+/// Applies the effects of a source, of its bus and of the master bus to its sound, and plays
+/// the tails of echoes and reverb once it ends.
+pub(crate) struct Effected<S> {
+    input: S,
+    chains: Vec<Chain>,
+    // format the effects process
+    channels: u16,
+    sample_rate: u32,
+    // index of the next sample within its frame
+    channel: u16,
+    // samples of silence left to process once the input has ended
+    tail: Option<u64>,
+}
+
+impl<S> Effected<S> {
+    pub fn new(input: S, effects: Vec<Effect>, master: Bus, bus: Option<Bus>) -> Self {
+        let mut chains = vec![Chain::new(effects)];
+        chains.extend(bus.map(Chain::bus));
+        chains.push(Chain::bus(master));
+        Effected {
+            input,
+            chains,
+            channels: 0,
+            sample_rate: 0,
+            channel: 0,
+            tail: None,
+        }
+    }
+
+    // the number of samples of the longest tail of the effects
+    fn tail_samples(&self) -> u64 {
+        let tail = self
+            .chains
+            .iter()
+            .flat_map(|chain| &chain.processors)
+            .filter(|processor| processor.mix > 0.)
+            .map(|processor| processor.process.tail())
+            .max()
+            .unwrap_or_default()
+            .min(MAX_TAIL);
+        let frames = tail.as_secs_f64() * f64::from(self.sample_rate);
+        frames.ceil() as u64 * u64::from(self.channels)
+    }
+
+    fn is_silent(&self) -> bool {
+        self.chains
+            .iter()
+            .flat_map(|chain| &chain.processors)
+            .filter(|processor| processor.mix > 0.)
+            .all(|processor| processor.process.is_silent())
+    }
+}
+
+impl<S> Iterator for Effected<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.channel == 0 {
+            let mut reformat = false;
+            if self.tail.is_none() {
+                let channels = self.input.channels().max(1);
+                let sample_rate = self.input.sample_rate();
+                reformat = (channels, sample_rate) != (self.channels, self.sample_rate);
+                self.channels = channels;
+                self.sample_rate = sample_rate;
+            }
+            for chain in &mut self.chains {
+                chain.update(reformat, self.channels, self.sample_rate);
+            }
+        }
+
+        // the tail ends early once the echoes have died away, e.g. after fading out
+        let check = matches!(self.tail, Some(remaining) if remaining % SILENCE_CHECK == 0);
+        if check && self.channel == 0 && self.is_silent() {
+            return None;
+        }
+
+        let mut sample = match &mut self.tail {
+            None => match self.input.next() {
+                Some(sample) => sample,
+                None if self.channel == 0 => {
+                    if self.is_silent() {
+                        return None;
+                    }
+                    self.tail = Some(self.tail_samples().checked_sub(1)?);
+                    0.
+                }
+                None => return None,
+            },
+            Some(remaining) => {
+                *remaining = remaining.checked_sub(1)?;
+                0.
+            }
+        };
+
+        let channel = usize::from(self.channel);
+        for processor in self
+            .chains
+            .iter_mut()
+            .flat_map(|chain| &mut chain.processors)
+        {
+            sample = processor.process(sample, channel);
+        }
+        self.channel = (self.channel + 1) % self.channels;
+        Some(sample)
+    }
+}
+
+impl<S> rodio::Source for Effected<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        match self.input.current_frame_len() {
+            // the tail may follow
+            Some(0) => None,
+            _ if self.tail.is_some() => None,
+            len => len,
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        if self.tail.is_some() {
+            self.channels
+        } else {
+            self.input.channels()
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        if self.tail.is_some() {
+            self.sample_rate
+        } else {
+            self.input.sample_rate()
+        }
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::Mixer;
+    use rodio::buffer::SamplesBuffer;
+
+    fn process(effect: impl Into<Effect>, samples: Vec<f32>) -> Vec<f32> {
+        let master = Mixer::new().master().clone();
+        let input = SamplesBuffer::new(1, 1000, samples);
+        Effected::new(input, vec![effect.into()], master, None).collect()
+    }
+
+    #[test]
+    fn effects() {
+        // a low-pass filter keeps a constant signal, and removes the highest frequency
+        let low_pass = Filter::low_pass(50.);
+        let constant = process(low_pass.clone(), vec![1.; 500]);
+        assert!((constant[499] - 1.).abs() < 1e-3);
+        let alternating: Vec<_> = (0..500).map(|i| [1., -1.][i % 2]).collect();
+        let output = process(low_pass.clone(), alternating.clone());
+        assert!(output[499].abs() < 1e-3);
+        low_pass.set_mix(0.);
+        assert_eq!(process(low_pass, alternating.clone()), alternating);
+
+        let high_pass = Filter::high_pass(50.);
+        assert!(process(high_pass.clone(), vec![1.; 500])[499].abs() < 1e-3);
+        assert!((process(high_pass, alternating)[499].abs() - 1.).abs() < 0.1);
+
+        // filters work at any sample rate, even below twice their lowest cutoff
+        let master = Mixer::new().master().clone();
+        let input = SamplesBuffer::new(1, 10, vec![1.; 4]);
+        let effects = vec![Filter::low_pass(50.).into()];
+        let output: Vec<_> = Effected::new(input, effects, master, None).collect();
+        assert!(output.len() == 4 && output.iter().all(|sample| sample.is_finite()));
+
+        // echoes every 2 ms, halving in volume, which play on after the sound ends
+        let delay = Delay::new(Duration::from_millis(2), 0.5);
+        delay.set_mix(1.);
+        let output = process(delay.clone(), vec![1.]);
+        assert_eq!(output[..7], [0., 0., 1., 0., 0.5, 0., 0.25]);
+        assert_eq!(output.len(), 21);
+        // there's no tail after silence, however long the echoes would ring
+        delay.set_feedback(0.99);
+        assert_eq!(process(delay, vec![0.; 10]).len(), 10);
+
+        let distortion = Distortion::new(2.);
+        assert_eq!(process(distortion, vec![0.5]), [1f32.tanh()]);
+
+        let reverb = Reverb::new(0.5, 0.5);
+        let output = process(reverb.clone(), vec![1.]);
+        assert!(output.len() > 1000);
+        assert!(output.iter().all(|sample| sample.abs() <= 1.));
+        // the tail ends once the reverb has died away, before the 10 s limit
+        reverb.set_room_size(1.);
+        let output = process(reverb, vec![1.]);
+        assert!(output.len() < 10_000);
+
+        // effects added to a bus apply to sounds that are already playing
+        let master = Mixer::new().master().clone();
+        let input = SamplesBuffer::new(1, 1000, vec![1.; 4]);
+        let mut effected = Effected::new(input, Vec::new(), master.clone(), None);
+        assert_eq!(effected.next(), Some(1.));
+        master.add_effect(Distortion::new(1.));
+        assert_eq!(effected.next(), Some(1f32.tanh()));
+        master.clear_effects();
+        assert_eq!(effected.next(), Some(1.));
+    }
+}
@@ -0,0 +1,198 @@
+use super::{AudioContext, SoundSource};
+use crate::{context::Has, GameResult};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Fades out `from` while fading in `to` from silence, over the same duration, such as when
+/// switching from the level music to the boss music.
+///
+/// `to` is played from the start, and `from` stops once it's faded out. Both fades happen on the
+/// audio thread, so nothing has to be updated every frame.
+///
+/// ```rust,no_run
+/// # use ggez::{audio::{self, Source}, Context, GameResult};
+/// # use std::time::Duration;
+/// # fn t(ctx: &mut Context, level: &mut Source, boss: &mut Source) -> GameResult {
+/// audio::crossfade(ctx, level, boss, Duration::from_secs(2))
+/// # }
+/// ```
+pub fn crossfade(
+    audio: &impl Has<AudioContext>,
+    from: &mut impl SoundSource,
+    to: &mut impl SoundSource,
+    duration: Duration,
+) -> GameResult {
+    from.fade_out(duration);
+    to.fade_to(0., Duration::ZERO);
+    to.play(audio)?;
+    to.fade_to(1., duration);
+    Ok(())
+}
+
+/// Volume of a source that changes over time on the audio thread, shared between the source and
+/// the sounds it plays.
+#[derive(Debug, Clone)]
+pub(crate) struct Fader(Arc<FadeState>);
+
+#[derive(Debug)]
+struct FadeState {
+    // all bits of `f32`s
+    volume: AtomicU32,
+    target: AtomicU32,
+    // change of the volume per second
+    rate: AtomicU32,
+    // whether to stop playing once the volume reaches 0
+    stop: AtomicBool,
+}
+
+impl Fader {
+    pub fn new() -> Self {
+        Fader(Arc::new(FadeState {
+            volume: AtomicU32::new(1.0f32.to_bits()),
+            target: AtomicU32::new(1.0f32.to_bits()),
+            rate: AtomicU32::new(0.0f32.to_bits()),
+            stop: AtomicBool::new(false),
+        }))
+    }
+
+    pub fn volume(&self) -> f32 {
+        load(&self.0.volume)
+    }
+
+    /// Changes the volume to `target` over `duration`, starting at the current volume.
+    pub fn fade_to(&self, target: f32, duration: Duration, stop: bool) {
+        let target = target.max(0.);
+        self.0.stop.store(stop, Ordering::Relaxed);
+        if duration.is_zero() {
+            store(&self.0.volume, target);
+        } else {
+            let distance = (target - self.volume()).abs();
+            store(&self.0.rate, distance / duration.as_secs_f32());
+        }
+        store(&self.0.target, target);
+    }
+
+    /// Restores the full volume after a fade out, before playing again.
+    pub fn reset_stopped(&self) {
+        if self.0.stop.swap(false, Ordering::Relaxed) {
+            store(&self.0.volume, 1.);
+            store(&self.0.target, 1.);
+        }
+    }
+}
+
+fn load(atomic: &AtomicU32) -> f32 {
+    f32::from_bits(atomic.load(Ordering::Relaxed))
+}
+
+fn store(atomic: &AtomicU32, value: f32) {
+    atomic.store(value.to_bits(), Ordering::Relaxed)
+}
+
+/// Applies the volume of a [`Fader`] to a sound, ending it once a fade out is done.
+pub(crate) struct Faded<S> {
+    input: S,
+    fader: Fader,
+    // volume of the current frame
+    volume: f32,
+    // index of the next sample within its frame
+    channel: u16,
+}
+
+impl<S> Faded<S> {
+    pub fn new(input: S, fader: Fader) -> Self {
+        Faded {
+            input,
+            volume: fader.volume(),
+            fader,
+            channel: 0,
+        }
+    }
+}
+
+impl<S> Iterator for Faded<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.channel == 0 {
+            let state = &self.fader.0;
+            let volume = load(&state.volume);
+            let target = load(&state.target);
+            let step = load(&state.rate) / self.input.sample_rate() as f32;
+            self.volume = if (target - volume).abs() <= step {
+                target
+            } else {
+                volume + step.copysign(target - volume)
+            };
+            store(&state.volume, self.volume);
+
+            if self.volume <= 0. && target <= 0. && state.stop.load(Ordering::Relaxed) {
+                return None;
+            }
+        }
+
+        let sample = self.input.next()?;
+        self.channel = (self.channel + 1) % self.input.channels().max(1);
+        Some(sample * self.volume)
+    }
+}
+
+impl<S> rodio::Source for Faded<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        // a fade out may end it early
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    #[test]
+    fn fades() {
+        let fader = Fader::new();
+        // 10 frames per second, 2 channels
+        let samples = SamplesBuffer::new(2, 10, vec![1.0f32; 40]);
+        let mut faded = Faded::new(samples, fader.clone());
+
+        assert_eq!(faded.next(), Some(1.));
+        assert_eq!(faded.next(), Some(1.));
+        fader.fade_to(0.5, Duration::from_millis(500), false);
+        let volumes: Vec<_> = faded.by_ref().take(12).step_by(2).collect();
+        for (volume, expected) in volumes.iter().zip([0.9, 0.8, 0.7, 0.6, 0.5, 0.5]) {
+            assert!((volume - expected).abs() < 1e-5, "{volumes:?}");
+        }
+
+        fader.fade_to(0., Duration::from_millis(200), true);
+        assert_eq!(faded.next(), Some(0.25));
+        assert_eq!(faded.next(), Some(0.25));
+        assert_eq!(faded.next(), None);
+
+        fader.reset_stopped();
+        assert_eq!(fader.volume(), 1.);
+    }
+}
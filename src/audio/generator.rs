@@ -0,0 +1,536 @@
+use crate::error::{GameError, GameResult};
+use std::{
+    f32::consts::TAU,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+/// Produces the samples of a sound while it plays, to synthesize sound effects and music in code
+/// rather than loading them from files; play one with
+/// [`Source::from_generator`](super::Source::from_generator).
+///
+/// Samples range from `-1.0` to `1.0` and are interleaved, so a stereo generator returns the left
+/// and then the right sample of each frame. Besides implementing this trait, generators can be
+/// made of closures with [`FnGenerator`], or of the built-in [`Oscillator`]s, and shaped with an
+/// [`Adsr`] envelope.
+///
+/// ```rust,no_run
+/// # use ggez::{audio::{Adsr, Generator, Oscillator, SoundSource, Source}, Context, GameResult};
+/// # use std::time::Duration;
+/// # fn t(ctx: &mut Context) -> GameResult {
+/// let blip = Oscillator::square(880.).with_envelope(Adsr::new(
+///     Duration::from_millis(5),
+///     Duration::from_millis(50),
+///     0.5,
+///     Duration::from_millis(50),
+///     Duration::from_millis(100),
+/// ));
+/// let mut sound = Source::from_generator(ctx, blip)?;
+/// sound.play(ctx)?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Generator: Send {
+    /// Returns the number of frames per second, which mustn't change while playing.
+    fn sample_rate(&self) -> u32;
+
+    /// Returns the number of channels, which mustn't change while playing; `1` by default.
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    /// Returns the next sample, or `None` once the sound is over.
+    fn next_sample(&mut self) -> Option<f32>;
+
+    /// Shapes the volume of the sound with the given envelope, ending it when the envelope ends.
+    fn with_envelope(self, envelope: Adsr) -> Enveloped<Self>
+    where
+        Self: Sized,
+    {
+        Enveloped {
+            input: self,
+            envelope,
+            frame: 0,
+            channel: 0,
+            level: 0.,
+        }
+    }
+}
+
+impl Generator for Box<dyn Generator> {
+    fn sample_rate(&self) -> u32 {
+        (**self).sample_rate()
+    }
+
+    fn channels(&self) -> u16 {
+        (**self).channels()
+    }
+
+    fn next_sample(&mut self) -> Option<f32> {
+        (**self).next_sample()
+    }
+}
+
+/// A [`Generator`] calling a closure for each sample.
+///
+/// The closure is cloned along with the generator each time it plays, so any state it captures
+/// by value starts over as well.
+///
+/// ```rust
+/// # use ggez::audio::FnGenerator;
+/// // a second of a 440 Hz sine wave
+/// let mut frame = 0;
+/// let generator = FnGenerator::new(44_100, 1, move || {
+///     frame += 1;
+///     let time = frame as f32 / 44_100.;
+///     (frame <= 44_100).then(|| (time * 440. * std::f32::consts::TAU).sin())
+/// });
+/// ```
+#[derive(Clone)]
+pub struct FnGenerator<F> {
+    sample_rate: u32,
+    channels: u16,
+    f: F,
+}
+
+impl<F> FnGenerator<F>
+where
+    F: FnMut() -> Option<f32> + Send,
+{
+    /// Creates a generator with the given sample rate and number of channels, returning the
+    /// samples returned by `f`.
+    pub fn new(sample_rate: u32, channels: u16, f: F) -> Self {
+        FnGenerator {
+            sample_rate,
+            channels,
+            f,
+        }
+    }
+}
+
+impl<F> Generator for FnGenerator<F>
+where
+    F: FnMut() -> Option<f32> + Send,
+{
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn next_sample(&mut self) -> Option<f32> {
+        (self.f)()
+    }
+}
+
+impl<F> fmt::Debug for FnGenerator<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FnGenerator")
+            .field("sample_rate", &self.sample_rate)
+            .field("channels", &self.channels)
+            .finish()
+    }
+}
+
+/// The shape of the wave of an [`Oscillator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Waveform {
+    /// A smooth sine wave.
+    Sine,
+    /// A square wave, alternating between full and negative full volume; see
+    /// [`Oscillator::with_duty_cycle`].
+    Square,
+    /// A sawtooth wave, rising steadily and dropping at the end of each period.
+    Saw,
+    /// A triangle wave, rising and falling steadily.
+    Triangle,
+    /// Random values, each held for one period; high frequencies sound like hiss, and low ones
+    /// like rumbling, such as for explosions.
+    Noise,
+}
+
+/// A mono [`Generator`] of a [`Waveform`] at a frequency, for retro style sound effects and
+/// music.
+///
+/// It plays forever, unless it's given a duration or an envelope.
+#[derive(Debug, Clone, Copy)]
+pub struct Oscillator {
+    waveform: Waveform,
+    frequency: f32,
+    amplitude: f32,
+    duty_cycle: f32,
+    // position within the current period, from 0 up to 1
+    phase: f32,
+    // state of the random number generator, and the current value of the noise
+    seed: u32,
+    noise: f32,
+    // frames left to play, if limited
+    remaining: Option<u64>,
+}
+
+impl Oscillator {
+    /// The sample rate of oscillators.
+    pub const SAMPLE_RATE: u32 = 44_100;
+
+    /// Creates an oscillator of the given waveform, with a frequency in Hz.
+    pub fn new(waveform: Waveform, frequency: f32) -> Self {
+        let mut oscillator = Oscillator {
+            waveform,
+            frequency,
+            amplitude: 1.,
+            duty_cycle: 0.5,
+            phase: 0.,
+            seed: 0x2545_f491,
+            noise: 0.,
+            remaining: None,
+        };
+        oscillator.next_noise();
+        oscillator
+    }
+
+    /// Creates a sine wave oscillator.
+    pub fn sine(frequency: f32) -> Self {
+        Oscillator::new(Waveform::Sine, frequency)
+    }
+
+    /// Creates a square wave oscillator.
+    pub fn square(frequency: f32) -> Self {
+        Oscillator::new(Waveform::Square, frequency)
+    }
+
+    /// Creates a sawtooth wave oscillator.
+    pub fn saw(frequency: f32) -> Self {
+        Oscillator::new(Waveform::Saw, frequency)
+    }
+
+    /// Creates a triangle wave oscillator.
+    pub fn triangle(frequency: f32) -> Self {
+        Oscillator::new(Waveform::Triangle, frequency)
+    }
+
+    /// Creates a noise oscillator, picking `frequency` random values per second.
+    pub fn noise(frequency: f32) -> Self {
+        Oscillator::new(Waveform::Noise, frequency)
+    }
+
+    /// Sets the volume of the wave, `1.0` by default.
+    #[must_use]
+    pub fn with_amplitude(mut self, amplitude: f32) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    /// Sets the part of each period a square wave is high, between `0.0` and `1.0`; `0.5` by
+    /// default, while smaller values sound thinner.
+    #[must_use]
+    pub fn with_duty_cycle(mut self, duty_cycle: f32) -> Self {
+        self.duty_cycle = duty_cycle.clamp(0., 1.);
+        self
+    }
+
+    /// Makes the oscillator stop after the given duration.
+    #[must_use]
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        let frames = duration.as_secs_f64() * f64::from(Self::SAMPLE_RATE);
+        self.remaining = Some(frames.round() as u64);
+        self
+    }
+
+    /// Returns the waveform of the oscillator.
+    pub fn waveform(&self) -> Waveform {
+        self.waveform
+    }
+
+    /// Returns the frequency of the oscillator in Hz.
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    fn next_noise(&mut self) {
+        // xorshift
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.noise = self.seed as f32 / u32::MAX as f32 * 2. - 1.;
+    }
+}
+
+impl Generator for Oscillator {
+    fn sample_rate(&self) -> u32 {
+        Self::SAMPLE_RATE
+    }
+
+    fn next_sample(&mut self) -> Option<f32> {
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.checked_sub(1)?;
+        }
+
+        let phase = self.phase;
+        let value = match self.waveform {
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Square if phase < self.duty_cycle => 1.,
+            Waveform::Square => -1.,
+            Waveform::Saw => phase * 2. - 1.,
+            Waveform::Triangle => 1. - (phase - 0.5).abs() * 4.,
+            Waveform::Noise => self.noise,
+        };
+
+        self.phase += self.frequency / Self::SAMPLE_RATE as f32;
+        if self.phase >= 1. {
+            self.phase = self.phase.fract();
+            if self.waveform == Waveform::Noise {
+                self.next_noise();
+            }
+        }
+        Some(value * self.amplitude)
+    }
+}
+
+/// An attack, decay, sustain and release envelope, shaping the volume of a sound over time.
+///
+/// The volume rises from silence to full during the attack, falls to the sustain level during
+/// the decay, stays there for the sustain time, and falls back to silence during the release.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adsr {
+    /// Time to rise to full volume.
+    pub attack: Duration,
+    /// Time to fall to the sustain level.
+    pub decay: Duration,
+    /// Volume during the sustain, from `0.0` to `1.0`.
+    pub sustain: f32,
+    /// Time the sustain level is held.
+    pub sustain_time: Duration,
+    /// Time to fall to silence.
+    pub release: Duration,
+}
+
+impl Adsr {
+    /// Creates an envelope.
+    pub fn new(
+        attack: Duration,
+        decay: Duration,
+        sustain: f32,
+        sustain_time: Duration,
+        release: Duration,
+    ) -> Self {
+        Adsr {
+            attack,
+            decay,
+            sustain,
+            sustain_time,
+            release,
+        }
+    }
+
+    /// Returns the length of the whole envelope.
+    pub fn duration(&self) -> Duration {
+        self.attack + self.decay + self.sustain_time + self.release
+    }
+
+    /// Returns the volume at the given time from the start of the envelope, which is `0.0` once
+    /// it's over.
+    pub fn level(&self, time: Duration) -> f32 {
+        let mut time = time.as_secs_f32();
+        let mut stages = [
+            (self.attack, 0., 1.),
+            (self.decay, 1., self.sustain),
+            (self.sustain_time, self.sustain, self.sustain),
+            (self.release, self.sustain, 0.),
+        ]
+        .into_iter();
+        stages
+            .find_map(|(duration, from, to)| {
+                let duration = duration.as_secs_f32();
+                if time < duration {
+                    Some(from + (to - from) * time / duration)
+                } else {
+                    time -= duration;
+                    None
+                }
+            })
+            .unwrap_or(0.)
+    }
+}
+
+/// A [`Generator`] with an [`Adsr`] envelope; see [`Generator::with_envelope`].
+#[derive(Debug, Clone)]
+pub struct Enveloped<G> {
+    input: G,
+    envelope: Adsr,
+    // index of the current frame, and of the next sample within it
+    frame: u64,
+    channel: u16,
+    // volume of the current frame
+    level: f32,
+}
+
+impl<G: Generator> Generator for Enveloped<G> {
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn next_sample(&mut self) -> Option<f32> {
+        if self.channel == 0 {
+            let rate = f64::from(self.input.sample_rate().max(1));
+            let time = Duration::from_secs_f64(self.frame as f64 / rate);
+            if time >= self.envelope.duration() {
+                return None;
+            }
+            self.level = self.envelope.level(time);
+            self.frame += 1;
+        }
+
+        let sample = self.input.next_sample()?;
+        self.channel = (self.channel + 1) % self.input.channels().max(1);
+        Some(sample * self.level)
+    }
+}
+
+/// The generator of a source, created anew each time it plays so that it starts over.
+#[derive(Clone)]
+pub(crate) struct GeneratorTemplate(Arc<dyn Fn() -> Box<dyn Generator> + Send + Sync>);
+
+impl GeneratorTemplate {
+    pub fn new<F>(factory: F) -> GameResult<Self>
+    where
+        F: Fn() -> Box<dyn Generator> + Send + Sync + 'static,
+    {
+        let generator = factory();
+        if generator.sample_rate() == 0 || generator.channels() == 0 {
+            return Err(GameError::AudioError(String::from(
+                "A generator needs a sample rate and at least one channel",
+            )));
+        }
+        Ok(GeneratorTemplate(Arc::new(factory)))
+    }
+
+    /// Creates the generators by cloning the given one.
+    pub fn from_clone<G>(generator: G) -> GameResult<Self>
+    where
+        G: Generator + Clone + 'static,
+    {
+        // in a mutex, as generators needn't be `Sync`
+        let generator = Mutex::new(generator);
+        GeneratorTemplate::new(move || {
+            Box::new(
+                generator
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone(),
+            )
+        })
+    }
+
+    fn instance(&self) -> Box<dyn Generator> {
+        (self.0)()
+    }
+}
+
+/// Plays the generator of a source, starting it over once it's done when repeating.
+pub(crate) struct Generated {
+    template: GeneratorTemplate,
+    current: Box<dyn Generator>,
+    repeat: bool,
+}
+
+impl Generated {
+    pub fn new(template: GeneratorTemplate, repeat: bool) -> Self {
+        Generated {
+            current: template.instance(),
+            template,
+            repeat,
+        }
+    }
+}
+
+impl Iterator for Generated {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        match self.current.next_sample() {
+            Some(sample) => Some(sample),
+            None if self.repeat => {
+                // stops if it's empty
+                self.current = self.template.instance();
+                self.current.next_sample()
+            }
+            None => None,
+        }
+    }
+}
+
+impl rodio::Source for Generated {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.current.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.current.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generators() {
+        let mut square = Oscillator::square(Oscillator::SAMPLE_RATE as f32 / 4.)
+            .with_duty_cycle(0.25)
+            .with_duration(Duration::from_secs_f64(6. / Oscillator::SAMPLE_RATE as f64));
+        let samples: Vec<_> = std::iter::from_fn(|| square.next_sample()).collect();
+        assert_eq!(samples, [1., -1., -1., -1., 1., -1.]);
+
+        let mut saw = Oscillator::saw(Oscillator::SAMPLE_RATE as f32 / 4.);
+        let samples: Vec<_> = std::iter::from_fn(|| saw.next_sample()).take(5).collect();
+        assert_eq!(samples, [-1., -0.5, 0., 0.5, -1.]);
+
+        let mut noise = Oscillator::noise(Oscillator::SAMPLE_RATE as f32 / 2.);
+        let samples: Vec<_> = std::iter::from_fn(|| noise.next_sample()).take(4).collect();
+        assert_eq!(samples[0], samples[1]);
+        assert_ne!(samples[1], samples[2]);
+        assert!(samples.iter().all(|sample| (-1. ..=1.).contains(sample)));
+
+        let millis = Duration::from_millis;
+        let adsr = Adsr::new(millis(100), millis(100), 0.5, millis(100), millis(200));
+        assert_eq!(adsr.duration(), millis(500));
+        for (time, level) in [(50, 0.5), (150, 0.75), (250, 0.5), (400, 0.25), (600, 0.)] {
+            assert!((adsr.level(millis(time)) - level).abs() < 1e-5, "{time}");
+        }
+
+        // 10 stereo frames per second
+        let ones = FnGenerator::new(10, 2, || Some(1.));
+        let envelope = Adsr::new(millis(200), millis(0), 1., millis(0), millis(0));
+        let template = GeneratorTemplate::from_clone(ones.with_envelope(envelope)).unwrap();
+        let once: Vec<_> = Generated::new(template.clone(), false).collect();
+        assert_eq!(once, [0., 0., 0.5, 0.5]);
+        let repeated: Vec<_> = Generated::new(template, true).take(6).collect();
+        assert_eq!(repeated, [0., 0., 0.5, 0.5, 0., 0.]);
+
+        assert!(GeneratorTemplate::from_clone(FnGenerator::new(0, 1, || None)).is_err());
+
+        // generators that can't be cloned are created by a function instead
+        let template = GeneratorTemplate::new(|| {
+            let mut samples = vec![0.5, 1.];
+            Box::new(FnGenerator::new(1, 1, move || samples.pop()))
+        })
+        .unwrap();
+        let repeated: Vec<_> = Generated::new(template, true).take(5).collect();
+        assert_eq!(repeated, [1., 0.5, 1., 0.5, 1.]);
+    }
+}
@@ -0,0 +1,325 @@
+use super::Effect;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::Duration,
+};
+
+/// Groups sources so that their volume can be changed, and they can be muted or paused, together;
+/// see [`Mixer`].
+///
+/// A bus is a cheap handle to state shared with the audio thread, so changes to it affect
+/// sources that are already playing. Cloning it returns another handle to the same bus.
+#[derive(Clone)]
+pub struct Bus(Arc<BusState>);
+
+struct BusState {
+    name: String,
+    // bits of an `f32`
+    volume: AtomicU32,
+    muted: AtomicBool,
+    paused: AtomicBool,
+    effects: Mutex<Vec<Effect>>,
+    // changed along with the effects, for playing sources to notice
+    effects_version: AtomicUsize,
+}
+
+impl Bus {
+    fn new(name: &str) -> Self {
+        Bus(Arc::new(BusState {
+            name: name.to_owned(),
+            volume: AtomicU32::new(1.0f32.to_bits()),
+            muted: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            effects: Mutex::new(Vec::new()),
+            effects_version: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Returns the name of the bus.
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// Returns the volume of the bus, `1.0` by default.
+    pub fn volume(&self) -> f32 {
+        f32::from_bits(self.0.volume.load(Ordering::Relaxed))
+    }
+
+    /// Sets the volume of the bus, which multiplies the volume of each of its sources.
+    pub fn set_volume(&self, volume: f32) {
+        self.0
+            .volume
+            .store(volume.max(0.).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Returns whether the bus is muted.
+    pub fn is_muted(&self) -> bool {
+        self.0.muted.load(Ordering::Relaxed)
+    }
+
+    /// Mutes or unmutes the bus. Muted sources keep playing silently, unlike paused ones.
+    pub fn set_muted(&self, muted: bool) {
+        self.0.muted.store(muted, Ordering::Relaxed);
+    }
+
+    /// Returns whether the bus is paused.
+    pub fn is_paused(&self) -> bool {
+        self.0.paused.load(Ordering::Relaxed)
+    }
+
+    /// Pauses all sources of the bus, such as the sound effects while a pause menu is open.
+    ///
+    /// This doesn't change whether the sources themselves are paused; they continue where they
+    /// were once the bus is resumed.
+    pub fn pause(&self) {
+        self.0.paused.store(true, Ordering::Relaxed);
+    }
+
+    /// Resumes the sources paused with [`Bus::pause`].
+    pub fn resume(&self) {
+        self.0.paused.store(false, Ordering::Relaxed);
+    }
+
+    /// Adds an effect to the sound of all sources of the bus, after the effects before it.
+    ///
+    /// Unlike the effects of a source, this also applies to sources that are already playing,
+    /// and so does removing effects. Each source is processed on its own, so a
+    /// [`Distortion`](super::Distortion) distorts each source rather than all of them mixed.
+    pub fn add_effect(&self, effect: impl Into<Effect>) {
+        self.0
+            .effects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(effect.into());
+        let _ = self.0.effects_version.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Removes all effects from the bus.
+    pub fn clear_effects(&self) {
+        self.0
+            .effects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        let _ = self.0.effects_version.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the effects of the bus.
+    pub fn effects(&self) -> Vec<Effect> {
+        self.0
+            .effects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub(crate) fn effects_version(&self) -> usize {
+        self.0.effects_version.load(Ordering::Relaxed)
+    }
+
+    // volume the samples of the bus are multiplied by
+    fn gain(&self) -> f32 {
+        if self.is_muted() {
+            0.
+        } else {
+            self.volume()
+        }
+    }
+}
+
+impl PartialEq for Bus {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Bus")
+            .field("name", &self.name())
+            .field("volume", &self.volume())
+            .field("muted", &self.is_muted())
+            .field("paused", &self.is_paused())
+            .field("effects", &self.effects())
+            .finish()
+    }
+}
+
+/// The buses of an [`AudioContext`](super::AudioContext), along with the master bus that every
+/// source plays through.
+///
+/// The buses [`Mixer::MUSIC`], [`Mixer::SFX`], [`Mixer::VOICE`] and [`Mixer::UI`] always exist,
+/// and more can be added with [`Mixer::add_bus`]. Sources are assigned to a bus with
+/// [`SoundSource::set_bus`](super::SoundSource::set_bus). Effects added to the master bus apply
+/// to all sources.
+///
+/// ```rust,no_run
+/// # use ggez::{audio::{Mixer, SoundSource, Source}, Context, GameResult};
+/// # fn t(ctx: &mut Context) -> GameResult {
+/// let mut music = Source::new(ctx, "/music.ogg")?;
+/// music.set_bus(ctx.audio.mixer().bus(Mixer::MUSIC));
+/// music.play(ctx)?;
+///
+/// // in the options menu
+/// ctx.audio.mixer().bus(Mixer::MUSIC).unwrap().set_volume(0.3);
+/// ctx.audio.set_master_volume(0.8);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Mixer {
+    master: Bus,
+    buses: HashMap<String, Bus>,
+}
+
+impl Mixer {
+    /// Name of the bus for music.
+    pub const MUSIC: &'static str = "music";
+    /// Name of the bus for sound effects.
+    pub const SFX: &'static str = "sfx";
+    /// Name of the bus for voices and dialogue.
+    pub const VOICE: &'static str = "voice";
+    /// Name of the bus for sounds of the user interface.
+    pub const UI: &'static str = "ui";
+
+    pub(crate) fn new() -> Self {
+        let mut mixer = Mixer {
+            master: Bus::new("master"),
+            buses: HashMap::new(),
+        };
+        for name in [Self::MUSIC, Self::SFX, Self::VOICE, Self::UI] {
+            let _ = mixer.add_bus(name);
+        }
+        mixer
+    }
+
+    /// Returns the master bus, which all sources play through, whether they're assigned to
+    /// another bus or not.
+    pub fn master(&self) -> &Bus {
+        &self.master
+    }
+
+    /// Returns the bus with the given name, if there is one.
+    pub fn bus(&self, name: &str) -> Option<&Bus> {
+        self.buses.get(name)
+    }
+
+    /// Adds a bus with the given name, or returns the existing one of that name.
+    pub fn add_bus(&mut self, name: &str) -> &Bus {
+        self.buses
+            .entry(name.to_owned())
+            .or_insert_with(|| Bus::new(name))
+    }
+
+    /// Returns all buses but the master bus, in no particular order.
+    pub fn buses(&self) -> impl Iterator<Item = &Bus> {
+        self.buses.values()
+    }
+}
+
+/// Plays a source through the master bus and an optional other bus; the last step of every
+/// source before it's handed to its sink.
+pub(crate) struct Mixed<S> {
+    input: S,
+    master: Bus,
+    bus: Option<Bus>,
+    // index of the next sample within its frame; pausing only happens between frames
+    channel: u16,
+}
+
+impl<S> Mixed<S> {
+    pub fn new(input: S, master: Bus, bus: Option<Bus>) -> Self {
+        Mixed {
+            input,
+            master,
+            bus,
+            channel: 0,
+        }
+    }
+
+    fn paused(&self) -> bool {
+        self.master.is_paused() || matches!(&self.bus, Some(bus) if bus.is_paused())
+    }
+
+    fn gain(&self) -> f32 {
+        self.master.gain() * self.bus.as_ref().map_or(1., Bus::gain)
+    }
+}
+
+impl<S> Iterator for Mixed<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.channel == 0 && self.paused() {
+            // play silence, without consuming the input
+            return Some(0.);
+        }
+
+        let sample = self.input.next()?;
+        self.channel = (self.channel + 1) % self.input.channels().max(1);
+        Some(sample * self.gain())
+    }
+}
+
+impl<S> rodio::Source for Mixed<S>
+where
+    S: rodio::Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        // pausing makes it longer
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    #[test]
+    fn buses() {
+        let mut mixer = Mixer::new();
+        assert!(mixer.bus(Mixer::MUSIC).is_some());
+        let ambience = mixer.add_bus("ambience").clone();
+        assert_eq!(mixer.add_bus("ambience"), &ambience);
+        assert_eq!(mixer.buses().count(), 5);
+
+        let master = mixer.master().clone();
+        let samples = SamplesBuffer::new(2, 44100, vec![1.0f32; 8]);
+        let mut mixed = Mixed::new(samples, master.clone(), Some(ambience.clone()));
+        master.set_volume(0.5);
+        ambience.set_volume(0.5);
+        assert_eq!(mixed.next(), Some(0.25));
+
+        // pausing waits for the end of the frame, and doesn't consume samples
+        ambience.pause();
+        assert_eq!(mixed.next(), Some(0.25));
+        assert_eq!(mixed.next(), Some(0.));
+        assert_eq!(mixed.next(), Some(0.));
+        ambience.resume();
+        ambience.set_muted(true);
+        assert_eq!(mixed.next(), Some(0.));
+        ambience.set_muted(false);
+        assert_eq!(mixed.by_ref().count(), 5);
+    }
+}
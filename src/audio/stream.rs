@@ -0,0 +1,324 @@
+use super::{GeneratorTemplate, SoundData};
+use crate::{
+    error::{GameError, GameResult},
+    filesystem::File,
+};
+use rodio::Source;
+use std::{
+    fmt,
+    io::{self, Read, Seek, SeekFrom},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+// samples the next loop of a `LoopingDecoder` skips towards its start per sample played
+const PREROLL_SPEED: usize = 4;
+
+/// Where the sound of a source comes from.
+#[derive(Clone)]
+pub(crate) enum SourceData {
+    /// The whole file in memory.
+    Memory(SoundData),
+    /// An open file, read as the sound plays.
+    Stream(Arc<Mutex<File>>),
+    /// Samples generated as the sound plays.
+    Generator(GeneratorTemplate),
+}
+
+impl SourceData {
+    pub fn stream(file: File) -> Self {
+        SourceData::Stream(Arc::new(Mutex::new(file)))
+    }
+
+    /// Returns a decoder of the sound from its beginning.
+    pub fn decoder(&self) -> GameResult<rodio::Decoder<Reader>> {
+        let reader = match self {
+            SourceData::Memory(data) => Reader::Memory(io::Cursor::new(data.clone())),
+            SourceData::Stream(file) => Reader::Stream {
+                file: file.clone(),
+                position: 0,
+            },
+            SourceData::Generator(_) => {
+                return Err(GameError::AudioError(String::from(
+                    "Generated sounds aren't decoded",
+                )))
+            }
+        };
+        rodio::Decoder::new(reader).map_err(|_| {
+            GameError::AudioError(String::from("Could not decode the given audio data"))
+        })
+    }
+}
+
+impl fmt::Debug for SourceData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SourceData::Memory(data) => write!(f, "Memory({} bytes)", data.as_ref().len()),
+            SourceData::Stream(_) => write!(f, "Stream"),
+            SourceData::Generator(_) => write!(f, "Generator"),
+        }
+    }
+}
+
+/// Reads a `SourceData`; the readers of a stream share its file, each at its own position.
+pub(crate) enum Reader {
+    Memory(io::Cursor<SoundData>),
+    Stream {
+        file: Arc<Mutex<File>>,
+        position: u64,
+    },
+}
+
+fn poisoned() -> io::Error {
+    io::Error::other("audio file lock poisoned")
+}
+
+impl Read for Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Reader::Memory(cursor) => cursor.read(buf),
+            Reader::Stream { file, position } => {
+                let mut file = file.lock().map_err(|_| poisoned())?;
+                let _ = file.seek(SeekFrom::Start(*position))?;
+                let read = file.read(buf)?;
+                *position += read as u64;
+                Ok(read)
+            }
+        }
+    }
+}
+
+impl Seek for Reader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Reader::Memory(cursor) => cursor.seek(pos),
+            Reader::Stream { file, position } => {
+                let mut file = file.lock().map_err(|_| poisoned())?;
+                if let SeekFrom::Current(offset) = pos {
+                    let _ = file.seek(SeekFrom::Start(*position))?;
+                    *position = file.seek(SeekFrom::Current(offset))?;
+                } else {
+                    *position = file.seek(pos)?;
+                }
+                Ok(*position)
+            }
+        }
+    }
+}
+
+/// When repeating, the part of a sound that's played again and again; see
+/// [`SoundSource::set_loop_points`](super::SoundSource::set_loop_points).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct LoopPoints {
+    pub start: Duration,
+    pub end: Option<Duration>,
+}
+
+/// Decodes a sound, and when repeating, starts decoding it again from the loop start once it
+/// reaches the loop end.
+///
+/// To not stall the audio thread at the loop end, the decoder of the next loop skips to the loop
+/// start while the current one plays.
+pub(crate) struct LoopingDecoder {
+    data: SourceData,
+    repeat: bool,
+    points: LoopPoints,
+    current: rodio::Decoder<Reader>,
+    // samples of the current decoder played or skipped
+    played: u64,
+    // the decoder of the next loop, with the number of samples it has yet to skip
+    next: Option<(rodio::Decoder<Reader>, u64)>,
+}
+
+impl LoopingDecoder {
+    pub fn new(data: SourceData, repeat: bool, points: LoopPoints) -> GameResult<Self> {
+        let mut decoder = LoopingDecoder {
+            current: data.decoder()?,
+            data,
+            repeat,
+            points,
+            played: 0,
+            next: None,
+        };
+        decoder.prepare_next();
+        Ok(decoder)
+    }
+
+    fn samples(&self, duration: Duration) -> u64 {
+        let channels = u64::from(self.current.channels().max(1));
+        let frames = duration.as_secs_f64() * f64::from(self.current.sample_rate());
+        frames.round() as u64 * channels
+    }
+
+    fn end(&self) -> Option<u64> {
+        self.points.end.map(|end| self.samples(end))
+    }
+
+    fn prepare_next(&mut self) {
+        if self.repeat {
+            let skip = self.samples(self.points.start);
+            self.next = self.data.decoder().ok().map(|decoder| (decoder, skip));
+        }
+    }
+
+    // starts the next loop, returning `None` if there's no next loop or it has nothing to play
+    fn restart(&mut self) -> Option<()> {
+        let (mut decoder, skip) = self.next.take()?;
+        for _ in 0..skip {
+            let _ = decoder.next()?;
+        }
+        self.played = self.samples(self.points.start);
+        self.current = decoder;
+        self.prepare_next();
+
+        if matches!(self.end(), Some(end) if self.played >= end) {
+            // the loop is empty
+            self.next = None;
+            return None;
+        }
+        Some(())
+    }
+}
+
+impl Iterator for LoopingDecoder {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if let Some((decoder, skip)) = &mut self.next {
+            for _ in 0..PREROLL_SPEED.min(*skip as usize) {
+                if decoder.next().is_none() {
+                    break;
+                }
+                *skip -= 1;
+            }
+        }
+
+        let at_end = matches!(self.end(), Some(end) if self.played >= end);
+        let sample = if at_end { None } else { self.current.next() };
+        let sample = match sample {
+            Some(sample) => sample,
+            None => {
+                self.restart()?;
+                self.current.next()?
+            }
+        };
+        self.played += 1;
+        Some(sample)
+    }
+}
+
+impl rodio::Source for LoopingDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        let len = match self.current.current_frame_len() {
+            // the next loop may have another format
+            Some(0) if self.repeat => None,
+            len => len,
+        };
+        match self.end() {
+            Some(end) if end <= self.played => None,
+            Some(end) => {
+                let remaining = (end - self.played) as usize;
+                Some(len.map_or(remaining, |len| len.min(remaining)))
+            }
+            None => len,
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.current.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.current.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        if self.repeat {
+            None
+        } else if self.points.end.is_some() {
+            self.points.end
+        } else {
+            self.current.total_duration()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a mono 16 bit wav file with the given samples
+    fn wav(samples: &[i16]) -> SoundData {
+        let data_len = samples.len() as u32 * 2;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&10u32.to_le_bytes());
+        bytes.extend_from_slice(&20u32.to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        SoundData::from(bytes)
+    }
+
+    #[test]
+    fn loop_points() {
+        // 10 samples per second
+        let bytes = wav(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        let data = SourceData::Memory(bytes.clone());
+        let points = LoopPoints {
+            start: Duration::from_millis(200),
+            end: Some(Duration::from_millis(600)),
+        };
+
+        let once = LoopingDecoder::new(data.clone(), false, points).unwrap();
+        assert_eq!(once.collect::<Vec<_>>(), [0, 1, 2, 3, 4, 5]);
+
+        let looping = LoopingDecoder::new(data.clone(), true, points).unwrap();
+        assert_eq!(
+            looping.take(14).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4, 5, 2, 3, 4, 5, 2, 3, 4, 5]
+        );
+
+        let whole = LoopingDecoder::new(data, true, LoopPoints::default()).unwrap();
+        assert_eq!(whole.skip(6).take(4).collect::<Vec<_>>(), [6, 7, 0, 1]);
+
+        let file = File::VfsFile(Box::new(io::Cursor::new(bytes.as_ref().to_vec())));
+        let stream = LoopingDecoder::new(SourceData::stream(file), true, points).unwrap();
+        assert_eq!(
+            stream.take(10).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4, 5, 2, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn zip_stream() {
+        let zip_path = std::env::temp_dir().join("ggez_audio_stream_test.zip");
+        {
+            let mut zip_archive = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+            zip_archive
+                .start_file("sound.wav", zip::write::FileOptions::default())
+                .unwrap();
+            io::Write::write_all(&mut zip_archive, wav(&[0, 1, 2, 3]).as_ref()).unwrap();
+            let _file = zip_archive.finish().unwrap();
+        }
+        let zfs = crate::vfs::ZipFS::new(&zip_path).unwrap();
+        let file =
+            File::VfsFile(crate::vfs::VFS::open(&zfs, std::path::Path::new("/sound.wav")).unwrap());
+        let data = SourceData::stream(file);
+        let stream = LoopingDecoder::new(data, true, LoopPoints::default()).unwrap();
+        assert_eq!(
+            stream.take(10).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 0, 1, 2, 3, 0, 1]
+        );
+        std::fs::remove_file(&zip_path).unwrap();
+    }
+}
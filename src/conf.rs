@@ -378,6 +378,71 @@ impl From<NumSamples> for u8 {
     }
 }
 
+/// Configuration of the fixed timestep mode of [`event::run()`](../event/fn.run.html).
+///
+/// When enabled, [`EventHandler::fixed_update()`](../event/trait.EventHandler.html#method.fixed_update)
+/// is called `rate` times per second, independent of the frame rate, while `update` and `draw`
+/// are still called once per frame.
+///
+/// Defaults:
+///
+/// ```rust
+/// # use ggez::conf::*;
+/// # fn main() { assert_eq!(
+/// FixedTimestep {
+///     enabled: false,
+///     rate: 60,
+///     max_steps_per_frame: 5,
+/// }
+/// # , FixedTimestep::default()); }
+/// ```
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    smart_default::SmartDefault,
+    serde::Serialize,
+    serde::Deserialize,
+    PartialEq,
+    Eq,
+)]
+pub struct FixedTimestep {
+    /// Whether or not to call `fixed_update`.
+    #[default = false]
+    pub enabled: bool,
+    /// How many times per second to call `fixed_update`; 0 is treated as 1.
+    #[default = 60]
+    pub rate: u32,
+    /// The most `fixed_update` calls per frame. If the game falls further behind than this,
+    /// for example because the window was being dragged, the remaining time is skipped
+    /// instead of trying to catch up, which would only make the next frame take even longer.
+    #[default = 5]
+    pub max_steps_per_frame: u32,
+}
+
+impl FixedTimestep {
+    /// Set whether or not to call `fixed_update`.
+    #[must_use]
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Set how many times per second to call `fixed_update`; 0 is treated as 1.
+    #[must_use]
+    pub fn rate(mut self, rate: u32) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Set the most `fixed_update` calls per frame.
+    #[must_use]
+    pub fn max_steps_per_frame(mut self, max_steps_per_frame: u32) -> Self {
+        self.max_steps_per_frame = max_steps_per_frame;
+        self
+    }
+}
+
 /// A structure containing configuration data
 /// for the game engine.
 ///
@@ -390,6 +455,7 @@ impl From<NumSamples> for u8 {
 ///     window_mode: WindowMode::default(),
 ///     window_setup: WindowSetup::default(),
 ///     backend: Backend::default(),
+///     fixed_timestep: FixedTimestep::default(),
 /// }
 /// # , Conf::default()); }
 /// ```
@@ -403,6 +469,9 @@ pub struct Conf {
     pub window_setup: WindowSetup,
     /// Graphics backend configuration
     pub backend: Backend,
+    /// Fixed timestep game loop configuration
+    #[serde(default)]
+    pub fixed_timestep: FixedTimestep,
 }
 
 impl Conf {
@@ -441,6 +510,13 @@ impl Conf {
         self.backend = backend;
         self
     }
+
+    /// Sets the fixed timestep configuration
+    #[must_use]
+    pub fn fixed_timestep(mut self, fixed_timestep: FixedTimestep) -> Self {
+        self.fixed_timestep = fixed_timestep;
+        self
+    }
 }
 
 #[cfg(test)]
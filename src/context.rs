@@ -9,10 +9,12 @@ pub use winit;
 use crate::audio;
 use crate::conf;
 use crate::error::GameResult;
+use crate::event;
 use crate::filesystem::Filesystem;
 use crate::graphics;
 use crate::graphics::GraphicsContext;
 use crate::input;
+use crate::profiler;
 use crate::timer;
 
 /// A `Context` is an object that holds on to global resources.
@@ -52,6 +54,10 @@ pub struct Context {
     /// Gamepad input context.
     #[cfg(feature = "gamepad")]
     pub gamepad: input::gamepad::GamepadContext,
+    /// Input recording and replay state.
+    pub replay: event::ReplayContext,
+    /// Frame profiler state.
+    pub profiler: profiler::Profiler,
 
     /// The Conf object the Context was created with.
     /// It's here just so that we can see the original settings,
@@ -127,6 +133,13 @@ impl Has<GraphicsContext> for Context {
     }
 }
 
+impl Has<timer::TimeContext> for Context {
+    #[inline]
+    fn retrieve(&self) -> &timer::TimeContext {
+        &self.time
+    }
+}
+
 #[cfg(feature = "audio")]
 impl Has<audio::AudioContext> for Context {
     #[inline]
@@ -192,10 +205,42 @@ impl Context {
             mouse: input::mouse::MouseContext::new(),
             #[cfg(feature = "gamepad")]
             gamepad: input::gamepad::GamepadContext::new()?,
+            replay: event::ReplayContext::new(),
+            profiler: profiler::Profiler::new(),
         };
 
         Ok((ctx, events_loop))
     }
+
+    /// Tries to create a new headless Context using settings from the given [`Conf`](../conf/struct.Conf.html) object.
+    /// Usually called by [`ContextBuilder::build_headless()`](struct.ContextBuilder.html#method.build_headless).
+    fn from_conf_headless(conf: conf::Conf, fs: Filesystem) -> GameResult<Context> {
+        // headless contexts also run on machines without a sound card
+        #[cfg(feature = "audio")]
+        let audio_context = audio::AudioContext::new(&fs).unwrap_or_else(|e| {
+            warn!("Creating a headless context without audio: {e:?}");
+            audio::AudioContext::without_device(&fs)
+        });
+        let timer_context = timer::TimeContext::new();
+        let graphics_context = graphics::context::GraphicsContext::new_headless(&conf, &fs)?;
+
+        Ok(Context {
+            conf,
+            fs,
+            gfx: graphics_context,
+            continuing: true,
+            quit_requested: false,
+            time: timer_context,
+            #[cfg(feature = "audio")]
+            audio: audio_context,
+            keyboard: input::keyboard::KeyboardContext::new(),
+            mouse: input::mouse::MouseContext::new(),
+            #[cfg(feature = "gamepad")]
+            gamepad: input::gamepad::GamepadContext::new()?,
+            replay: event::ReplayContext::new(),
+            profiler: profiler::Profiler::new(),
+        })
+    }
 }
 
 use std::borrow::Cow;
@@ -250,10 +295,18 @@ impl ContextBuilder {
         self
     }
 
+    /// Sets the fixed timestep game loop settings.
+    #[must_use]
+    pub fn fixed_timestep(mut self, fixed_timestep: conf::FixedTimestep) -> Self {
+        self.conf.fixed_timestep = fixed_timestep;
+        self
+    }
+
     /// Sets all the config options, overriding any previous
     /// ones from [`window_setup()`](#method.window_setup),
-    /// [`window_mode()`](#method.window_mode), and
-    /// [`backend()`](#method.backend).  These are used as
+    /// [`window_mode()`](#method.window_mode),
+    /// [`backend()`](#method.backend), and
+    /// [`fixed_timestep()`](#method.fixed_timestep).  These are used as
     /// defaults and are overridden by any external config
     /// file found.
     #[must_use]
@@ -321,6 +374,29 @@ impl ContextBuilder {
 
     /// Build the `Context`.
     pub fn build(self) -> GameResult<(Context, winit::event_loop::EventLoop<()>)> {
+        let game_id = self.game_id.clone();
+        let (config, fs) = self.build_conf_and_fs()?;
+        Context::from_conf(game_id.as_ref(), config, fs)
+    }
+
+    /// Build a headless `Context`, which has no window and no event loop.
+    ///
+    /// Drawing still works as usual, but only into images; use [`Image::to_pixels`](crate::graphics::Image::to_pixels)
+    /// or [`Image::encode`](crate::graphics::Image::encode) to get at the results.
+    /// The size of the default frame image is taken from the configured [`WindowMode`](crate::conf::WindowMode).
+    /// This is mostly useful for running rendering code in tests or on machines without a display.
+    /// On machines without an audio device, the context still builds, but sound sources can't be
+    /// created in it.
+    ///
+    /// Since there is no event loop, [`event::run()`](crate::event::run) can't be used with a headless `Context`;
+    /// drive your game by calling [`GraphicsContext::begin_frame`](crate::graphics::GraphicsContext::begin_frame)
+    /// and [`GraphicsContext::end_frame`](crate::graphics::GraphicsContext::end_frame) yourself.
+    pub fn build_headless(self) -> GameResult<Context> {
+        let (config, fs) = self.build_conf_and_fs()?;
+        Context::from_conf_headless(config, fs)
+    }
+
+    fn build_conf_and_fs(self) -> GameResult<(conf::Conf, Filesystem)> {
         let fs = Filesystem::new(
             self.game_id.as_ref(),
             self.author.as_ref(),
@@ -342,10 +418,34 @@ impl ContextBuilder {
             self.conf
         };
 
-        Context::from_conf(self.game_id.as_ref(), config, fs)
+        Ok((config, fs))
     }
 }
 
+/// Side length of the frame of [`test_context`].
+#[cfg(test)]
+pub(crate) const TEST_SIZE: usize = 64;
+
+/// Creates a headless context with a square frame of [`TEST_SIZE`] pixels for tests, which
+/// should each use their own `game_id`, so they don't share files.
+#[cfg(test)]
+pub(crate) fn test_context(game_id: &str) -> Context {
+    let size = TEST_SIZE as f32;
+    ContextBuilder::new(game_id, "ggez")
+        .with_conf_file(false)
+        .window_mode(conf::WindowMode::default().dimensions(size, size))
+        .build_headless()
+        .unwrap()
+}
+
+/// Returns the RGB color at `x`, `y` of the pixels read back from the frame of a
+/// [`test_context`].
+#[cfg(test)]
+pub(crate) fn test_pixel(pixels: &[u8], x: usize, y: usize) -> [u8; 3] {
+    let i = (y * TEST_SIZE + x) * 4;
+    [pixels[i], pixels[i + 1], pixels[i + 2]]
+}
+
 /// Terminates the [`ggez::event::run()`](crate::event::run) loop _without_ requesting a
 /// [`quit_event`](crate::event::EventHandler::quit_event). [`Context.continuing`](struct.Context.html#structfield.continuing)
 /// is set to `false` and the loop breaks.
@@ -360,8 +460,8 @@ pub fn quit(ctx: &mut Context) {
 #[cfg(test)]
 mod tests {
     use crate::{
-        context::{Has, HasMut},
-        graphics::GraphicsContext,
+        context::{test_context, Has, HasMut},
+        graphics::{Canvas, Color, GraphicsContext, Image, ImageFormat},
         ContextBuilder,
     };
 
@@ -378,4 +478,23 @@ mod tests {
         takes_mut_gfx(&mut ctx);
         takes_mut_gfx(&mut ctx.gfx);
     }
+
+    // This needs a GPU adapter, but no display
+    #[test]
+    fn headless_render() {
+        let mut ctx = test_context("ggez_headless_test");
+        assert!(ctx.gfx.is_headless());
+        assert_eq!(ctx.gfx.drawable_size(), (64., 64.));
+
+        let image = Image::new_canvas_image(&ctx, ImageFormat::Rgba8UnormSrgb, 64, 64, 1);
+        ctx.gfx.begin_frame().unwrap();
+        Canvas::from_image(&ctx, image.clone(), Color::RED)
+            .finish(&mut ctx)
+            .unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        let pixels = image.to_pixels(&ctx).unwrap();
+        assert_eq!(pixels.len(), 64 * 64 * 4);
+        assert!(pixels.chunks(4).all(|p| p == [255, 0, 0, 255]));
+    }
 }
@@ -10,7 +10,7 @@
 //! source code for this module, or the [`eventloop`
 //! example](https://github.com/ggez/ggez/blob/master/examples/eventloop.rs).
 
-use winit::{self, dpi};
+use winit;
 
 /// A mouse button.
 pub use winit::event::{MouseButton, ScanCode};
@@ -31,23 +31,29 @@ pub mod winit_event {
 }
 #[cfg(feature = "gamepad")]
 pub use crate::input::gamepad::GamepadId;
-use crate::input::keyboard::{KeyCode, KeyInput, KeyMods};
-use crate::GameError;
+use crate::input::keyboard::{KeyCode, KeyInput};
+use crate::profiler::Phase;
+use crate::{GameError, GameResult};
 
-use self::winit_event::{
-    ElementState, Event, KeyboardInput, MouseScrollDelta, TouchPhase, WindowEvent,
-};
+use self::winit_event::{Event, TouchPhase, WindowEvent};
 /// `winit` event loop.
 pub use winit::event_loop::{ControlFlow, EventLoop};
 
 use crate::context::Context;
 
+mod replay;
+mod scene;
+pub use replay::*;
+pub use scene::*;
+
 /// Used in [`EventHandler::on_error()`](trait.EventHandler.html#method.on_error)
 /// to specify where an error originated
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ErrorOrigin {
     /// error originated in `update()`
     Update,
+    /// error originated in `fixed_update()`
+    FixedUpdate,
     /// error originated in `draw()`
     Draw,
     /// error originated in `mouse_button_down_event()`
@@ -103,6 +109,24 @@ where
     /// This should be where the game's logic takes place.
     fn update(&mut self, _ctx: &mut Context) -> Result<(), E>;
 
+    /// Called at a constant rate, independent of the frame rate, if the fixed timestep mode is
+    /// enabled in [`Conf::fixed_timestep`](../conf/struct.Conf.html#structfield.fixed_timestep).
+    /// This is the place for physics and other logic that should behave the same regardless of
+    /// how fast the game is running; each call should advance it by
+    /// [`ctx.time.fixed_delta()`](../timer/struct.TimeContext.html#method.fixed_delta).
+    ///
+    /// Depending on how long the last frame took, this is called zero or more times per frame,
+    /// right before [`update()`](#tymethod.update). In [`draw()`](#tymethod.draw),
+    /// [`ctx.time.interpolation_alpha()`](../timer/struct.TimeContext.html#method.interpolation_alpha)
+    /// tells how far the game is between two fixed updates.
+    ///
+    /// Since this may not be called every frame, input that only lasts for one frame, like
+    /// [`is_key_just_pressed()`](../input/keyboard/struct.KeyboardContext.html#method.is_key_just_pressed),
+    /// is better handled in `update`.
+    fn fixed_update(&mut self, _ctx: &mut Context) -> Result<(), E> {
+        Ok(())
+    }
+
     /// Called to do the drawing of your game.
     /// You probably want to start this with
     /// [`Canvas::from_frame`](../graphics/struct.Canvas.html#method.from_frame) and end it
@@ -331,153 +355,18 @@ where
                         return;
                     };
                 }
-                WindowEvent::ReceivedCharacter(ch) => {
-                    let res = state.text_input_event(ctx, ch);
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::TextInputEvent) {
-                        return;
-                    };
-                }
-                WindowEvent::ModifiersChanged(mods) => {
-                    ctx.keyboard.set_modifiers(KeyMods::from(mods))
-                }
-                WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            state: ElementState::Pressed,
-                            virtual_keycode: keycode,
-                            scancode,
-                            ..
-                        },
-                    ..
-                } => {
-                    let repeat = ctx.keyboard.is_key_repeated();
-                    let res = state.key_down_event(
-                        ctx,
-                        KeyInput {
-                            scancode,
-                            keycode,
-                            mods: ctx.keyboard.active_mods(),
-                        },
-                        repeat,
-                    );
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::KeyDownEvent) {
-                        return;
-                    };
-                }
-                WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            state: ElementState::Released,
-                            virtual_keycode: keycode,
-                            scancode,
-                            ..
-                        },
-                    ..
-                } => {
-                    let res = state.key_up_event(
-                        ctx,
-                        KeyInput {
-                            scancode,
-                            keycode,
-                            mods: ctx.keyboard.active_mods(),
-                        },
-                    );
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::KeyUpEvent) {
-                        return;
-                    };
-                }
-                WindowEvent::MouseWheel { delta, .. } => {
-                    let (x, y) = match delta {
-                        MouseScrollDelta::LineDelta(x, y) => (x, y),
-                        MouseScrollDelta::PixelDelta(pos) => {
-                            let scale_factor = ctx.gfx.window.scale_factor();
-                            let dpi::LogicalPosition { x, y } = pos.to_logical::<f32>(scale_factor);
-                            (x, y)
-                        }
-                    };
-                    let res = state.mouse_wheel_event(ctx, x, y);
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::MouseWheelEvent) {
+                event => {
+                    if ctx.replay.is_replaying() {
                         return;
-                    };
-                }
-                WindowEvent::MouseInput {
-                    state: element_state,
-                    button,
-                    ..
-                } => {
-                    let position = ctx.mouse.position();
-                    match element_state {
-                        ElementState::Pressed => {
-                            let res =
-                                state.mouse_button_down_event(ctx, button, position.x, position.y);
-                            if catch_error(
-                                ctx,
-                                res,
-                                state,
-                                control_flow,
-                                ErrorOrigin::MouseButtonDownEvent,
-                            ) {
-                                return;
-                            };
-                        }
-                        ElementState::Released => {
-                            let res =
-                                state.mouse_button_up_event(ctx, button, position.x, position.y);
-                            if catch_error(
-                                ctx,
-                                res,
-                                state,
-                                control_flow,
-                                ErrorOrigin::MouseButtonUpEvent,
-                            ) {
+                    }
+                    if let Some(input) = InputEvent::from_window_event(ctx, &event) {
+                        if let Err((origin, e)) = input.dispatch(ctx, state) {
+                            if catch_error(ctx, Err::<(), E>(e), state, control_flow, origin) {
                                 return;
-                            };
+                            }
                         }
                     }
                 }
-                WindowEvent::CursorMoved { .. } => {
-                    let position = ctx.mouse.position();
-                    let delta = ctx.mouse.last_delta();
-                    let res =
-                        state.mouse_motion_event(ctx, position.x, position.y, delta.x, delta.y);
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::MouseMotionEvent) {
-                        return;
-                    };
-                }
-                WindowEvent::Touch(touch) => {
-                    let res =
-                        state.touch_event(ctx, touch.phase, touch.location.x, touch.location.y);
-                    if catch_error(ctx, res, state, control_flow, ErrorOrigin::TouchEvent) {
-                        return;
-                    };
-                }
-                WindowEvent::CursorEntered { device_id: _ } => {
-                    let res = state.mouse_enter_or_leave(ctx, true);
-                    if catch_error(
-                        ctx,
-                        res,
-                        state,
-                        control_flow,
-                        ErrorOrigin::MouseEnterOrLeave,
-                    ) {
-                        return;
-                    }
-                }
-                WindowEvent::CursorLeft { device_id: _ } => {
-                    let res = state.mouse_enter_or_leave(ctx, false);
-                    if catch_error(
-                        ctx,
-                        res,
-                        state,
-                        control_flow,
-                        ErrorOrigin::MouseEnterOrLeave,
-                    ) {
-                        return;
-                    }
-                }
-                _x => {
-                    // trace!("ignoring window event {:?}", x);
-                }
             },
             Event::DeviceEvent { .. } => (),
             Event::Resumed => (),
@@ -489,7 +378,10 @@ where
                 // you include `timer_context.tick()` and
                 // `ctx.process_event()` calls.  These update ggez's
                 // internal state however necessary.
-                ctx.time.tick();
+                if tick(ctx, state).is_err() {
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
 
                 // Handle gamepad events if necessary.
                 #[cfg(feature = "gamepad")]
@@ -535,40 +427,9 @@ where
                     }
                 }
 
-                let res = state.update(ctx);
-                if catch_error(ctx, res, state, control_flow, ErrorOrigin::Update) {
-                    return;
-                };
-
-                if let Err(e) = ctx.gfx.begin_frame() {
-                    error!("Error on GraphicsContext::begin_frame(): {e:?}");
-                    eprintln!("Error on GraphicsContext::begin_frame(): {e:?}");
-                    *control_flow = ControlFlow::Exit;
-                }
-
-                if let Err(e) = state.draw(ctx) {
-                    error!("Error on EventHandler::draw(): {e:?}");
-                    eprintln!("Error on EventHandler::draw(): {e:?}");
-                    if state.on_error(ctx, ErrorOrigin::Draw, e) {
-                        *control_flow = ControlFlow::Exit;
-                        return;
-                    }
-                }
-
-                if let Err(e) = ctx.gfx.end_frame() {
-                    error!("Error on GraphicsContext::end_frame(): {e:?}");
-                    eprintln!("Error on GraphicsContext::end_frame(): {e:?}");
+                if frame(ctx, state).is_err() {
                     *control_flow = ControlFlow::Exit;
                 }
-
-                // reset the mouse delta for the next frame
-                // necessary because it's calculated cumulatively each cycle
-                ctx.mouse.reset_delta();
-
-                // Copy the state of the keyboard into the KeyboardContext
-                // and the mouse into the MouseContext
-                ctx.keyboard.save_keyboard_state();
-                ctx.mouse.save_mouse_state();
             }
             Event::RedrawRequested(_) => (),
             Event::RedrawEventsCleared => (),
@@ -577,11 +438,172 @@ where
     })
 }
 
-fn catch_error<T, E, S: 'static>(
+/// Plays back an input recording without a window, calling event callbacks on the given
+/// state object as [`run()`] would, until the recording ends or the game quits.
+///
+/// Frame times are taken from the recording, so this runs as fast as possible. Together with
+/// a headless context (see [`ContextBuilder::build_headless`](crate::ContextBuilder::build_headless))
+/// this allows running recorded sessions as regression tests:
+///
+/// ```rust,no_run
+/// # use ggez::{event::{self, EventHandler, InputRecording}, graphics::Canvas, Context, GameResult};
+/// # struct MyGame;
+/// # impl EventHandler for MyGame {
+/// #     fn update(&mut self, _ctx: &mut Context) -> GameResult { Ok(()) }
+/// #     fn draw(&mut self, _ctx: &mut Context) -> GameResult { Ok(()) }
+/// # }
+/// # fn t() -> GameResult {
+/// let mut ctx = ggez::ContextBuilder::new("my_game", "me").build_headless()?;
+/// let recording = InputRecording::from_path(&ctx, "/bug_report.toml")?;
+/// let mut game = MyGame;
+/// event::replay(&mut ctx, &mut game, recording)?;
+/// // ...check the state of the game...
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Gamepad input is neither recorded nor replayed. Returns an error if an event callback
+/// fails and [`EventHandler::on_error`] asks to quit.
+pub fn replay<S, E>(ctx: &mut Context, state: &mut S, recording: InputRecording) -> GameResult
+where
+    S: EventHandler<E>,
+    E: std::fmt::Debug,
+{
+    ctx.replay.start_replay(recording);
+    let res = replay_frames(ctx, state);
+    ctx.replay.stop_replay();
+    res
+}
+
+fn replay_frames<S, E>(ctx: &mut Context, state: &mut S) -> GameResult
+where
+    S: EventHandler<E>,
+    E: std::fmt::Debug,
+{
+    while ctx.replay.is_replaying() {
+        if ctx.quit_requested {
+            let res = state.quit_event(ctx);
+            ctx.quit_requested = false;
+            if let Ok(false) = res {
+                ctx.continuing = false;
+            } else if report_error(ctx, res, state, ErrorOrigin::QuitEvent) {
+                return Err(handler_error(ErrorOrigin::QuitEvent));
+            }
+        }
+        if !ctx.continuing {
+            break;
+        }
+
+        tick(ctx, state)?;
+        frame(ctx, state)?;
+    }
+    Ok(())
+}
+
+/// Advances the timer, and feeds the input of the next frame into the context
+/// and the state object if a recording is being played back.
+fn tick<S, E>(ctx: &mut Context, state: &mut S) -> GameResult
+where
+    S: EventHandler<E>,
+    E: std::fmt::Debug,
+{
+    let recorded = match ctx.replay.next_frame() {
+        Some(recorded) => recorded,
+        None => {
+            ctx.time.tick();
+            return Ok(());
+        }
+    };
+    ctx.time.tick_by(recorded.delta);
+    for input in recorded.events {
+        input.apply(ctx);
+        if let Err((origin, e)) = input.dispatch(ctx, state) {
+            if report_error(ctx, Err::<(), E>(e), state, origin) {
+                return Err(handler_error(origin));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the updates and the drawing of a frame, after all its input was handled.
+fn frame<S, E>(ctx: &mut Context, state: &mut S) -> GameResult
+where
+    S: EventHandler<E>,
+    E: std::fmt::Debug,
+{
+    ctx.replay.finish_frame(ctx.time.delta());
+    ctx.profiler.begin_frame();
+    ctx.gfx.set_gpu_timing(ctx.profiler.is_enabled());
+
+    let fixed = ctx.conf.fixed_timestep;
+    if fixed.enabled {
+        let steps = ctx
+            .time
+            .fixed_update_steps(fixed.rate, fixed.max_steps_per_frame);
+        for _ in 0..steps {
+            let res = state.fixed_update(ctx);
+            if report_error(ctx, res, state, ErrorOrigin::FixedUpdate) {
+                return Err(handler_error(ErrorOrigin::FixedUpdate));
+            };
+        }
+    }
+
+    let res = state.update(ctx);
+    if report_error(ctx, res, state, ErrorOrigin::Update) {
+        return Err(handler_error(ErrorOrigin::Update));
+    };
+    ctx.profiler.mark(Phase::Update);
+
+    let mut result = Ok(());
+    if let Err(e) = ctx.gfx.begin_frame() {
+        error!("Error on GraphicsContext::begin_frame(): {e:?}");
+        eprintln!("Error on GraphicsContext::begin_frame(): {e:?}");
+        result = Err(e);
+    }
+
+    if let Err(e) = state.draw(ctx) {
+        error!("Error on EventHandler::draw(): {e:?}");
+        eprintln!("Error on EventHandler::draw(): {e:?}");
+        if state.on_error(ctx, ErrorOrigin::Draw, e) {
+            return Err(handler_error(ErrorOrigin::Draw));
+        }
+    }
+    ctx.profiler.mark(Phase::Draw);
+
+    if let Err(e) = ctx.profiler.draw_overlay(&mut ctx.gfx) {
+        error!("Error drawing the profiler overlay: {e:?}");
+    }
+
+    if let Err(e) = ctx.gfx.end_frame() {
+        error!("Error on GraphicsContext::end_frame(): {e:?}");
+        eprintln!("Error on GraphicsContext::end_frame(): {e:?}");
+        result = Err(e);
+    }
+    ctx.profiler.mark(Phase::Present);
+    ctx.profiler.end_frame(&ctx.gfx);
+
+    // reset the mouse delta for the next frame
+    // necessary because it's calculated cumulatively each cycle
+    ctx.mouse.reset_delta();
+
+    // Copy the state of the keyboard into the KeyboardContext
+    // and the mouse into the MouseContext
+    ctx.keyboard.save_keyboard_state();
+    ctx.mouse.save_mouse_state();
+
+    result
+}
+
+fn handler_error(origin: ErrorOrigin) -> GameError {
+    GameError::EventLoopError(format!("stopped after an error in {origin:?}"))
+}
+
+/// Logs the error of an event callback, if any, and returns whether the event loop should stop.
+fn report_error<T, E, S>(
     ctx: &mut Context,
     event_result: Result<T, E>,
     state: &mut S,
-    control_flow: &mut ControlFlow,
     origin: ErrorOrigin,
 ) -> bool
 where
@@ -591,10 +613,25 @@ where
     if let Err(e) = event_result {
         error!("Error on EventHandler {origin:?}: {e:?}");
         eprintln!("Error on EventHandler {origin:?}: {e:?}");
-        if state.on_error(ctx, origin, e) {
-            *control_flow = ControlFlow::Exit;
-            return true;
-        }
+        return state.on_error(ctx, origin, e);
+    }
+    false
+}
+
+fn catch_error<T, E, S: 'static>(
+    ctx: &mut Context,
+    event_result: Result<T, E>,
+    state: &mut S,
+    control_flow: &mut ControlFlow,
+    origin: ErrorOrigin,
+) -> bool
+where
+    S: EventHandler<E>,
+    E: std::fmt::Debug,
+{
+    if report_error(ctx, event_result, state, origin) {
+        *control_flow = ControlFlow::Exit;
+        return true;
     }
     false
 }
@@ -603,48 +640,15 @@ where
 /// state it needs to, such as detecting window resizes.  If you are
 /// rolling your own event loop, you should call this on the events
 /// you receive before processing them yourself.
+///
+/// Input events are recorded if [`ReplayContext::start_recording`] was called,
+/// and ignored while a recording is being played back.
 pub fn process_event(ctx: &mut Context, event: &mut winit::event::Event<()>) {
     if let winit_event::Event::WindowEvent { event, .. } = event {
         match event {
             winit_event::WindowEvent::Resized(physical_size) => {
                 ctx.gfx.resize(*physical_size);
             }
-            winit_event::WindowEvent::CursorMoved {
-                position: physical_position,
-                ..
-            } => {
-                ctx.mouse
-                    .handle_move(physical_position.x as f32, physical_position.y as f32);
-            }
-            winit_event::WindowEvent::MouseInput { button, state, .. } => {
-                let pressed = match state {
-                    winit_event::ElementState::Pressed => true,
-                    winit_event::ElementState::Released => false,
-                };
-                ctx.mouse.set_button(*button, pressed);
-            }
-            winit_event::WindowEvent::ModifiersChanged(mods) => {
-                ctx.keyboard.set_modifiers(KeyMods::from(*mods))
-            }
-            winit_event::WindowEvent::KeyboardInput {
-                input:
-                    winit::event::KeyboardInput {
-                        state,
-                        scancode,
-                        virtual_keycode: keycode,
-                        ..
-                    },
-                ..
-            } => {
-                let pressed = match state {
-                    winit_event::ElementState::Pressed => true,
-                    winit_event::ElementState::Released => false,
-                };
-                ctx.keyboard.set_scancode(*scancode, pressed);
-                if let Some(key) = keycode {
-                    ctx.keyboard.set_key(*key, pressed);
-                }
-            }
             winit_event::WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
                 if !ctx.conf.window_mode.resize_on_scale_factor_change {
                     // actively set the new_inner_size to be the desired size
@@ -655,7 +659,14 @@ pub fn process_event(ctx: &mut Context, event: &mut winit::event::Event<()>) {
                     ]);
                 }
             }
-            _ => (),
+            event => {
+                // while replaying, input comes from the recording instead
+                if !ctx.replay.is_replaying() {
+                    if let Some(input) = InputEvent::from_window_event(ctx, event) {
+                        input.apply(ctx);
+                    }
+                }
+            }
         }
     };
 }
@@ -0,0 +1,534 @@
+use super::{winit_event, ErrorOrigin, EventHandler, MouseButton, TouchPhase};
+use crate::{
+    context::{Context, Has},
+    error::GameResult,
+    filesystem::Filesystem,
+    input::keyboard::{KeyCode, KeyInput, KeyMods, ScanCode},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, io, path, time::Duration};
+use winit::dpi;
+
+/// A single input event, as stored in an [`InputRecording`].
+///
+/// These are the window events that affect the input state of a [`Context`]
+/// or are passed on to an [`EventHandler`]. Gamepad input isn't recorded.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputEvent {
+    /// A key was pressed or released.
+    Key {
+        /// Whether the key was pressed, as opposed to released.
+        pressed: bool,
+        /// The scancode of the key.
+        scancode: ScanCode,
+        /// The keycode of the key, if there is one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        keycode: Option<KeyCode>,
+    },
+    /// The active keyboard modifiers changed.
+    Modifiers {
+        /// The new modifiers.
+        #[serde(with = "key_mods")]
+        mods: KeyMods,
+    },
+    /// A character was typed.
+    Text {
+        /// The character.
+        character: char,
+    },
+    /// The mouse cursor moved to the given position, in pixels.
+    MouseMoved {
+        /// X coordinate of the cursor.
+        x: f32,
+        /// Y coordinate of the cursor.
+        y: f32,
+    },
+    /// A mouse button was pressed or released.
+    MouseButton {
+        /// Whether the button was pressed, as opposed to released.
+        pressed: bool,
+        /// The button.
+        #[serde(with = "mouse_button")]
+        button: MouseButton,
+    },
+    /// The mouse wheel was scrolled.
+    MouseWheel {
+        /// Horizontal scroll amount.
+        x: f32,
+        /// Vertical scroll amount.
+        y: f32,
+    },
+    /// The mouse cursor entered or left the window.
+    MouseEnter {
+        /// Whether the cursor entered, as opposed to left, the window.
+        entered: bool,
+    },
+    /// A touch screen was touched.
+    Touch {
+        /// The phase of the touch.
+        phase: TouchPhase,
+        /// X coordinate of the touch.
+        x: f64,
+        /// Y coordinate of the touch.
+        y: f64,
+    },
+}
+
+impl InputEvent {
+    /// Converts a window event to an input event, if it is one.
+    pub(crate) fn from_window_event(
+        ctx: &Context,
+        event: &winit_event::WindowEvent,
+    ) -> Option<Self> {
+        use winit_event::{ElementState, MouseScrollDelta, WindowEvent};
+
+        Some(match event {
+            WindowEvent::KeyboardInput { input, .. } => InputEvent::Key {
+                pressed: input.state == ElementState::Pressed,
+                scancode: input.scancode,
+                keycode: input.virtual_keycode,
+            },
+            WindowEvent::ModifiersChanged(mods) => InputEvent::Modifiers {
+                mods: KeyMods::from(*mods),
+            },
+            WindowEvent::ReceivedCharacter(character) => InputEvent::Text {
+                character: *character,
+            },
+            WindowEvent::CursorMoved { position, .. } => InputEvent::MouseMoved {
+                x: position.x as f32,
+                y: position.y as f32,
+            },
+            WindowEvent::MouseInput { state, button, .. } => InputEvent::MouseButton {
+                pressed: *state == ElementState::Pressed,
+                button: *button,
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match *delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x, y),
+                    MouseScrollDelta::PixelDelta(pos) => {
+                        let scale_factor = ctx.gfx.window().scale_factor();
+                        let dpi::LogicalPosition { x, y } = pos.to_logical::<f32>(scale_factor);
+                        (x, y)
+                    }
+                };
+                InputEvent::MouseWheel { x, y }
+            }
+            WindowEvent::CursorEntered { .. } => InputEvent::MouseEnter { entered: true },
+            WindowEvent::CursorLeft { .. } => InputEvent::MouseEnter { entered: false },
+            WindowEvent::Touch(touch) => InputEvent::Touch {
+                phase: touch.phase,
+                x: touch.location.x,
+                y: touch.location.y,
+            },
+            _ => return None,
+        })
+    }
+
+    /// Updates the input state of the context, and records the event if recording.
+    pub(crate) fn apply(&self, ctx: &mut Context) {
+        match *self {
+            InputEvent::Key {
+                pressed,
+                scancode,
+                keycode,
+            } => {
+                ctx.keyboard.set_scancode(scancode, pressed);
+                if let Some(key) = keycode {
+                    ctx.keyboard.set_key(key, pressed);
+                }
+            }
+            InputEvent::Modifiers { mods } => ctx.keyboard.set_modifiers(mods),
+            InputEvent::MouseMoved { x, y } => ctx.mouse.handle_move(x, y),
+            InputEvent::MouseButton { pressed, button } => ctx.mouse.set_button(button, pressed),
+            _ => (),
+        }
+        if let Some(recorder) = &mut ctx.replay.recorder {
+            recorder.events.push(*self);
+        }
+    }
+
+    /// Calls the matching callback of the event handler.
+    pub(crate) fn dispatch<S, E>(
+        &self,
+        ctx: &mut Context,
+        state: &mut S,
+    ) -> Result<(), (ErrorOrigin, E)>
+    where
+        S: EventHandler<E>,
+        E: std::fmt::Debug,
+    {
+        let (origin, res) = match *self {
+            InputEvent::Key {
+                pressed: true,
+                scancode,
+                keycode,
+            } => {
+                let repeat = ctx.keyboard.is_key_repeated();
+                let input = KeyInput {
+                    scancode,
+                    keycode,
+                    mods: ctx.keyboard.active_mods(),
+                };
+                let res = state.key_down_event(ctx, input, repeat);
+                (ErrorOrigin::KeyDownEvent, res)
+            }
+            InputEvent::Key {
+                pressed: false,
+                scancode,
+                keycode,
+            } => {
+                let input = KeyInput {
+                    scancode,
+                    keycode,
+                    mods: ctx.keyboard.active_mods(),
+                };
+                (ErrorOrigin::KeyUpEvent, state.key_up_event(ctx, input))
+            }
+            InputEvent::Modifiers { .. } => return Ok(()),
+            InputEvent::Text { character } => (
+                ErrorOrigin::TextInputEvent,
+                state.text_input_event(ctx, character),
+            ),
+            InputEvent::MouseMoved { .. } => {
+                let position = ctx.mouse.position();
+                let delta = ctx.mouse.last_delta();
+                let res = state.mouse_motion_event(ctx, position.x, position.y, delta.x, delta.y);
+                (ErrorOrigin::MouseMotionEvent, res)
+            }
+            InputEvent::MouseButton { pressed, button } => {
+                let position = ctx.mouse.position();
+                if pressed {
+                    let res = state.mouse_button_down_event(ctx, button, position.x, position.y);
+                    (ErrorOrigin::MouseButtonDownEvent, res)
+                } else {
+                    let res = state.mouse_button_up_event(ctx, button, position.x, position.y);
+                    (ErrorOrigin::MouseButtonUpEvent, res)
+                }
+            }
+            InputEvent::MouseWheel { x, y } => (
+                ErrorOrigin::MouseWheelEvent,
+                state.mouse_wheel_event(ctx, x, y),
+            ),
+            InputEvent::MouseEnter { entered } => (
+                ErrorOrigin::MouseEnterOrLeave,
+                state.mouse_enter_or_leave(ctx, entered),
+            ),
+            InputEvent::Touch { phase, x, y } => {
+                (ErrorOrigin::TouchEvent, state.touch_event(ctx, phase, x, y))
+            }
+        };
+        res.map_err(|e| (origin, e))
+    }
+}
+
+/// The input events of a single frame of an [`InputRecording`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// How long the frame took.
+    #[serde(with = "nanos")]
+    pub delta: Duration,
+    /// The input events processed before the frame's update, in order.
+    #[serde(default)]
+    pub events: Vec<InputEvent>,
+}
+
+/// A recording of all input events and frame times of a game session, made with
+/// [`ReplayContext::start_recording`], to be played back with [`replay()`](crate::event::replay)
+/// or [`ReplayContext::start_replay`].
+///
+/// As long as the game doesn't rely on other sources of nondeterminism (such as random numbers
+/// with an unknown seed, or the wall clock), replaying a recording reproduces the session exactly.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct InputRecording {
+    frames: Vec<RecordedFrame>,
+}
+
+impl InputRecording {
+    /// Creates a new, empty recording.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a recording from the given frames.
+    pub fn from_frames(frames: impl IntoIterator<Item = RecordedFrame>) -> Self {
+        InputRecording {
+            frames: frames.into_iter().collect(),
+        }
+    }
+
+    /// Returns the recorded frames.
+    #[inline]
+    pub fn frames(&self) -> &[RecordedFrame] {
+        &self.frames
+    }
+
+    /// Returns the total duration of the recording.
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|f| f.delta).sum()
+    }
+
+    /// Loads a recording from the given `Read` object, which should contain TOML
+    /// as written by [`InputRecording::to_toml_file`].
+    pub fn from_toml_file<R: io::Read>(file: &mut R) -> GameResult<Self> {
+        let mut s = String::new();
+        let _ = file.read_to_string(&mut s)?;
+        Ok(toml::from_str(&s)?)
+    }
+
+    /// Saves the recording to the given `Write` object, formatted as TOML.
+    pub fn to_toml_file<W: io::Write>(&self, file: &mut W) -> GameResult {
+        let s = toml::to_vec(self)?;
+        file.write_all(&s)?;
+        Ok(())
+    }
+
+    /// Loads a recording from the given path in the [`Filesystem`].
+    pub fn from_path(fs: &impl Has<Filesystem>, path: impl AsRef<path::Path>) -> GameResult<Self> {
+        let mut file = fs.retrieve().open(path)?;
+        Self::from_toml_file(&mut file)
+    }
+
+    /// Saves the recording to the given path in the user data directory,
+    /// overwriting any file already there.
+    pub fn save(&self, fs: &impl Has<Filesystem>, path: impl AsRef<path::Path>) -> GameResult {
+        let mut file = fs.retrieve().create(path)?;
+        self.to_toml_file(&mut file)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Recorder {
+    frames: Vec<RecordedFrame>,
+    events: Vec<InputEvent>,
+}
+
+/// Recording and replaying of input, see [`InputRecording`].
+#[derive(Debug, Default)]
+pub struct ReplayContext {
+    pub(crate) recorder: Option<Recorder>,
+    replay: Option<VecDeque<RecordedFrame>>,
+}
+
+impl ReplayContext {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts recording all input events and frame times, discarding any recording in progress.
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(Recorder {
+            frames: Vec::new(),
+            events: Vec::new(),
+        });
+    }
+
+    /// Stops recording and returns what was recorded.
+    ///
+    /// Events that weren't followed by an update yet aren't included.
+    pub fn stop_recording(&mut self) -> Option<InputRecording> {
+        self.recorder
+            .take()
+            .map(|recorder| InputRecording::from_frames(recorder.frames))
+    }
+
+    /// Returns whether input is currently being recorded.
+    #[inline]
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Starts playing back a recording in [`event::run()`](crate::event::run), starting with the next frame.
+    ///
+    /// While replaying, live input is ignored and frame times are taken from the recording.
+    /// Once all frames were played, live input resumes.
+    pub fn start_replay(&mut self, recording: InputRecording) {
+        self.replay = Some(recording.frames.into());
+    }
+
+    /// Stops playing back a recording, resuming live input.
+    pub fn stop_replay(&mut self) {
+        self.replay = None;
+    }
+
+    /// Returns whether a recording is currently being played back.
+    #[inline]
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Returns how many frames of the current replay are left.
+    pub fn remaining_frames(&self) -> usize {
+        self.replay.as_ref().map_or(0, VecDeque::len)
+    }
+
+    pub(crate) fn next_frame(&mut self) -> Option<RecordedFrame> {
+        let replay = self.replay.as_mut()?;
+        let frame = replay.pop_front();
+        if replay.is_empty() {
+            self.replay = None;
+        }
+        frame
+    }
+
+    pub(crate) fn finish_frame(&mut self, delta: Duration) {
+        if let Some(recorder) = &mut self.recorder {
+            let events = std::mem::take(&mut recorder.events);
+            recorder.frames.push(RecordedFrame { delta, events });
+        }
+    }
+}
+
+mod nanos {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(d.as_nanos() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_nanos(u64::deserialize(d)?))
+    }
+}
+
+mod key_mods {
+    use crate::input::keyboard::KeyMods;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(mods: &KeyMods, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u8(mods.bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<KeyMods, D::Error> {
+        Ok(KeyMods::from_bits_truncate(u8::deserialize(d)?))
+    }
+}
+
+// `MouseButton::Other` is a newtype variant, which TOML can't represent
+mod mouse_button {
+    use super::MouseButton;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Other(u16),
+        Named(MouseButton),
+    }
+
+    pub fn serialize<S: Serializer>(button: &MouseButton, s: S) -> Result<S::Ok, S::Error> {
+        match *button {
+            MouseButton::Other(n) => Repr::Other(n),
+            named => Repr::Named(named),
+        }
+        .serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<MouseButton, D::Error> {
+        Ok(match Repr::deserialize(d)? {
+            Repr::Other(n) => MouseButton::Other(n),
+            Repr::Named(named) => named,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{context::test_context, event};
+
+    fn recording() -> InputRecording {
+        let key = |pressed| InputEvent::Key {
+            pressed,
+            scancode: 30,
+            keycode: Some(KeyCode::A),
+        };
+        let frame = |ms, events| RecordedFrame {
+            delta: Duration::from_millis(ms),
+            events,
+        };
+        InputRecording::from_frames([
+            frame(
+                16,
+                vec![InputEvent::MouseMoved { x: 10., y: 20. }, key(true)],
+            ),
+            frame(17, vec![]),
+            frame(
+                16,
+                vec![
+                    InputEvent::MouseButton {
+                        pressed: true,
+                        button: MouseButton::Other(4),
+                    },
+                    InputEvent::Modifiers {
+                        mods: KeyMods::SHIFT,
+                    },
+                    InputEvent::Text { character: 'A' },
+                ],
+            ),
+            frame(18, vec![key(false)]),
+        ])
+    }
+
+    #[derive(Default)]
+    struct Game {
+        updates: u32,
+        keys_down: u32,
+        held_frames: u32,
+    }
+
+    impl EventHandler for Game {
+        fn update(&mut self, ctx: &mut Context) -> GameResult {
+            self.updates += 1;
+            if ctx.keyboard.is_key_pressed(KeyCode::A) {
+                self.held_frames += 1;
+            }
+            Ok(())
+        }
+
+        fn draw(&mut self, _ctx: &mut Context) -> GameResult {
+            Ok(())
+        }
+
+        fn key_down_event(
+            &mut self,
+            _ctx: &mut Context,
+            _input: KeyInput,
+            _repeat: bool,
+        ) -> GameResult {
+            self.keys_down += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let recording = recording();
+        let mut file = Vec::new();
+        recording.to_toml_file(&mut file).unwrap();
+        let loaded = InputRecording::from_toml_file(&mut file.as_slice()).unwrap();
+        assert_eq!(loaded, recording);
+        assert_eq!(loaded.duration(), Duration::from_millis(67));
+    }
+
+    #[test]
+    fn headless_replay() {
+        let mut ctx = test_context("ggez_replay_test");
+        let mut game = Game::default();
+
+        // recording while replaying records the same session again
+        ctx.replay.start_recording();
+        event::replay(&mut ctx, &mut game, recording()).unwrap();
+        assert_eq!(ctx.replay.stop_recording(), Some(recording()));
+        assert!(!ctx.replay.is_replaying());
+
+        assert_eq!(game.updates, 4);
+        assert_eq!(game.keys_down, 1);
+        assert_eq!(game.held_frames, 3);
+        assert_eq!(ctx.time.delta(), Duration::from_millis(18));
+        assert_eq!(ctx.mouse.position(), mint::Point2 { x: 10., y: 20. });
+        assert!(ctx.mouse.button_pressed(MouseButton::Other(4)));
+        assert_eq!(ctx.keyboard.active_mods(), KeyMods::SHIFT);
+    }
+}
@@ -0,0 +1,603 @@
+use super::{ErrorOrigin, EventHandler, MouseButton, TouchPhase};
+use crate::{
+    context::Context,
+    graphics::{Canvas, Color, DrawParam, Quad},
+    input::keyboard::KeyInput,
+    GameError, GameResult,
+};
+use std::{fmt, time::Duration};
+
+#[cfg(feature = "gamepad")]
+use crate::input::gamepad::GamepadId;
+
+/// A single screen of a game, such as a menu, a pause screen or the game itself,
+/// managed by a [`SceneStack`].
+///
+/// Scenes receive the same callbacks as any other [`EventHandler`], but only while they're
+/// on top of the stack. To switch to another scene, store a [`SceneSwitch`] and hand it
+/// out in [`take_switch()`](#method.take_switch), which is checked after every callback.
+pub trait Scene<E = GameError>: EventHandler<E>
+where
+    E: fmt::Debug,
+{
+    /// Returns the scene switch this scene wants to happen, if any.
+    fn take_switch(&mut self) -> Option<SceneSwitch<E>> {
+        None
+    }
+
+    /// Whether the scene below this one should be drawn before this one, for example because
+    /// this is a menu shown on top of the game.
+    ///
+    /// Such scenes shouldn't clear the frame; create their canvas with
+    /// [`Canvas::from_frame(ctx, None)`](crate::graphics::Canvas::from_frame).
+    fn draw_previous(&self) -> bool {
+        false
+    }
+
+    /// Called when this scene becomes the top of the stack, either because it was pushed or
+    /// because the scene above it was popped.
+    fn activate(&mut self, _ctx: &mut Context) -> Result<(), E> {
+        Ok(())
+    }
+
+    /// Called when this scene stops being the top of the stack, either because it was popped
+    /// or because another scene was pushed on top of it.
+    fn deactivate(&mut self, _ctx: &mut Context) -> Result<(), E> {
+        Ok(())
+    }
+}
+
+/// An effect drawn on top of the scenes while a [`SceneStack`] switches between them.
+///
+/// Transitions are split in two halves: the old scene is shown during the first half,
+/// the new one during the second.
+pub trait Transition {
+    /// How long the whole transition takes.
+    fn duration(&self) -> Duration;
+
+    /// Draws the effect on top of the current frame. `progress` goes from 0.0 at the start
+    /// of the transition to 1.0 at its end, with the scenes switching at 0.5.
+    fn draw(&mut self, ctx: &mut Context, progress: f32) -> GameResult;
+}
+
+/// A [`Transition`] that fades the old scene out to a color, then fades the new one in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Fade {
+    color: Color,
+    duration: Duration,
+}
+
+impl Fade {
+    /// Creates a new fade through the given color, taking `duration` in total.
+    pub fn new(color: impl Into<Color>, duration: Duration) -> Self {
+        Fade {
+            color: color.into(),
+            duration,
+        }
+    }
+}
+
+impl Transition for Fade {
+    fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    fn draw(&mut self, ctx: &mut Context, progress: f32) -> GameResult {
+        let (w, h) = ctx.gfx.drawable_size();
+        let mut color = self.color;
+        color.a *= 1. - (2. * progress - 1.).abs();
+        let mut canvas = Canvas::from_frame(ctx, None);
+        canvas.draw(&Quad, DrawParam::new().scale([w, h]).color(color));
+        canvas.finish(ctx)
+    }
+}
+
+enum SwitchKind<E> {
+    Push(Box<dyn Scene<E>>),
+    Pop,
+    Replace(Box<dyn Scene<E>>),
+}
+
+/// A change to the scenes of a [`SceneStack`], optionally with a [`Transition`].
+pub struct SceneSwitch<E = GameError> {
+    kind: SwitchKind<E>,
+    transition: Option<Box<dyn Transition>>,
+}
+
+impl<E> SceneSwitch<E>
+where
+    E: fmt::Debug,
+{
+    /// Puts a new scene on top of the current one.
+    pub fn push(scene: impl Scene<E> + 'static) -> Self {
+        Self::new(SwitchKind::Push(Box::new(scene)))
+    }
+
+    /// Removes the current scene, going back to the one below it.
+    ///
+    /// Popping the last scene quits the game.
+    pub fn pop() -> Self {
+        Self::new(SwitchKind::Pop)
+    }
+
+    /// Replaces the current scene with a new one.
+    pub fn replace(scene: impl Scene<E> + 'static) -> Self {
+        Self::new(SwitchKind::Replace(Box::new(scene)))
+    }
+
+    fn new(kind: SwitchKind<E>) -> Self {
+        SceneSwitch {
+            kind,
+            transition: None,
+        }
+    }
+
+    /// Plays the given transition while switching.
+    #[must_use]
+    pub fn with_transition(mut self, transition: impl Transition + 'static) -> Self {
+        self.transition = Some(Box::new(transition));
+        self
+    }
+}
+
+impl<E> fmt::Debug for SceneSwitch<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            SwitchKind::Push(_) => "Push",
+            SwitchKind::Pop => "Pop",
+            SwitchKind::Replace(_) => "Replace",
+        };
+        write!(f, "<SceneSwitch: {kind}>")
+    }
+}
+
+struct ActiveTransition<E> {
+    effect: Box<dyn Transition>,
+    elapsed: Duration,
+    // applied halfway through
+    switch: Option<SwitchKind<E>>,
+}
+
+/// A stack of [`Scene`]s, itself an [`EventHandler`] to pass to [`event::run()`](crate::event::run).
+///
+/// Events go to the scene on top of the stack, except for resize events, which go to all scenes.
+///
+/// ```rust,no_run
+/// # use ggez::{event::{self, EventHandler, Scene, SceneStack, SceneSwitch}, input::keyboard::{KeyCode, KeyInput}, Context, GameResult};
+/// struct Menu {
+///     switch: Option<SceneSwitch>,
+/// }
+///
+/// impl EventHandler for Menu {
+///     fn update(&mut self, _ctx: &mut Context) -> GameResult { Ok(()) }
+///     fn draw(&mut self, _ctx: &mut Context) -> GameResult { Ok(()) }
+///
+///     fn key_down_event(&mut self, _ctx: &mut Context, input: KeyInput, _repeated: bool) -> GameResult {
+///         if input.keycode == Some(KeyCode::Escape) {
+///             self.switch = Some(SceneSwitch::pop());
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// impl Scene for Menu {
+///     fn take_switch(&mut self) -> Option<SceneSwitch> {
+///         self.switch.take()
+///     }
+/// }
+///
+/// # fn main() -> GameResult {
+/// let (ctx, event_loop) = ggez::ContextBuilder::new("scenes", "ggez").build()?;
+/// let scenes = SceneStack::new().with_scene(Menu { switch: None });
+/// event::run(ctx, event_loop, scenes)
+/// # }
+/// ```
+pub struct SceneStack<E = GameError> {
+    scenes: Vec<Box<dyn Scene<E>>>,
+    pending: Vec<SceneSwitch<E>>,
+    transition: Option<ActiveTransition<E>>,
+}
+
+impl<E> SceneStack<E>
+where
+    E: fmt::Debug + From<GameError>,
+{
+    /// Creates a new, empty stack.
+    pub fn new() -> Self {
+        SceneStack {
+            scenes: Vec::new(),
+            pending: Vec::new(),
+            transition: None,
+        }
+    }
+
+    /// Puts a scene on the stack, without calling [`Scene::activate`].
+    /// Meant for setting up the initial scenes.
+    #[must_use]
+    pub fn with_scene(mut self, scene: impl Scene<E> + 'static) -> Self {
+        self.scenes.push(Box::new(scene));
+        self
+    }
+
+    /// Returns how many scenes are on the stack.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    /// Returns whether the stack has no scenes on it.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    /// Returns whether a transition is currently playing.
+    #[inline]
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Queues a scene switch, as if the top scene had requested it.
+    ///
+    /// Switches are applied in order, each one waiting for the transition of the previous one.
+    pub fn switch(&mut self, switch: SceneSwitch<E>) {
+        self.pending.push(switch);
+    }
+
+    fn apply(&mut self, ctx: &mut Context, switch: SwitchKind<E>) -> Result<(), E> {
+        if let Some(top) = self.scenes.last_mut() {
+            top.deactivate(ctx)?;
+        }
+        match switch {
+            SwitchKind::Push(scene) => self.scenes.push(scene),
+            SwitchKind::Pop => {
+                let _ = self.scenes.pop();
+            }
+            SwitchKind::Replace(scene) => {
+                let _ = self.scenes.pop();
+                self.scenes.push(scene);
+            }
+        }
+        match self.scenes.last_mut() {
+            Some(top) => top.activate(ctx),
+            None => {
+                ctx.request_quit();
+                Ok(())
+            }
+        }
+    }
+
+    // Collects the switch requested by the top scene and starts the next pending switch,
+    // unless a transition is still playing.
+    fn process_switches(&mut self, ctx: &mut Context) -> Result<(), E> {
+        if let Some(switch) = self.scenes.last_mut().and_then(|top| top.take_switch()) {
+            self.pending.push(switch);
+        }
+        while self.transition.is_none() && !self.pending.is_empty() {
+            let SceneSwitch { kind, transition } = self.pending.remove(0);
+            match transition {
+                Some(effect) => {
+                    self.transition = Some(ActiveTransition {
+                        effect,
+                        elapsed: Duration::ZERO,
+                        switch: Some(kind),
+                    });
+                }
+                None => self.apply(ctx, kind)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn forward(
+        &mut self,
+        ctx: &mut Context,
+        f: impl FnOnce(&mut dyn Scene<E>, &mut Context) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if let Some(top) = self.scenes.last_mut() {
+            f(top.as_mut(), ctx)?;
+        }
+        self.process_switches(ctx)
+    }
+}
+
+impl<E> Default for SceneStack<E>
+where
+    E: fmt::Debug + From<GameError>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> fmt::Debug for SceneStack<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<SceneStack: {} scenes>", self.scenes.len())
+    }
+}
+
+impl<E> EventHandler<E> for SceneStack<E>
+where
+    E: fmt::Debug + From<GameError>,
+{
+    fn update(&mut self, ctx: &mut Context) -> Result<(), E> {
+        if let Some(transition) = &mut self.transition {
+            transition.elapsed += ctx.time.delta();
+            let duration = transition.effect.duration();
+            if transition.elapsed >= duration / 2 {
+                if let Some(switch) = transition.switch.take() {
+                    self.apply(ctx, switch)?;
+                }
+            }
+            if matches!(&self.transition, Some(t) if t.elapsed >= duration) {
+                self.transition = None;
+            }
+        }
+        self.forward(ctx, |scene, ctx| scene.update(ctx))
+    }
+
+    fn fixed_update(&mut self, ctx: &mut Context) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| scene.fixed_update(ctx))
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> Result<(), E> {
+        let mut first = self.scenes.len().saturating_sub(1);
+        while first > 0 && self.scenes[first].draw_previous() {
+            first -= 1;
+        }
+        for scene in &mut self.scenes[first..] {
+            scene.draw(ctx)?;
+        }
+
+        if let Some(transition) = &mut self.transition {
+            let duration = transition.effect.duration().as_secs_f32();
+            let progress = if duration > 0. {
+                (transition.elapsed.as_secs_f32() / duration).min(1.)
+            } else {
+                1.
+            };
+            transition.effect.draw(ctx, progress)?;
+        }
+        Ok(())
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| {
+            scene.mouse_button_down_event(ctx, button, x, y)
+        })
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| {
+            scene.mouse_button_up_event(ctx, button, x, y)
+        })
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        ctx: &mut Context,
+        x: f32,
+        y: f32,
+        dx: f32,
+        dy: f32,
+    ) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| {
+            scene.mouse_motion_event(ctx, x, y, dx, dy)
+        })
+    }
+
+    fn mouse_enter_or_leave(&mut self, ctx: &mut Context, entered: bool) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| scene.mouse_enter_or_leave(ctx, entered))
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, x: f32, y: f32) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| scene.mouse_wheel_event(ctx, x, y))
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        input: KeyInput,
+        repeated: bool,
+    ) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| scene.key_down_event(ctx, input, repeated))
+    }
+
+    fn key_up_event(&mut self, ctx: &mut Context, input: KeyInput) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| scene.key_up_event(ctx, input))
+    }
+
+    fn text_input_event(&mut self, ctx: &mut Context, character: char) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| scene.text_input_event(ctx, character))
+    }
+
+    fn touch_event(
+        &mut self,
+        ctx: &mut Context,
+        phase: TouchPhase,
+        x: f64,
+        y: f64,
+    ) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| scene.touch_event(ctx, phase, x, y))
+    }
+
+    #[cfg(feature = "gamepad")]
+    fn gamepad_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        btn: gilrs::Button,
+        id: GamepadId,
+    ) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| {
+            scene.gamepad_button_down_event(ctx, btn, id)
+        })
+    }
+
+    #[cfg(feature = "gamepad")]
+    fn gamepad_button_up_event(
+        &mut self,
+        ctx: &mut Context,
+        btn: gilrs::Button,
+        id: GamepadId,
+    ) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| {
+            scene.gamepad_button_up_event(ctx, btn, id)
+        })
+    }
+
+    #[cfg(feature = "gamepad")]
+    fn gamepad_axis_event(
+        &mut self,
+        ctx: &mut Context,
+        axis: gilrs::Axis,
+        value: f32,
+        id: GamepadId,
+    ) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| {
+            scene.gamepad_axis_event(ctx, axis, value, id)
+        })
+    }
+
+    fn focus_event(&mut self, ctx: &mut Context, gained: bool) -> Result<(), E> {
+        self.forward(ctx, |scene, ctx| scene.focus_event(ctx, gained))
+    }
+
+    fn quit_event(&mut self, ctx: &mut Context) -> Result<bool, E> {
+        match self.scenes.last_mut() {
+            Some(top) => top.quit_event(ctx),
+            None => Ok(false),
+        }
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) -> Result<(), E> {
+        for scene in &mut self.scenes {
+            scene.resize_event(ctx, width, height)?;
+        }
+        self.process_switches(ctx)
+    }
+
+    fn on_error(&mut self, ctx: &mut Context, origin: ErrorOrigin, e: E) -> bool {
+        match self.scenes.last_mut() {
+            Some(top) => top.on_error(ctx, origin, e),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::test_context;
+    use std::{cell::RefCell, rc::Rc};
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    struct Recorder {
+        name: &'static str,
+        log: Log,
+        next: Option<SceneSwitch>,
+    }
+
+    impl Recorder {
+        fn new(name: &'static str, log: &Log, next: Option<SceneSwitch>) -> Self {
+            Recorder {
+                name,
+                log: log.clone(),
+                next,
+            }
+        }
+
+        fn record(&self, what: &str) {
+            self.log.borrow_mut().push(format!("{} {what}", self.name));
+        }
+    }
+
+    impl EventHandler for Recorder {
+        fn update(&mut self, _ctx: &mut Context) -> GameResult {
+            self.record("update");
+            Ok(())
+        }
+
+        fn draw(&mut self, _ctx: &mut Context) -> GameResult {
+            self.record("draw");
+            Ok(())
+        }
+    }
+
+    impl Scene for Recorder {
+        fn take_switch(&mut self) -> Option<SceneSwitch> {
+            self.next.take()
+        }
+
+        fn draw_previous(&self) -> bool {
+            self.name == "pause"
+        }
+
+        fn activate(&mut self, _ctx: &mut Context) -> GameResult {
+            self.record("activate");
+            Ok(())
+        }
+
+        fn deactivate(&mut self, _ctx: &mut Context) -> GameResult {
+            self.record("deactivate");
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn switches() {
+        let mut ctx = test_context("ggez_scene_test");
+        let log = Log::default();
+
+        let pause = Recorder::new("pause", &log, Some(SceneSwitch::pop()));
+        let game = Recorder::new("game", &log, Some(SceneSwitch::push(pause)));
+        let mut scenes = SceneStack::new().with_scene(game);
+
+        scenes.update(&mut ctx).unwrap();
+        assert_eq!(scenes.len(), 2);
+        scenes.draw(&mut ctx).unwrap();
+        scenes.update(&mut ctx).unwrap();
+        assert_eq!(scenes.len(), 1);
+        assert!(!ctx.quit_requested);
+
+        scenes.switch(SceneSwitch::replace(Recorder::new("menu", &log, None)));
+        scenes.update(&mut ctx).unwrap();
+        scenes.draw(&mut ctx).unwrap();
+        scenes.switch(SceneSwitch::pop());
+        scenes.update(&mut ctx).unwrap();
+        assert!(scenes.is_empty());
+        assert!(ctx.quit_requested);
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                "game update",
+                "game deactivate",
+                "pause activate",
+                "game draw",
+                "pause draw",
+                "pause update",
+                "pause deactivate",
+                "game activate",
+                "game update",
+                "game deactivate",
+                "menu activate",
+                "menu draw",
+                "menu update",
+                "menu deactivate",
+            ]
+        );
+    }
+}
@@ -44,6 +44,7 @@ use std::{
     ops::DerefMut,
     path,
     sync::{Arc, Mutex},
+    time::SystemTime,
 };
 
 pub use crate::vfs::OpenOptions;
@@ -296,6 +297,17 @@ impl Filesystem {
             .unwrap_or(false)
     }
 
+    /// Returns when the file at the given path was last modified.
+    ///
+    /// Returns `None` if the file doesn't exist, or is in a zip file, which doesn't
+    /// keep track of modification times.
+    pub fn modified<P: AsRef<path::Path>>(&self, path: P) -> Option<SystemTime> {
+        self.vfs()
+            .metadata(path.as_ref())
+            .ok()
+            .and_then(|m| m.modified())
+    }
+
     /// Returns a list of all files and directories in the resource directory,
     /// in no particular order.
     ///
@@ -0,0 +1,440 @@
+use super::{Canvas, DrawParam, Drawable, GraphicsContext, Image, Rect};
+use crate::{context::Has, timer::TimeContext, GameError, GameResult};
+use std::{collections::HashMap, time::Duration};
+
+/// How an [`AnimationClip`] continues once it reaches its last frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum PlaybackMode {
+    /// Stop on the last frame.
+    Once,
+    /// Start again from the first frame.
+    #[default]
+    Loop,
+    /// Play the frames backwards down to the first one, then forwards again, and so on.
+    PingPong,
+}
+
+/// A single frame of an [`AnimationClip`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnimationFrame {
+    /// Region of the image shown during this frame, in UV coordinates (see [`DrawParam::src`]).
+    pub src: Rect,
+    /// How long this frame is shown.
+    pub duration: Duration,
+}
+
+/// A sequence of frames that can be played back by an [`Animation`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    frames: Vec<AnimationFrame>,
+    mode: PlaybackMode,
+}
+
+impl AnimationClip {
+    /// Creates a new looping clip from the given frames.
+    pub fn new(frames: impl IntoIterator<Item = AnimationFrame>) -> Self {
+        AnimationClip {
+            frames: frames.into_iter().collect(),
+            mode: PlaybackMode::Loop,
+        }
+    }
+
+    /// Creates a new looping clip from cells of a sprite sheet that is evenly divided into
+    /// `columns` by `rows` cells, each shown for `frame_duration`.
+    ///
+    /// `cells` are the indices of the cells to show, counted left to right and then top to bottom.
+    pub fn from_grid(
+        columns: u32,
+        rows: u32,
+        cells: impl IntoIterator<Item = u32>,
+        frame_duration: Duration,
+    ) -> Self {
+        let (w, h) = (1. / columns as f32, 1. / rows as f32);
+        Self::new(cells.into_iter().map(|cell| AnimationFrame {
+            src: Rect::new(
+                (cell % columns) as f32 * w,
+                (cell / columns) as f32 * h,
+                w,
+                h,
+            ),
+            duration: frame_duration,
+        }))
+    }
+
+    /// Sets how the clip continues once it reaches its last frame.
+    #[must_use]
+    pub fn mode(mut self, mode: PlaybackMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns the frames of this clip.
+    #[inline]
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    /// Returns the playback mode of this clip.
+    #[inline]
+    pub fn playback_mode(&self) -> PlaybackMode {
+        self.mode
+    }
+}
+
+/// Something that happened while advancing an [`Animation`].
+///
+/// Useful to synchronize sounds, hitboxes and the like with the animation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// The given frame of the given clip started showing.
+    FrameChanged {
+        /// Name of the playing clip.
+        clip: String,
+        /// Index of the new frame.
+        frame: usize,
+    },
+    /// A clip with [`PlaybackMode::Once`] reached the end of its last frame.
+    Finished {
+        /// Name of the clip that finished.
+        clip: String,
+    },
+}
+
+/// Playback state for a set of named [`AnimationClip`]s.
+///
+/// An `Animation` only keeps track of which frame should be shown; to draw it, use an [`AnimatedSprite`].
+#[derive(Debug, Clone)]
+pub struct Animation {
+    clips: HashMap<String, AnimationClip>,
+    current: Option<String>,
+    frame: usize,
+    elapsed: Duration,
+    backwards: bool,
+    playing: bool,
+    speed: f32,
+    events: Vec<AnimationEvent>,
+}
+
+impl Animation {
+    /// Creates a new animation without any clips.
+    pub fn new() -> Self {
+        Animation {
+            clips: HashMap::new(),
+            current: None,
+            frame: 0,
+            elapsed: Duration::ZERO,
+            backwards: false,
+            playing: false,
+            speed: 1.,
+            events: Vec::new(),
+        }
+    }
+
+    /// Adds a clip with the given name, replacing any clip with the same name.
+    #[must_use]
+    pub fn with_clip(mut self, name: impl Into<String>, clip: AnimationClip) -> Self {
+        self.add_clip(name, clip);
+        self
+    }
+
+    /// Adds a clip with the given name, replacing any clip with the same name.
+    pub fn add_clip(&mut self, name: impl Into<String>, clip: AnimationClip) {
+        let _ = self.clips.insert(name.into(), clip);
+    }
+
+    /// Returns the clip with the given name.
+    pub fn clip(&self, name: &str) -> Option<&AnimationClip> {
+        self.clips.get(name)
+    }
+
+    /// Starts playing the clip with the given name from its first frame.
+    ///
+    /// If that clip is already playing, this does nothing; use [`Animation::restart`] to start it over.
+    pub fn play(&mut self, name: &str) -> GameResult {
+        if self.current.as_deref() == Some(name) && self.playing {
+            return Ok(());
+        }
+        self.set_clip(name)
+    }
+
+    /// Starts playing the clip with the given name from its first frame, even if it is already playing.
+    pub fn restart(&mut self, name: &str) -> GameResult {
+        self.set_clip(name)
+    }
+
+    fn set_clip(&mut self, name: &str) -> GameResult {
+        if !self.clips.contains_key(name) {
+            return Err(GameError::ResourceLoadError(format!(
+                "no animation clip named {name:?}"
+            )));
+        }
+        self.current = Some(name.to_string());
+        self.frame = 0;
+        self.elapsed = Duration::ZERO;
+        self.backwards = false;
+        self.playing = true;
+        self.events.push(AnimationEvent::FrameChanged {
+            clip: name.to_string(),
+            frame: 0,
+        });
+        Ok(())
+    }
+
+    /// Pauses playback, keeping the current frame.
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Resumes playback after [`Animation::pause`].
+    pub fn resume(&mut self) {
+        self.playing = self.current.is_some();
+    }
+
+    /// Returns whether a clip is currently being played back.
+    ///
+    /// This is `false` when paused and after a [`PlaybackMode::Once`] clip has finished.
+    #[inline]
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Sets the playback speed multiplier; 1.0 is normal speed. Negative values are treated as 0.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.);
+    }
+
+    /// Returns the playback speed multiplier.
+    #[inline]
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Returns the name of the current clip.
+    #[inline]
+    pub fn current_clip(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// Returns the index of the current frame within the current clip.
+    #[inline]
+    pub fn current_frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Returns the source rectangle of the current frame, or the whole image if no clip is playing.
+    pub fn current_src(&self) -> Rect {
+        self.current
+            .as_ref()
+            .and_then(|name| self.clips.get(name))
+            .and_then(|clip| clip.frames.get(self.frame))
+            .map_or(Rect::one(), |frame| frame.src)
+    }
+
+    /// Advances the animation by the last frame's [`TimeContext::delta`].
+    ///
+    /// Call this once per `update`.
+    pub fn update(&mut self, time: &impl Has<TimeContext>) {
+        self.advance(time.retrieve().delta());
+    }
+
+    /// Advances the animation by the given amount of time.
+    pub fn advance(&mut self, dt: Duration) {
+        if !self.playing {
+            return;
+        }
+        let (name, clip) = match self
+            .current
+            .as_ref()
+            .and_then(|n| self.clips.get_key_value(n))
+        {
+            Some(current) => current,
+            None => return,
+        };
+        // zero-length clips would never advance
+        if clip.frames.iter().all(|f| f.duration.is_zero()) {
+            return;
+        }
+
+        self.elapsed += dt.mul_f32(self.speed);
+        while self.elapsed >= clip.frames[self.frame].duration {
+            self.elapsed -= clip.frames[self.frame].duration;
+
+            let last = clip.frames.len() - 1;
+            let next = match (clip.mode, self.backwards) {
+                (_, false) if self.frame < last => self.frame + 1,
+                (PlaybackMode::Once, _) => {
+                    self.playing = false;
+                    self.elapsed = Duration::ZERO;
+                    self.events
+                        .push(AnimationEvent::Finished { clip: name.clone() });
+                    return;
+                }
+                (PlaybackMode::Loop, _) => 0,
+                (PlaybackMode::PingPong, false) => {
+                    self.backwards = true;
+                    last.saturating_sub(1)
+                }
+                (PlaybackMode::PingPong, true) if self.frame > 0 => self.frame - 1,
+                (PlaybackMode::PingPong, true) => {
+                    self.backwards = false;
+                    last.min(1)
+                }
+            };
+
+            if next != self.frame {
+                self.frame = next;
+                self.events.push(AnimationEvent::FrameChanged {
+                    clip: name.clone(),
+                    frame: next,
+                });
+            }
+        }
+    }
+
+    /// Removes and returns all events that happened since the last call.
+    pub fn drain_events(&mut self) -> impl Iterator<Item = AnimationEvent> + '_ {
+        self.events.drain(..)
+    }
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An [`Image`] (usually a sprite sheet) drawn according to an [`Animation`].
+///
+/// ```rust,no_run
+/// # use ggez::{graphics::{self, AnimatedSprite, Animation, AnimationClip, PlaybackMode}, GameResult, Context};
+/// # use std::time::Duration;
+/// # fn t(ctx: &mut Context, canvas: &mut graphics::Canvas) -> GameResult {
+/// let sheet = graphics::Image::from_path(ctx, "/hero.png")?;
+/// let frame = Duration::from_millis(100);
+/// let animation = Animation::new()
+///     .with_clip("walk", AnimationClip::from_grid(4, 2, 0..4, frame))
+///     .with_clip("jump", AnimationClip::from_grid(4, 2, 4..8, frame).mode(PlaybackMode::Once));
+/// let mut hero = AnimatedSprite::new(sheet, animation);
+/// hero.animation_mut().play("walk")?;
+///
+/// // in update:
+/// hero.update(ctx);
+/// // in draw:
+/// canvas.draw(&hero, [100., 100.]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnimatedSprite {
+    image: Image,
+    animation: Animation,
+}
+
+impl AnimatedSprite {
+    /// Creates a new sprite drawing from `image` according to `animation`.
+    pub fn new(image: Image, animation: Animation) -> Self {
+        AnimatedSprite { image, animation }
+    }
+
+    /// Returns the image frames are drawn from.
+    #[inline]
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Returns the animation of this sprite.
+    #[inline]
+    pub fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    /// Returns the animation of this sprite, mutably.
+    #[inline]
+    pub fn animation_mut(&mut self) -> &mut Animation {
+        &mut self.animation
+    }
+
+    /// Advances the animation by the last frame's [`TimeContext::delta`], see [`Animation::update`].
+    pub fn update(&mut self, time: &impl Has<TimeContext>) {
+        self.animation.update(time);
+    }
+}
+
+impl Drawable for AnimatedSprite {
+    fn draw(&self, canvas: &mut Canvas, param: impl Into<DrawParam>) {
+        self.image
+            .draw(canvas, param.into().src(self.animation.current_src()));
+    }
+
+    fn dimensions(&self, _gfx: &impl Has<GraphicsContext>) -> Option<Rect> {
+        let src = self.animation.current_src();
+        Some(Rect {
+            x: 0.,
+            y: 0.,
+            w: src.w * self.image.width() as f32,
+            h: src.h * self.image.height() as f32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(100);
+
+    fn frames_after(mode: PlaybackMode, steps: usize) -> Vec<usize> {
+        let mut animation = Animation::new().with_clip(
+            "clip",
+            AnimationClip::from_grid(4, 1, 0..4, FRAME).mode(mode),
+        );
+        animation.play("clip").unwrap();
+        (0..steps)
+            .map(|_| {
+                animation.advance(FRAME);
+                animation.current_frame()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn playback_modes() {
+        assert_eq!(frames_after(PlaybackMode::Loop, 6), vec![1, 2, 3, 0, 1, 2]);
+        assert_eq!(frames_after(PlaybackMode::Once, 6), vec![1, 2, 3, 3, 3, 3]);
+        assert_eq!(
+            frames_after(PlaybackMode::PingPong, 8),
+            vec![1, 2, 3, 2, 1, 0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn events() {
+        let mut animation = Animation::new().with_clip(
+            "clip",
+            AnimationClip::from_grid(2, 2, [1, 3], FRAME).mode(PlaybackMode::Once),
+        );
+        animation.play("clip").unwrap();
+        assert_eq!(animation.current_src(), Rect::new(0.5, 0., 0.5, 0.5));
+
+        animation.advance(FRAME * 3);
+        assert_eq!(animation.current_src(), Rect::new(0.5, 0.5, 0.5, 0.5));
+        assert!(!animation.is_playing());
+
+        let clip = String::from("clip");
+        assert_eq!(
+            animation.drain_events().collect::<Vec<_>>(),
+            vec![
+                AnimationEvent::FrameChanged {
+                    clip: clip.clone(),
+                    frame: 0
+                },
+                AnimationEvent::FrameChanged {
+                    clip: clip.clone(),
+                    frame: 1
+                },
+                AnimationEvent::Finished { clip },
+            ]
+        );
+        assert!(animation.play("missing").is_err());
+    }
+}
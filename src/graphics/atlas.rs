@@ -0,0 +1,531 @@
+use super::{
+    DrawParam, GraphicsContext, Image, ImageEncodingFormat, ImageFormat, InstanceArray, Rect,
+};
+use crate::{
+    context::{Has, HasMut},
+    filesystem::Filesystem,
+    Context, GameError, GameResult,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// A named rectangle inside a [`TextureAtlas`], in pixel coordinates.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AtlasRegion {
+    /// X coordinate of the top-left corner.
+    pub x: u32,
+    /// Y coordinate of the top-left corner.
+    pub y: u32,
+    /// Width of the region.
+    pub w: u32,
+    /// Height of the region.
+    pub h: u32,
+}
+
+/// The serializable description of a [`TextureAtlas`]: where its image is stored and which regions it contains.
+///
+/// This is what [`TextureAtlas::save`] writes and [`TextureAtlas::from_path`] reads, formatted as TOML:
+///
+/// ```toml
+/// image = "/atlas.png"
+/// width = 256
+/// height = 256
+///
+/// [regions.player]
+/// x = 0
+/// y = 0
+/// w = 32
+/// h = 48
+/// ```
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct AtlasDescription {
+    /// Path of the atlas image, in the ggez filesystem.
+    pub image: PathBuf,
+    /// Width of the atlas image in pixels.
+    pub width: u32,
+    /// Height of the atlas image in pixels.
+    pub height: u32,
+    /// The named regions of the atlas.
+    pub regions: BTreeMap<String, AtlasRegion>,
+}
+
+impl AtlasDescription {
+    /// Load a TOML file from the given `Read` and attempts to parse
+    /// an `AtlasDescription` from it.
+    pub fn from_toml_file<R: Read>(file: &mut R) -> GameResult<Self> {
+        let mut s = String::new();
+        let _ = file.read_to_string(&mut s)?;
+        let decoded = toml::from_str(&s)?;
+        Ok(decoded)
+    }
+
+    /// Saves the `AtlasDescription` to the given `Write` object,
+    /// formatted as TOML.
+    pub fn to_toml_file<W: Write>(&self, file: &mut W) -> GameResult {
+        let s = toml::to_vec(self)?;
+        file.write_all(&s)?;
+        Ok(())
+    }
+}
+
+/// Many images packed into a single GPU texture, each accessible through a name.
+///
+/// Drawing sprites from a single atlas lets them be batched together, e.g. into one [`InstanceArray`]
+/// created with [`TextureAtlas::instance_array`], regardless of which file or buffer each sprite originally came from.
+///
+/// Atlases are built at runtime with a [`TextureAtlasBuilder`], or loaded from an image and an [`AtlasDescription`]
+/// with [`TextureAtlas::from_path`].
+///
+/// ```rust,no_run
+/// # use ggez::{graphics::{self, TextureAtlas}, GameResult, Context};
+/// # fn t(ctx: &mut Context, canvas: &mut graphics::Canvas) -> GameResult {
+/// let atlas = TextureAtlas::builder(512, 512)
+///     .add_path("player", "/player.png")
+///     .add_path("enemy", "/enemy.png")
+///     .build(ctx)?;
+///
+/// let mut sprites = atlas.instance_array(ctx);
+/// sprites.push(atlas.draw_param("player").unwrap().dest([10., 10.]));
+/// sprites.push(atlas.draw_param("enemy").unwrap().dest([100., 10.]));
+/// canvas.draw(&sprites, graphics::DrawParam::new());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TextureAtlas {
+    image: Image,
+    regions: BTreeMap<String, AtlasRegion>,
+}
+
+impl TextureAtlas {
+    /// Returns a new [`TextureAtlasBuilder`] for an atlas of the given size in pixels.
+    pub fn builder(width: u32, height: u32) -> TextureAtlasBuilder {
+        TextureAtlasBuilder::new(width, height)
+    }
+
+    /// Creates an atlas from an existing image and a set of named regions within it.
+    pub fn from_regions(image: Image, regions: BTreeMap<String, AtlasRegion>) -> GameResult<Self> {
+        if let Some((name, _)) = regions.iter().find(|(_, r)| {
+            r.x.saturating_add(r.w) > image.width() || r.y.saturating_add(r.h) > image.height()
+        }) {
+            return Err(GameError::ResourceLoadError(format!(
+                "atlas region {name:?} lies outside of the atlas image"
+            )));
+        }
+
+        Ok(TextureAtlas { image, regions })
+    }
+
+    /// Loads an atlas from an [`AtlasDescription`] TOML file in the filesystem,
+    /// along with the image it refers to.
+    pub fn from_path(gfx: &impl Has<GraphicsContext>, path: impl AsRef<Path>) -> GameResult<Self> {
+        let gfx = gfx.retrieve();
+        let description = AtlasDescription::from_toml_file(&mut gfx.fs.open(path)?)?;
+        let image = Image::from_path(gfx, &description.image)?;
+        if (image.width(), image.height()) != (description.width, description.height) {
+            return Err(GameError::ResourceLoadError(format!(
+                "atlas image {:?} is {}x{}, but its description says {}x{}",
+                description.image,
+                image.width(),
+                image.height(),
+                description.width,
+                description.height
+            )));
+        }
+        Self::from_regions(image, description.regions)
+    }
+
+    /// Saves the atlas image as a PNG to `image_path` and its [`AtlasDescription`] as TOML to `description_path`,
+    /// both in the user directory of the filesystem. The result can be loaded again with [`TextureAtlas::from_path`].
+    ///
+    /// **This is a very expensive operation - call sparingly.**
+    pub fn save(
+        &self,
+        ctx: &Context,
+        image_path: impl AsRef<Path>,
+        description_path: impl AsRef<Path>,
+    ) -> GameResult {
+        let image_path = image_path.as_ref();
+        self.image
+            .encode(ctx, ImageEncodingFormat::Png, image_path)?;
+        let mut file = ctx.fs.create(description_path)?;
+        self.description(image_path).to_toml_file(&mut file)
+    }
+
+    /// Returns the [`AtlasDescription`] of this atlas, referring to its image as `image_path`.
+    pub fn description(&self, image_path: impl Into<PathBuf>) -> AtlasDescription {
+        AtlasDescription {
+            image: image_path.into(),
+            width: self.image.width(),
+            height: self.image.height(),
+            regions: self.regions.clone(),
+        }
+    }
+
+    /// Returns the image holding all the packed sprites.
+    #[inline]
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Returns the region with the given name, in pixel coordinates.
+    #[inline]
+    pub fn region(&self, name: &str) -> Option<AtlasRegion> {
+        self.regions.get(name).copied()
+    }
+
+    /// Returns an iterator over all named regions of the atlas.
+    pub fn regions(&self) -> impl Iterator<Item = (&str, AtlasRegion)> {
+        self.regions.iter().map(|(name, r)| (name.as_str(), *r))
+    }
+
+    /// Returns the region with the given name in UV coordinates, suitable for [`DrawParam::src`].
+    pub fn uv_rect(&self, name: &str) -> Option<Rect> {
+        self.region(name)
+            .map(|r| self.image.uv_rect(r.x, r.y, r.w, r.h))
+    }
+
+    /// Returns a [`DrawParam`] with `src` set to the region with the given name.
+    ///
+    /// Drawing the atlas image (or an [`InstanceArray`] using it) with this `DrawParam` draws only that sprite,
+    /// at its original size.
+    pub fn draw_param(&self, name: &str) -> Option<DrawParam> {
+        self.uv_rect(name).map(|src| DrawParam::new().src(src))
+    }
+
+    /// Creates a new [`InstanceArray`] that draws from this atlas' image.
+    ///
+    /// Fill it with [`TextureAtlas::draw_param`]s to draw any number of sprites from this atlas in a single draw call.
+    pub fn instance_array(&self, gfx: &impl Has<GraphicsContext>) -> InstanceArray {
+        InstanceArray::new(gfx, self.image.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum AtlasSource {
+    Image(Image),
+    Pixels {
+        pixels: Vec<u8>,
+        width: u32,
+        height: u32,
+    },
+    Path(PathBuf),
+}
+
+/// Builder for [`TextureAtlas`], which packs the added images into a single texture when built.
+///
+/// Sprites are packed in rows ("shelves"), tallest first.
+/// All sources must be RGBA8 (sRGB) images, which is the format images loaded from files have.
+#[derive(Debug, Clone)]
+pub struct TextureAtlasBuilder {
+    width: u32,
+    height: u32,
+    padding: u32,
+    sources: Vec<(String, AtlasSource)>,
+}
+
+impl TextureAtlasBuilder {
+    /// Creates a new builder for an atlas of the given size in pixels.
+    pub fn new(width: u32, height: u32) -> Self {
+        TextureAtlasBuilder {
+            width,
+            height,
+            padding: 1,
+            sources: Vec::new(),
+        }
+    }
+
+    /// Sets the number of empty pixels left between packed sprites, to avoid bleeding when sampling with linear filtering.
+    ///
+    /// Defaults to 1.
+    #[must_use]
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Adds an image that is already on the GPU.
+    /// It must be an `Rgba8UnormSrgb` image with exactly 1 sample.
+    #[must_use]
+    pub fn add_image(mut self, name: impl Into<String>, image: &Image) -> Self {
+        self.sources
+            .push((name.into(), AtlasSource::Image(image.clone())));
+        self
+    }
+
+    /// Adds raw RGBA8 (sRGB) pixel data.
+    #[must_use]
+    pub fn add_pixels(
+        mut self,
+        name: impl Into<String>,
+        pixels: impl Into<Vec<u8>>,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        self.sources.push((
+            name.into(),
+            AtlasSource::Pixels {
+                pixels: pixels.into(),
+                width,
+                height,
+            },
+        ));
+        self
+    }
+
+    /// Adds an encoded image (e.g. PNG or JPEG) from the filesystem. It is loaded when the atlas is built.
+    #[must_use]
+    pub fn add_path(mut self, name: impl Into<String>, path: impl AsRef<Path>) -> Self {
+        self.sources
+            .push((name.into(), AtlasSource::Path(path.as_ref().to_path_buf())));
+        self
+    }
+
+    /// Loads all sources, packs them and uploads them into a new atlas texture.
+    ///
+    /// Fails if the sources don't fit into the atlas size, if two sources share a name,
+    /// or if a source can't be loaded, has the wrong format or is empty.
+    pub fn build(self, gfx: &mut impl HasMut<GraphicsContext>) -> GameResult<TextureAtlas> {
+        let gfx = gfx.retrieve_mut();
+
+        let mut names = BTreeSet::new();
+        if let Some((name, _)) = self
+            .sources
+            .iter()
+            .find(|(name, _)| !names.insert(name.as_str()))
+        {
+            return Err(GameError::ResourceLoadError(format!(
+                "atlas source name {name:?} is used more than once"
+            )));
+        }
+
+        let mut sources = Vec::with_capacity(self.sources.len());
+        let mut sizes = Vec::with_capacity(self.sources.len());
+        for (name, source) in self.sources {
+            let source = match source {
+                AtlasSource::Path(path) => load_pixels(&gfx.fs, &path)?,
+                AtlasSource::Pixels {
+                    pixels,
+                    width,
+                    height,
+                } if pixels.len() as u64 != 4 * u64::from(width) * u64::from(height) => {
+                    return Err(GameError::ResourceLoadError(format!(
+                        "atlas source {name:?} should have {} bytes of RGBA8 pixels, but has {}",
+                        4 * u64::from(width) * u64::from(height),
+                        pixels.len()
+                    )));
+                }
+                AtlasSource::Image(image)
+                    if image.format() != ImageFormat::Rgba8UnormSrgb || image.samples() != 1 =>
+                {
+                    return Err(GameError::ResourceLoadError(format!(
+                        "atlas source {name:?} must be a non-multisampled Rgba8UnormSrgb image"
+                    )));
+                }
+                source => source,
+            };
+            let size = match &source {
+                AtlasSource::Image(image) => (image.width(), image.height()),
+                AtlasSource::Pixels { width, height, .. } => (*width, *height),
+                AtlasSource::Path(_) => unreachable!("paths have been loaded above"),
+            };
+            if size.0 == 0 || size.1 == 0 {
+                return Err(GameError::ResourceLoadError(format!(
+                    "atlas source {name:?} is empty"
+                )));
+            }
+            sources.push((name, source));
+            sizes.push(size);
+        }
+
+        let positions =
+            pack_shelves(&sizes, self.width, self.height, self.padding).ok_or_else(|| {
+                GameError::ResourceLoadError(format!(
+                    "atlas sources do not fit into a {}x{} atlas",
+                    self.width, self.height
+                ))
+            })?;
+
+        let image = Image::new(
+            &gfx.wgpu,
+            ImageFormat::Rgba8UnormSrgb,
+            self.width,
+            self.height,
+            1,
+            wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        );
+
+        let mut cmd = gfx
+            .wgpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let mut regions = BTreeMap::new();
+
+        for (((name, source), (w, h)), (x, y)) in sources.into_iter().zip(sizes).zip(positions) {
+            let destination = wgpu::ImageCopyTexture {
+                texture: &image.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            };
+            let extent = wgpu::Extent3d {
+                width: w,
+                height: h,
+                depth_or_array_layers: 1,
+            };
+
+            match source {
+                AtlasSource::Image(src) => {
+                    cmd.copy_texture_to_texture(src.texture.as_image_copy(), destination, extent)
+                }
+                AtlasSource::Pixels { pixels, .. } => gfx.wgpu.queue.write_texture(
+                    destination,
+                    &pixels,
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * w),
+                        rows_per_image: None,
+                    },
+                    extent,
+                ),
+                AtlasSource::Path(_) => unreachable!("paths have been loaded above"),
+            }
+
+            let _ = regions.insert(name, AtlasRegion { x, y, w, h });
+        }
+
+        let _ = gfx.wgpu.queue.submit([cmd.finish()]);
+
+        Ok(TextureAtlas { image, regions })
+    }
+}
+
+fn load_pixels(fs: &Filesystem, path: &Path) -> GameResult<AtlasSource> {
+    let mut encoded = Vec::new();
+    let _ = fs.open(path)?.read_to_end(&mut encoded)?;
+    let rgba8 = ::image::load_from_memory(&encoded)?.to_rgba8();
+    let (width, height) = rgba8.dimensions();
+    Ok(AtlasSource::Pixels {
+        pixels: rgba8.into_raw(),
+        width,
+        height,
+    })
+}
+
+/// Packs rectangles of the given sizes into shelves, tallest first, returning the top-left corner of each
+/// (in the order they were given), or `None` if they don't fit.
+fn pack_shelves(
+    sizes: &[(u32, u32)],
+    width: u32,
+    height: u32,
+    padding: u32,
+) -> Option<Vec<(u32, u32)>> {
+    let mut order = (0..sizes.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| sizes[b].1.cmp(&sizes[a].1));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+
+    for i in order {
+        let (w, h) = sizes[i];
+        if w > width || h > height {
+            return None;
+        }
+
+        if x + w > width {
+            // start a new shelf
+            x = 0;
+            y += shelf_height + padding;
+            shelf_height = 0;
+        }
+
+        if y + h > height {
+            return None;
+        }
+
+        positions[i] = (x, y);
+        x += w + padding;
+        shelf_height = shelf_height.max(h);
+    }
+
+    Some(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(a: ((u32, u32), (u32, u32)), b: ((u32, u32), (u32, u32))) -> bool {
+        let (((ax, ay), (aw, ah)), ((bx, by), (bw, bh))) = (a, b);
+        ax < bx + bw && bx < ax + aw && ay < by + bh && by < ay + ah
+    }
+
+    #[test]
+    fn shelves_do_not_overlap() {
+        let sizes = [(10, 20), (30, 5), (16, 16), (40, 8), (8, 8), (25, 12)];
+        let positions = pack_shelves(&sizes, 64, 64, 1).unwrap();
+
+        for (i, (&pos, &size)) in positions.iter().zip(&sizes).enumerate() {
+            assert!(pos.0 + size.0 <= 64 && pos.1 + size.1 <= 64);
+            for (&other_pos, &other_size) in positions.iter().zip(&sizes).skip(i + 1) {
+                assert!(!overlaps((pos, size), (other_pos, other_size)));
+            }
+        }
+    }
+
+    #[test]
+    fn shelves_respect_bounds() {
+        assert!(pack_shelves(&[(65, 1)], 64, 64, 0).is_none());
+        assert!(pack_shelves(&[(32, 32); 4], 64, 64, 0).is_some());
+        assert!(pack_shelves(&[(32, 32); 4], 64, 64, 1).is_none());
+    }
+
+    #[test]
+    fn invalid_sources() {
+        let mut ctx = crate::context::test_context("ggez_atlas_test");
+        let pixel = [255u8; 4];
+        let duplicate = TextureAtlas::builder(16, 16)
+            .add_pixels("a", pixel.to_vec(), 1, 1)
+            .add_pixels("a", pixel.to_vec(), 1, 1)
+            .build(&mut ctx);
+        assert!(duplicate.is_err());
+        let empty = TextureAtlas::builder(16, 16)
+            .add_pixels("a", pixel.to_vec(), 1, 1)
+            .add_pixels("b", Vec::new(), 0, 0)
+            .build(&mut ctx);
+        assert!(empty.is_err());
+        let huge = TextureAtlas::builder(16, 16)
+            .add_pixels("a", pixel.to_vec(), 1 << 16, 1 << 16)
+            .build(&mut ctx);
+        assert!(huge.is_err());
+    }
+
+    #[test]
+    fn description_round_trip() {
+        let mut regions = BTreeMap::new();
+        let _ = regions.insert(
+            String::from("player"),
+            AtlasRegion {
+                x: 1,
+                y: 2,
+                w: 3,
+                h: 4,
+            },
+        );
+        let d1 = AtlasDescription {
+            image: PathBuf::from("/atlas.png"),
+            width: 64,
+            height: 32,
+            regions,
+        };
+        let mut writer = Vec::new();
+        d1.to_toml_file(&mut writer).unwrap();
+        let d2 = AtlasDescription::from_toml_file(&mut writer.as_slice()).unwrap();
+        assert_eq!(d1, d2);
+    }
+}
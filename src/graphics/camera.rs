@@ -0,0 +1,324 @@
+use super::{internal_canvas::screen_to_mat, Rect};
+use crate::{context::Has, timer::TimeContext};
+use glam::{Mat4, Vec2, Vec3};
+use std::time::Duration;
+
+/// A 2D camera looking at a scrolling, zoomable and rotatable world.
+///
+/// The camera shows the world around its [`position`](Camera2D::position) in its
+/// [`viewport`](Camera2D::viewport), which is given in screen coordinates (pixels, by default).
+/// One world unit is one screen unit at a zoom of 1.0, and increasing the zoom magnifies the world.
+///
+/// ```rust,no_run
+/// # use ggez::{graphics::{self, Camera2D, Color}, GameResult, Context};
+/// # fn t(ctx: &mut Context, camera: &mut Camera2D, player: [f32; 2]) -> GameResult {
+/// // in update:
+/// camera.follow(player);
+/// camera.update(ctx);
+/// let cursor = camera.screen_to_world(ctx.mouse.position());
+///
+/// // in draw:
+/// let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
+/// canvas.set_camera(camera)?;
+/// // ...draw the world in world coordinates...
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera2D {
+    position: Vec2,
+    zoom: f32,
+    rotation: f32,
+    viewport: Rect,
+    target: Option<Vec2>,
+    deadzone: Vec2,
+    smoothing: f32,
+    shake: Option<Shake>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Shake {
+    amplitude: f32,
+    duration: Duration,
+    elapsed: Duration,
+}
+
+impl Shake {
+    fn offset(&self) -> Vec2 {
+        let t = self.elapsed.as_secs_f32();
+        let falloff = 1. - t / self.duration.as_secs_f32();
+        // incommensurable frequencies so the motion doesn't visibly repeat
+        Vec2::new(
+            (t * 61.).sin() + (t * 23.).sin(),
+            (t * 53.).cos() + (t * 29.).sin(),
+        ) * (0.5 * self.amplitude * falloff)
+    }
+}
+
+impl Camera2D {
+    /// Creates a new camera with the given viewport, looking at the world origin.
+    pub fn new(viewport: Rect) -> Self {
+        Camera2D {
+            position: Vec2::ZERO,
+            zoom: 1.,
+            rotation: 0.,
+            viewport,
+            target: None,
+            deadzone: Vec2::ZERO,
+            smoothing: 0.,
+            shake: None,
+        }
+    }
+
+    /// Returns the world point in the center of the viewport.
+    #[inline]
+    pub fn position(&self) -> mint::Point2<f32> {
+        self.position.into()
+    }
+
+    /// Moves the camera so that the given world point is in the center of the viewport.
+    ///
+    /// This also stops following the current [`follow`](Camera2D::follow) target.
+    pub fn set_position(&mut self, position: impl Into<mint::Point2<f32>>) {
+        self.position = Vec2::from(position.into());
+        self.target = None;
+    }
+
+    /// Moves the camera by the given offset in world units.
+    pub fn translate(&mut self, offset: impl Into<mint::Vector2<f32>>) {
+        self.position += Vec2::from(offset.into());
+    }
+
+    /// Returns the zoom factor.
+    #[inline]
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Sets the zoom factor; 2.0 shows everything twice as large. Must be larger than 0.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(f32::EPSILON);
+    }
+
+    /// Returns the rotation of the camera, in radians.
+    #[inline]
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// Sets the rotation of the camera, in radians.
+    ///
+    /// Rotating the camera clockwise makes the world appear rotated counterclockwise.
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+
+    /// Returns the region of the screen the camera draws to.
+    #[inline]
+    pub fn viewport(&self) -> Rect {
+        self.viewport
+    }
+
+    /// Sets the region of the screen the camera draws to, e.g. after the window was resized.
+    pub fn set_viewport(&mut self, viewport: Rect) {
+        self.viewport = viewport;
+    }
+
+    /// Makes the camera follow the given world point from now on.
+    ///
+    /// The camera only moves once the target leaves the [deadzone](Camera2D::set_deadzone),
+    /// either immediately or [smoothly](Camera2D::set_follow_smoothing) during [`Camera2D::update`].
+    /// Call this whenever the followed thing moves.
+    pub fn follow(&mut self, target: impl Into<mint::Point2<f32>>) {
+        self.target = Some(Vec2::from(target.into()));
+    }
+
+    /// Stops following the current target.
+    pub fn unfollow(&mut self) {
+        self.target = None;
+    }
+
+    /// Sets the size of the area around the center of the viewport, in screen units,
+    /// in which the followed target can move without moving the camera.
+    pub fn set_deadzone(&mut self, width: f32, height: f32) {
+        self.deadzone = Vec2::new(width.max(0.), height.max(0.));
+    }
+
+    /// Sets how quickly the camera catches up with the followed target; higher is faster.
+    ///
+    /// With a smoothing of 3.0, the camera covers about 95% of the distance to the target within one second.
+    /// 0.0, the default, moves the camera all the way immediately.
+    pub fn set_follow_smoothing(&mut self, smoothing: f32) {
+        self.smoothing = smoothing.max(0.);
+    }
+
+    /// Shakes the camera for the given duration, displacing it by up to `amplitude` screen units.
+    ///
+    /// The shake fades out over its duration, and replaces any shake that is still going on.
+    pub fn shake(&mut self, amplitude: f32, duration: Duration) {
+        self.shake = Some(Shake {
+            amplitude,
+            duration,
+            elapsed: Duration::ZERO,
+        });
+    }
+
+    /// Returns whether the camera is currently shaking.
+    #[inline]
+    pub fn is_shaking(&self) -> bool {
+        self.shake.is_some()
+    }
+
+    /// Advances following and shaking by the last frame's [`TimeContext::delta`].
+    ///
+    /// Call this once per `update`.
+    pub fn update(&mut self, time: &impl Has<TimeContext>) {
+        self.advance(time.retrieve().delta());
+    }
+
+    /// Advances following and shaking by the given amount of time.
+    pub fn advance(&mut self, dt: Duration) {
+        if let Some(target) = self.target {
+            // how far the target is outside the deadzone, in screen units along the camera's axes
+            let local = Vec2::from_angle(-self.rotation).rotate(target - self.position) * self.zoom;
+            let half = self.deadzone / 2.;
+            let excess = local - local.clamp(-half, half);
+            let step = if self.smoothing > 0. {
+                1. - (-self.smoothing * dt.as_secs_f32()).exp()
+            } else {
+                1.
+            };
+            self.position += Vec2::from_angle(self.rotation).rotate(excess) / self.zoom * step;
+        }
+
+        if let Some(shake) = &mut self.shake {
+            shake.elapsed += dt;
+            if shake.elapsed >= shake.duration {
+                self.shake = None;
+            }
+        }
+    }
+
+    /// Returns the matrix transforming world coordinates to screen coordinates.
+    pub fn view_matrix(&self) -> mint::ColumnMatrix4<f32> {
+        self.view().into()
+    }
+
+    /// Returns the projection matrix for a canvas whose screen coordinates are `screen`,
+    /// i.e. `Rect::new(0., 0., width, height)` for a canvas drawing to a `width` by `height` image.
+    ///
+    /// This is what [`Canvas::set_camera`](super::Canvas::set_camera) uses; it is useful to combine the camera with other transforms.
+    pub fn projection(&self, screen: Rect) -> mint::ColumnMatrix4<f32> {
+        (screen_to_mat(screen) * self.view()).into()
+    }
+
+    /// Converts a point in screen coordinates, such as [`MouseContext::position`](crate::input::mouse::MouseContext::position),
+    /// to world coordinates.
+    pub fn screen_to_world(&self, point: impl Into<mint::Point2<f32>>) -> mint::Point2<f32> {
+        let point = Vec2::from(point.into());
+        self.view()
+            .inverse()
+            .transform_point3(point.extend(0.))
+            .truncate()
+            .into()
+    }
+
+    /// Converts a point in world coordinates to screen coordinates.
+    pub fn world_to_screen(&self, point: impl Into<mint::Point2<f32>>) -> mint::Point2<f32> {
+        let point = Vec2::from(point.into());
+        self.view()
+            .transform_point3(point.extend(0.))
+            .truncate()
+            .into()
+    }
+
+    /// Returns the smallest axis-aligned rectangle, in world coordinates, containing everything visible
+    /// in the viewport. Useful to skip drawing things that are off screen.
+    pub fn visible_rect(&self) -> Rect {
+        let v = self.viewport;
+        let corners = [
+            (v.left(), v.top()),
+            (v.right(), v.top()),
+            (v.left(), v.bottom()),
+            (v.right(), v.bottom()),
+        ]
+        .map(|(x, y)| Vec2::from(self.screen_to_world([x, y])));
+        let min = corners.iter().fold(Vec2::splat(f32::MAX), |a, &c| a.min(c));
+        let max = corners.iter().fold(Vec2::splat(f32::MIN), |a, &c| a.max(c));
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    fn view(&self) -> Mat4 {
+        let center = Vec2::from(self.viewport.center())
+            + self.shake.as_ref().map_or(Vec2::ZERO, Shake::offset);
+        Mat4::from_translation(center.extend(0.))
+            * Mat4::from_rotation_z(-self.rotation)
+            * Mat4::from_scale(Vec3::new(self.zoom, self.zoom, 1.))
+            * Mat4::from_translation((-self.position).extend(0.))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn assert_point_eq(a: mint::Point2<f32>, b: [f32; 2]) {
+        assert_relative_eq!(a.x, b[0], epsilon = 1e-3);
+        assert_relative_eq!(a.y, b[1], epsilon = 1e-3);
+    }
+
+    #[test]
+    fn screen_world_conversion() {
+        let mut camera = Camera2D::new(Rect::new(0., 0., 800., 600.));
+        camera.set_position([100., 50.]);
+        assert_point_eq(camera.world_to_screen([100., 50.]), [400., 300.]);
+        assert_point_eq(camera.screen_to_world([0., 0.]), [-300., -250.]);
+
+        camera.set_zoom(2.);
+        camera.set_rotation(std::f32::consts::FRAC_PI_2);
+        assert_point_eq(camera.world_to_screen([110., 50.]), [400., 280.]);
+        for p in [[0., 0.], [123., -45.], [800., 600.]] {
+            let world = camera.screen_to_world(p);
+            assert_point_eq(camera.world_to_screen(world), p);
+        }
+        assert_relative_eq!(
+            camera.visible_rect(),
+            Rect::new(-50., -150., 300., 400.),
+            epsilon = 1e-3
+        );
+    }
+
+    #[test]
+    fn follow_deadzone() {
+        let mut camera = Camera2D::new(Rect::new(0., 0., 800., 600.));
+        camera.set_deadzone(100., 50.);
+
+        camera.follow([40., 20.]);
+        camera.advance(Duration::from_millis(16));
+        assert_point_eq(camera.position(), [0., 0.]);
+
+        camera.follow([80., -40.]);
+        camera.advance(Duration::from_millis(16));
+        assert_point_eq(camera.position(), [30., -15.]);
+
+        camera.set_follow_smoothing(10.);
+        camera.follow([130., -15.]);
+        camera.advance(Duration::from_millis(16));
+        let x = camera.position().x;
+        assert!(x > 30. && x < 80.);
+        camera.advance(Duration::from_secs(10));
+        assert_point_eq(camera.position(), [80., -15.]);
+    }
+
+    #[test]
+    fn shake_wears_off() {
+        let mut camera = Camera2D::new(Rect::new(0., 0., 800., 600.));
+        camera.shake(10., Duration::from_millis(500));
+        camera.advance(Duration::from_millis(100));
+        assert!(camera.is_shaking());
+        camera.advance(Duration::from_millis(400));
+        assert!(!camera.is_shaking());
+        assert_point_eq(camera.world_to_screen([0., 0.]), [400., 300.]);
+    }
+}
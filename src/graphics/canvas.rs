@@ -6,12 +6,16 @@ use crate::{
 };
 
 use super::{
-    gpu::arc::{ArcBindGroup, ArcBindGroupLayout},
-    internal_canvas::{screen_to_mat, InstanceArrayView, InternalCanvas},
-    BlendMode, Color, DrawParam, Drawable, GraphicsContext, Image, InstanceArray, Mesh, Rect,
-    Sampler, ScreenImage, Shader, ShaderParams, Text, WgpuContext, ZIndex,
+    gpu::{
+        arc::{ArcBindGroup, ArcBindGroupLayout},
+        counters::RenderCounters,
+    },
+    internal_canvas::{create_depth_stencil, screen_to_mat, InstanceArrayView, InternalCanvas},
+    BlendMode, Camera2D, Color, DrawParam, Drawable, GraphicsContext, Image, InstanceArray, Mesh,
+    Rect, Sampler, ScreenImage, Shader, ShaderParams, Text, WgpuContext, ZIndex,
 };
 use std::{collections::BTreeMap, sync::Arc};
+pub use wgpu::CompareFunction;
 
 /// Canvases are the main method of drawing meshes and text to images in ggez.
 ///
@@ -26,6 +30,7 @@ use std::{collections::BTreeMap, sync::Arc};
 #[derive(Debug)]
 pub struct Canvas {
     pub(crate) wgpu: Arc<WgpuContext>,
+    pub(crate) counters: Arc<RenderCounters>,
     draws: BTreeMap<ZIndex, Vec<DrawCommand>>,
     state: DrawState,
     original_state: DrawState,
@@ -36,6 +41,12 @@ pub struct Canvas {
     resolve: Option<Image>,
     clear: Option<Color>,
 
+    mask_mode: MaskMode,
+    // stencil value of the latest mask; 0 if there is none yet, in which case there's no stencil attachment
+    mask_reference: u8,
+    // whether any draw has a depth test, in which case there's a depth attachment
+    depth_tested: bool,
+
     // This will be removed after queue_text and draw_queued_text have been removed.
     pub(crate) queued_texts: Vec<(Text, mint::Point2<f32>, Option<Color>)>,
 }
@@ -131,6 +142,8 @@ impl Canvas {
             premul_text: true,
             projection: glam::Mat4::IDENTITY.into(),
             scissor_rect: (0, 0, target.width(), target.height()),
+            mask: MaskState::Off,
+            depth: None,
         };
 
         let screen = Rect {
@@ -142,6 +155,7 @@ impl Canvas {
 
         let mut this = Canvas {
             wgpu: gfx.wgpu.clone(),
+            counters: gfx.counters.clone(),
             draws: BTreeMap::new(),
             state: state.clone(),
             original_state: state,
@@ -152,6 +166,10 @@ impl Canvas {
             resolve,
             clear,
 
+            mask_mode: MaskMode::default(),
+            mask_reference: 0,
+            depth_tested: false,
+
             queued_texts: Vec::new(),
         };
 
@@ -313,6 +331,16 @@ impl Canvas {
         self.screen
     }
 
+    /// Sets the projection to show the world as seen by the given camera, and restricts drawing
+    /// to the camera's viewport with the [scissor rect](Canvas::set_scissor_rect).
+    ///
+    /// The camera's viewport is in pixels of the canvas' target image.
+    pub fn set_camera(&mut self, camera: &Camera2D) -> GameResult {
+        let (width, height) = (self.target.width() as f32, self.target.height() as f32);
+        self.set_projection(camera.projection(Rect::new(0., 0., width, height)));
+        self.set_scissor_rect(camera.viewport())
+    }
+
     /// Sets the scissor rectangle used when drawing. Nothing will be drawn to the canvas
     /// that falls outside of this region.
     ///
@@ -359,6 +387,93 @@ impl Canvas {
         self.state.scissor_rect = self.original_state.scissor_rect;
     }
 
+    /// Starts drawing a mask, which replaces the previous one.
+    ///
+    /// Until [`Canvas::end_mask`] is called, anything drawn adds its shape to the mask instead of
+    /// being drawn visibly. Any [`Drawable`] can be used, including images and text; their
+    /// transparent pixels are left out of the mask when drawn with the default shaders.
+    ///
+    /// Like all draws, the mask is drawn in order of `z`; masking draws with different `z` values
+    /// can mix up which draws are masked by which mask.
+    ///
+    /// ```rust,no_run
+    /// # use ggez::{graphics::*, Context, GameResult};
+    /// # fn t(ctx: &mut Context, minimap: &Image) -> GameResult {
+    /// let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+    /// canvas.begin_mask();
+    /// let circle = Mesh::new_circle(ctx, DrawMode::fill(), [100., 100.], 64., 0.5, Color::WHITE)?;
+    /// canvas.draw(&circle, DrawParam::new());
+    /// canvas.end_mask();
+    /// // only the part of the minimap inside the circle is drawn
+    /// canvas.draw(minimap, [36., 36.]);
+    /// canvas.set_mask_mode(MaskMode::Off);
+    /// canvas.finish(ctx)
+    /// # }
+    /// ```
+    pub fn begin_mask(&mut self) {
+        // after 255 masks, a new mask also contains what's left of the first one
+        self.mask_reference = self.mask_reference % u8::MAX + 1;
+        self.state.mask = MaskState::Write(self.mask_reference);
+    }
+
+    /// Stops drawing the mask started with [`Canvas::begin_mask`], and masks the following draws
+    /// with it as set with [`Canvas::set_mask_mode`].
+    pub fn end_mask(&mut self) {
+        self.state.mask = self.masking();
+    }
+
+    /// Sets whether the following draws are restricted to the inside or the outside of the mask,
+    /// or not masked at all. The default is [`MaskMode::Inside`].
+    pub fn set_mask_mode(&mut self, mode: MaskMode) {
+        self.mask_mode = mode;
+        if !self.state.mask.writes() {
+            self.state.mask = self.masking();
+        }
+    }
+
+    /// Returns the mask mode as set by [`Canvas::set_mask_mode`].
+    #[inline]
+    pub fn mask_mode(&self) -> MaskMode {
+        self.mask_mode
+    }
+
+    /// Sets the depth test of the following draws, or disables it with `None`, which is the
+    /// default.
+    ///
+    /// Once any draw is depth tested, the canvas gets a depth buffer, cleared to the far plane
+    /// (depth `1.0`) when the canvas begins drawing. Draws that pass the test are drawn, regardless
+    /// of their order, so the closest of overlapping [`Vertex3d`](super::Vertex3d) meshes ends up
+    /// on top. Draws without a depth test are drawn as usual, neither testing nor writing depth.
+    ///
+    /// ```rust
+    /// # use ggez::{graphics::*, Context, GameResult};
+    /// # fn t(ctx: &mut Context, cube: &Mesh, view_projection: glam::Mat4) -> GameResult {
+    /// let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+    /// canvas.set_projection(view_projection);
+    /// canvas.set_depth_mode(DepthMode::default());
+    /// canvas.draw(cube, DrawParam::default());
+    /// canvas.finish(ctx)
+    /// # }
+    /// ```
+    pub fn set_depth_mode(&mut self, mode: impl Into<Option<DepthMode>>) {
+        self.state.depth = mode.into();
+        self.depth_tested |= self.state.depth.is_some();
+    }
+
+    /// Returns the depth test as set by [`Canvas::set_depth_mode`].
+    #[inline]
+    pub fn depth_mode(&self) -> Option<DepthMode> {
+        self.state.depth
+    }
+
+    fn masking(&self) -> MaskState {
+        match (self.mask_mode, self.mask_reference) {
+            (MaskMode::Off, _) | (_, 0) => MaskState::Off,
+            (MaskMode::Inside, reference) => MaskState::Inside(reference),
+            (MaskMode::Outside, reference) => MaskState::Outside(reference),
+        }
+    }
+
     /// Draws the given `Drawable` to the canvas with a given `DrawParam`.
     #[inline]
     pub fn draw(&mut self, drawable: &impl Drawable, param: impl Into<DrawParam>) {
@@ -389,7 +504,7 @@ impl Canvas {
         instances: &InstanceArray,
         param: impl Into<DrawParam>,
     ) {
-        instances.flush_wgpu(&self.wgpu).unwrap(); // Will only fail if you can't lock the buffers shouldn't happen
+        instances.flush_wgpu(&self.wgpu, &self.counters).unwrap(); // Will only fail if you can't lock the buffers shouldn't happen
         self.push_draw(
             Draw::MeshInstances {
                 mesh,
@@ -422,10 +537,23 @@ impl Canvas {
     }
 
     fn finalize(&mut self, gfx: &mut GraphicsContext) -> GameResult {
+        let depth_stencil = (self.mask_reference != 0 || self.depth_tested).then(|| {
+            let key = (
+                self.target.width(),
+                self.target.height(),
+                self.target.samples(),
+            );
+            gfx.depth_stencils
+                .entry(key)
+                .or_insert_with(|| create_depth_stencil(&gfx.wgpu.device, key))
+                .clone()
+        });
+        let depth_stencil = depth_stencil.as_ref().map(|view| view.handle.as_ref());
+
         let mut canvas = if let Some(resolve) = &self.resolve {
-            InternalCanvas::from_msaa(gfx, self.clear, &self.target, resolve)?
+            InternalCanvas::from_msaa(gfx, self.clear, &self.target, resolve, depth_stencil)?
         } else {
-            InternalCanvas::from_image(gfx, self.clear, &self.target)?
+            InternalCanvas::from_image(gfx, self.clear, &self.target, depth_stencil)?
         };
 
         let mut state = self.state.clone();
@@ -444,6 +572,8 @@ impl Canvas {
         canvas.set_sampler(state.sampler);
         canvas.set_blend_mode(state.blend_mode);
         canvas.set_projection(state.projection);
+        canvas.set_mask(state.mask);
+        canvas.set_depth(state.depth);
 
         if state.scissor_rect.2 > 0 && state.scissor_rect.3 > 0 {
             canvas.set_scissor_rect(state.scissor_rect);
@@ -493,11 +623,19 @@ impl Canvas {
                     canvas.set_scissor_rect(draw.state.scissor_rect);
                 }
 
+                if draw.state.mask != state.mask {
+                    canvas.set_mask(draw.state.mask);
+                }
+
+                if draw.state.depth != state.depth {
+                    canvas.set_depth(draw.state.depth);
+                }
+
                 state = draw.state.clone();
 
                 match &draw.draw {
                     Draw::Mesh { mesh, image, scale } => {
-                        canvas.draw_mesh(mesh, image, draw.param, *scale)
+                        canvas.draw_mesh(mesh, image, draw.param, *scale)?
                     }
                     Draw::MeshInstances {
                         mesh,
@@ -526,6 +664,91 @@ struct DrawState {
     premul_text: bool,
     projection: mint::ColumnMatrix4<f32>,
     scissor_rect: (u32, u32, u32, u32),
+    mask: MaskState,
+    depth: Option<DepthMode>,
+}
+
+/// Which draws are restricted by the mask of a [`Canvas`]; see [`Canvas::begin_mask`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MaskMode {
+    /// Draw only inside the mask.
+    #[default]
+    Inside,
+    /// Draw only outside the mask.
+    Outside,
+    /// Draw regardless of the mask.
+    Off,
+}
+
+/// Depth test of draws on a [`Canvas`]; see [`Canvas::set_depth_mode`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DepthMode {
+    /// How the depth of a fragment compares to the depth buffer for the fragment to be drawn.
+    pub compare: CompareFunction,
+    /// Whether drawn fragments write their depth to the depth buffer.
+    pub write: bool,
+}
+
+impl DepthMode {
+    /// Creates a depth test with the given comparison, writing depth.
+    pub const fn new(compare: CompareFunction) -> Self {
+        DepthMode {
+            compare,
+            write: true,
+        }
+    }
+
+    /// Sets whether drawn fragments write their depth, e.g. `false` for transparent draws that
+    /// shouldn't hide what's drawn behind them later.
+    #[must_use]
+    pub fn with_write(mut self, write: bool) -> Self {
+        self.write = write;
+        self
+    }
+}
+
+impl Default for DepthMode {
+    /// Draws fragments at least as close as what's already drawn; `LessEqual` rather than `Less`
+    /// so that flat 2D draws, which all have depth `0.0`, are drawn in order on top of each other.
+    fn default() -> Self {
+        DepthMode::new(CompareFunction::LessEqual)
+    }
+}
+
+/// How draws interact with the stencil buffer, holding the stencil value of the mask.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum MaskState {
+    Off,
+    Write(u8),
+    Inside(u8),
+    Outside(u8),
+}
+
+impl MaskState {
+    pub fn writes(self) -> bool {
+        matches!(self, MaskState::Write(_))
+    }
+
+    pub fn stencil_state(self) -> wgpu::StencilFaceState {
+        let (compare, pass_op) = match self {
+            MaskState::Off => (wgpu::CompareFunction::Always, wgpu::StencilOperation::Keep),
+            MaskState::Write(_) => (
+                wgpu::CompareFunction::Always,
+                wgpu::StencilOperation::Replace,
+            ),
+            MaskState::Inside(_) => (wgpu::CompareFunction::Equal, wgpu::StencilOperation::Keep),
+            MaskState::Outside(_) => (
+                wgpu::CompareFunction::NotEqual,
+                wgpu::StencilOperation::Keep,
+            ),
+        };
+        wgpu::StencilFaceState {
+            compare,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -573,6 +796,7 @@ pub fn default_shader() -> Shader {
     Shader {
         fs_module: None,
         vs_module: None,
+        vertex_layout: None,
     }
 }
 
@@ -581,5 +805,196 @@ pub fn default_text_shader() -> Shader {
     Shader {
         fs_module: None,
         vs_module: None,
+        vertex_layout: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        context::{test_context, test_pixel},
+        graphics::{DrawMode, ImageFormat, Quad, ShaderBuilder, Vertex3d, VertexLayout},
+        Context,
+    };
+
+    #[test]
+    fn masks() {
+        let mut ctx = test_context("ggez_mask_test");
+        let circle =
+            Mesh::new_circle(&ctx, DrawMode::fill(), [32., 32.], 16., 0.5, Color::WHITE).unwrap();
+        // the right pixel is transparent
+        let half = Image::from_pixels(
+            &ctx,
+            &[255, 255, 255, 255, 0, 0, 0, 0],
+            ImageFormat::Rgba8UnormSrgb,
+            2,
+            1,
+        );
+        let full = DrawParam::new().dest_rect(Rect::new(0., 0., 64., 64.));
+
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_frame(&ctx, Color::BLACK);
+        canvas.begin_mask();
+        canvas.draw(&circle, DrawParam::new());
+        canvas.end_mask();
+        canvas.draw(&Quad, full.color(Color::WHITE));
+        canvas.set_mask_mode(MaskMode::Outside);
+        canvas.draw(&Quad, full.color(Color::GREEN));
+        canvas.finish(&mut ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        let pixels = ctx.gfx.frame().to_pixels(&ctx).unwrap();
+        assert_eq!(test_pixel(&pixels, 32, 32), [255, 255, 255]);
+        assert_eq!(test_pixel(&pixels, 2, 2)[1], 255);
+        assert_eq!(test_pixel(&pixels, 2, 2)[0], 0);
+
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_frame(&ctx, Color::BLACK);
+        canvas.set_sampler(Sampler::nearest_clamp());
+        canvas.begin_mask();
+        canvas.draw(&half, DrawParam::new().scale([32., 64.]));
+        canvas.end_mask();
+        canvas.draw(&Quad, full.color(Color::WHITE));
+        canvas.finish(&mut ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        let pixels = ctx.gfx.frame().to_pixels(&ctx).unwrap();
+        assert_eq!(test_pixel(&pixels, 16, 32), [255, 255, 255]);
+        assert_eq!(test_pixel(&pixels, 48, 32), [0, 0, 0]);
+    }
+
+    #[test]
+    fn depth() {
+        let mut ctx = test_context("ggez_depth_test");
+        let quad = |ctx: &Context, w: f32, z: f32, color: Color| {
+            let color = color.into();
+            let vertex = |x, y| Vertex3d {
+                position: [x, y, z],
+                uv: [0., 0.],
+                color,
+            };
+            Mesh::from_data_3d(
+                ctx,
+                &[
+                    vertex(0., 0.),
+                    vertex(w, 0.),
+                    vertex(w, 64.),
+                    vertex(0., 64.),
+                ],
+                &[0, 1, 2, 0, 2, 3],
+            )
+        };
+        // the screen projection maps z from 0 (near) to -1 (far)
+        let near = quad(&ctx, 32., -0.2, Color::WHITE);
+        let far = quad(&ctx, 64., -0.8, Color::GREEN);
+
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_frame(&ctx, Color::BLACK);
+        canvas.set_depth_mode(DepthMode::default());
+        canvas.draw(&near, DrawParam::new());
+        canvas.draw(&far, DrawParam::new());
+        canvas.set_depth_mode(None);
+        canvas.draw(&Quad, DrawParam::new().dest([60., 0.]).scale([4., 64.]));
+        canvas.finish(&mut ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        let pixels = ctx.gfx.frame().to_pixels(&ctx).unwrap();
+        let pixel = |x| test_pixel(&pixels, x, 32);
+        assert_eq!(pixel(16), [255, 255, 255]);
+        assert_eq!(pixel(48)[1], 255);
+        assert_eq!(pixel(48)[0], 0);
+        assert_eq!(pixel(62), [255, 255, 255]);
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+    struct GreenVertex {
+        position: [f32; 2],
+        green: f32,
+    }
+
+    impl VertexLayout for GreenVertex {
+        fn layout() -> wgpu::VertexBufferLayout<'static> {
+            const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+                wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32];
+            wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<GreenVertex>() as u64,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &ATTRIBUTES,
+            }
+        }
+    }
+
+    #[test]
+    fn custom_vertices() {
+        const SHADER: &str = "
+            struct VertexOutput {
+                @builtin(position) position: vec4<f32>,
+                @location(0) uv: vec2<f32>,
+                @location(1) color: vec4<f32>,
+            }
+
+            struct DrawUniforms {
+                color: vec4<f32>,
+                src_rect: vec4<f32>,
+                transform: mat4x4<f32>,
+            }
+
+            @group(0) @binding(0)
+            var<uniform> uniforms: DrawUniforms;
+
+            @vertex
+            fn vs_main(@location(0) position: vec2<f32>, @location(1) green: f32) -> VertexOutput {
+                var out: VertexOutput;
+                out.position = uniforms.transform * vec4<f32>(position, 0.0, 1.0);
+                out.uv = vec2<f32>(0.0, 0.0);
+                out.color = vec4<f32>(0.0, green, 0.0, 1.0);
+                return out;
+            }
+        ";
+
+        let mut ctx = test_context("ggez_vertex_layout_test");
+        let vertex = |x, y| GreenVertex {
+            position: [x, y],
+            green: 1.,
+        };
+        let mesh = Mesh::from_vertices(
+            &ctx,
+            &[
+                vertex(0., 0.),
+                vertex(64., 0.),
+                vertex(64., 64.),
+                vertex(0., 64.),
+            ],
+            &[0, 1, 2, 0, 2, 3],
+        );
+        assert_eq!(mesh.dimensions(&ctx), None);
+        let shader = ShaderBuilder::new()
+            .vertex_code(SHADER)
+            .vertex_layout::<GreenVertex>()
+            .build(&ctx)
+            .unwrap();
+
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_frame(&ctx, Color::BLACK);
+        canvas.set_shader(&shader);
+        canvas.draw(&mesh, DrawParam::new());
+        canvas.finish(&mut ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        let pixels = ctx.gfx.frame().to_pixels(&ctx).unwrap();
+        assert_eq!(test_pixel(&pixels, 32, 32)[..2], [0, 255]);
+
+        // neither the default shaders nor shaders of other layouts draw the mesh
+        ctx.gfx.begin_frame().unwrap();
+        let mut canvas = Canvas::from_frame(&ctx, None);
+        canvas.draw(&mesh, DrawParam::new());
+        assert!(canvas.finish(&mut ctx).is_err());
+        let mut canvas = Canvas::from_frame(&ctx, None);
+        canvas.set_shader(&shader);
+        canvas.draw(&Quad, DrawParam::new());
+        assert!(canvas.finish(&mut ctx).is_err());
+        ctx.gfx.end_frame().unwrap();
     }
 }
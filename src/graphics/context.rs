@@ -1,4 +1,5 @@
 use super::{
+    debug_draw::DebugDraw,
     draw::DrawUniforms,
     gpu::{
         arc::{
@@ -6,12 +7,14 @@ use super::{
             ArcShaderModule, ArcTextureView,
         },
         bind_group::{BindGroupCache, BindGroupEntryKey},
+        counters::RenderCounters,
         growing::GrowingBufferArena,
         pipeline::PipelineCache,
         text::TextRenderer,
+        timer::GpuTimer,
     },
     image::{Image, ImageFormat},
-    mesh::{Mesh, Vertex},
+    mesh::{Mesh, Vertex, VertexLayout},
     sampler::{Sampler, SamplerCache},
     text::FontData,
     MeshData, ScreenImage,
@@ -38,8 +41,8 @@ pub(crate) struct FrameContext {
     pub cmd: wgpu::CommandEncoder,
     pub present: Image,
     pub arenas: FrameArenas,
-    pub frame: wgpu::SurfaceTexture,
-    pub frame_view: wgpu::TextureView,
+    /// The swapchain image and its view; `None` for headless contexts.
+    pub frame: Option<(wgpu::SurfaceTexture, wgpu::TextureView)>,
 }
 
 #[derive(Default)]
@@ -49,12 +52,37 @@ pub(crate) struct FrameArenas {
     pub bind_groups: TypedArena<ArcBindGroup>,
 }
 
+/// Counts of the render work done for a frame, returned by [`GraphicsContext::last_frame_stats`].
+///
+/// Work done between frames, such as creating a [`Mesh`] in `update`, counts towards the next frame.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FrameStats {
+    /// Number of draw calls, including those drawing text.
+    pub draw_calls: u64,
+    /// Number of instances drawn, counting each mesh, image and glyph drawn.
+    pub instances: u64,
+    /// Number of times the render pipeline changed.
+    pub pipeline_switches: u64,
+    /// Number of times a bind group changed, e.g. to draw with another image or uniforms.
+    pub bind_group_changes: u64,
+    /// Bytes of mesh and instance data uploaded.
+    pub vertex_bytes: u64,
+    /// Bytes of text vertex data uploaded.
+    pub text_bytes: u64,
+    /// Bytes of uniforms and text vertices uploaded into the per-frame buffer arenas.
+    pub arena_bytes: u64,
+    /// Number of uploads of glyphs missing from the glyph cache, after rasterizing them. Glyphs
+    /// missing from an empty cache are uploaded at once.
+    pub glyph_cache_misses: u64,
+}
+
 /// WGPU graphics context objects.
 #[derive(Debug)]
 #[allow(missing_docs)]
 pub struct WgpuContext {
     pub instance: wgpu::Instance,
-    pub surface: wgpu::Surface,
+    /// The window surface; `None` for headless contexts.
+    pub surface: Option<wgpu::Surface>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
 }
@@ -64,7 +92,7 @@ pub struct WgpuContext {
 pub struct GraphicsContext {
     pub(crate) wgpu: Arc<WgpuContext>,
 
-    pub(crate) window: winit::window::Window,
+    pub(crate) window: Option<winit::window::Window>,
     pub(crate) surface_config: wgpu::SurfaceConfiguration,
 
     pub(crate) bind_group_cache: BindGroupCache,
@@ -78,10 +106,16 @@ pub struct GraphicsContext {
     pub(crate) frame_msaa_image: Option<Image>,
 
     pub(crate) fcx: Option<FrameContext>,
+    pub(crate) debug_draw: DebugDraw,
+    pub(crate) counters: Arc<RenderCounters>,
+    pub(crate) last_stats: FrameStats,
+    pub(crate) gpu_timer: Option<GpuTimer>,
     pub(crate) text: TextRenderer,
     pub(crate) fonts: HashMap<String, FontId>,
     pub(crate) staging_belt: wgpu::util::StagingBelt,
     pub(crate) uniform_arena: GrowingBufferArena,
+    // depth and stencil attachments of canvases with masks or depth testing, by width, height and sample count
+    pub(crate) depth_stencils: HashMap<(u32, u32, u32), ArcTextureView>,
 
     pub(crate) draw_shader: ArcShaderModule,
     pub(crate) instance_shader: ArcShaderModule,
@@ -105,6 +139,31 @@ impl GraphicsContext {
         event_loop: &winit::event_loop::EventLoop<()>,
         conf: &Conf,
         filesystem: &Filesystem,
+    ) -> GameResult<Self> {
+        Self::with_backends(conf, |instance| {
+            Self::new_from_instance(game_id, instance, event_loop, conf, filesystem)
+        })
+    }
+
+    /// Create a new graphics context that has no window or surface.
+    ///
+    /// Rendering works as usual with canvases targeting an [`Image`] (or the default frame
+    /// image returned by [`GraphicsContext::frame`]), and the results can be read back with
+    /// [`Image::to_pixels`] or [`Image::encode`]. Nothing is ever presented to a screen.
+    ///
+    /// The size of the frame image is taken from `conf.window_mode`.
+    /// If no hardware adapter is available, a fallback (software) adapter is used instead, if there is one.
+    pub fn new_headless(conf: &Conf, filesystem: &Filesystem) -> GameResult<Self> {
+        Self::with_backends(conf, |instance| {
+            Self::new_headless_from_instance(instance, conf, filesystem)
+        })
+    }
+
+    /// Creates a wgpu instance for the backends selected in `conf` and hands it to `create`,
+    /// retrying with the secondary backends if `Backend::All` was selected and the primary ones failed.
+    fn with_backends(
+        conf: &Conf,
+        mut create: impl FnMut(wgpu::Instance) -> GameResult<Self>,
     ) -> GameResult<Self> {
         let new_instance = |backends| {
             wgpu::Instance::new(wgpu::InstanceDescriptor {
@@ -114,13 +173,7 @@ impl GraphicsContext {
         };
 
         if conf.backend == Backend::All {
-            match Self::new_from_instance(
-                game_id,
-                new_instance(wgpu::Backends::PRIMARY),
-                event_loop,
-                conf,
-                filesystem,
-            ) {
+            match create(new_instance(wgpu::Backends::PRIMARY)) {
                 Ok(o) => Ok(o),
                 Err(GameError::GraphicsInitializationError) => {
                     println!(
@@ -130,13 +183,7 @@ impl GraphicsContext {
                         "Failed to initialize graphics, trying secondary backends.. Please mention this if you encounter any bugs!"
                     );
 
-                    Self::new_from_instance(
-                        game_id,
-                        new_instance(wgpu::Backends::SECONDARY),
-                        event_loop,
-                        conf,
-                        filesystem,
-                    )
+                    create(new_instance(wgpu::Backends::SECONDARY))
                 }
                 Err(e) => Err(e),
             }
@@ -152,7 +199,7 @@ impl GraphicsContext {
                 Backend::BrowserWebGpu => wgpu::Backends::BROWSER_WEBGPU,
             });
 
-            Self::new_from_instance(game_id, instance, event_loop, conf, filesystem)
+            create(instance)
         }
     }
 
@@ -284,39 +331,9 @@ impl GraphicsContext {
         }))
         .ok_or(GameError::GraphicsInitializationError)?;
 
-        // One instance is 96 bytes, and we allow 1 million of them, for a total of 96MB (default being 128MB).
-        const MAX_INSTANCES: u32 = 1_000_000;
-        const INSTANCE_BUFFER_SIZE: u32 = 96 * MAX_INSTANCES;
+        let (device, queue) = request_device(&adapter)?;
 
-        let (device, queue) = pollster::block_on(adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                label: None,
-                features: wgpu::Features::default(),
-                limits: wgpu::Limits {
-                    // 1st: DrawParams
-                    // 2nd: Texture + Sampler
-                    // 3rd: InstanceArray
-                    // 4th: ShaderParams
-                    max_bind_groups: 4,
-                    // InstanceArray uses 2 storage buffers.
-                    max_storage_buffers_per_shader_stage: 2,
-                    max_storage_buffer_binding_size: INSTANCE_BUFFER_SIZE,
-                    max_texture_dimension_1d: 8192,
-                    max_texture_dimension_2d: 8192,
-                    ..wgpu::Limits::downlevel_webgl2_defaults()
-                },
-            },
-            None,
-        ))?;
-
-        let wgpu = Arc::new(WgpuContext {
-            instance,
-            surface,
-            device,
-            queue,
-        });
-
-        let capabilities = wgpu.surface.get_capabilities(&adapter);
+        let capabilities = surface.get_capabilities(&adapter);
 
         let size = window.inner_size();
         let surface_config = wgpu::SurfaceConfiguration {
@@ -333,8 +350,65 @@ impl GraphicsContext {
             view_formats: vec![],
         };
 
-        wgpu.surface.configure(&wgpu.device, &surface_config);
+        surface.configure(&device, &surface_config);
+
+        let wgpu = Arc::new(WgpuContext {
+            instance,
+            surface: Some(surface),
+            device,
+            queue,
+        });
+
+        Self::from_parts(wgpu, Some(window), surface_config, conf, filesystem)
+    }
+
+    pub(crate) fn new_headless_from_instance(
+        instance: wgpu::Instance,
+        conf: &Conf,
+        filesystem: &Filesystem,
+    ) -> GameResult<Self> {
+        let request_adapter = |force_fallback_adapter| {
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter,
+                compatible_surface: None,
+            }))
+        };
+
+        let adapter = request_adapter(false)
+            .or_else(|| request_adapter(true))
+            .ok_or(GameError::GraphicsInitializationError)?;
+
+        let (device, queue) = request_device(&adapter)?;
+
+        let size = conf.window_mode.actual_size()?.to_physical::<u32>(1.0);
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: ImageFormat::Rgba8UnormSrgb,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: wgpu::PresentMode::AutoNoVsync,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+        };
+
+        let wgpu = Arc::new(WgpuContext {
+            instance,
+            surface: None,
+            device,
+            queue,
+        });
 
+        Self::from_parts(wgpu, None, surface_config, conf, filesystem)
+    }
+
+    fn from_parts(
+        wgpu: Arc<WgpuContext>,
+        window: Option<winit::window::Window>,
+        surface_config: wgpu::SurfaceConfiguration,
+        conf: &Conf,
+        filesystem: &Filesystem,
+    ) -> GameResult<Self> {
         let mut bind_group_cache = BindGroupCache::new();
         let pipeline_cache = PipelineCache::new();
         let sampler_cache = SamplerCache::new();
@@ -439,6 +513,8 @@ impl GraphicsContext {
         let white_image =
             Image::from_pixels_wgpu(&wgpu, &[255, 255, 255, 255], ImageFormat::Rgba8Unorm, 1, 1);
 
+        let gpu_timer = GpuTimer::new(&wgpu.device, &wgpu.queue);
+
         let mut this = GraphicsContext {
             wgpu,
 
@@ -460,6 +536,7 @@ impl GraphicsContext {
             fonts: HashMap::new(),
             staging_belt,
             uniform_arena,
+            depth_stencils: HashMap::new(),
             draw_shader,
             instance_shader,
             instance_unordered_shader,
@@ -471,6 +548,10 @@ impl GraphicsContext {
 
             fs: InternalClone::clone(filesystem),
 
+            debug_draw: DebugDraw::new(),
+            counters: Arc::default(),
+            last_stats: FrameStats::default(),
+            gpu_timer,
             bind_group: None,
         };
 
@@ -500,6 +581,12 @@ impl GraphicsContext {
         &self.wgpu
     }
 
+    /// Returns whether this context was created without a window, see [`GraphicsContext::new_headless`].
+    #[inline]
+    pub fn is_headless(&self) -> bool {
+        self.window.is_none()
+    }
+
     /// Sets the image that will be presented to the screen at the end of the frame.
     pub fn present(&mut self, image: &Image) -> GameResult {
         if let Some(fcx) = &mut self.fcx {
@@ -512,6 +599,31 @@ impl GraphicsContext {
         }
     }
 
+    /// Returns the image that will be presented at the end of the frame, for overlays drawn
+    /// on top of everything else, or an error if it can't be drawn onto.
+    pub(crate) fn presented_image(&self) -> GameResult<Image> {
+        let present = match &self.fcx {
+            Some(fcx) => fcx.present.clone(),
+            None => {
+                return Err(GameError::RenderError(String::from(
+                    "there is no presented image outside of a frame",
+                )))
+            }
+        };
+        if present.samples() > 1
+            || !present
+                .texture
+                .handle
+                .usage()
+                .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+        {
+            return Err(GameError::RenderError(String::from(
+                "the presented image can't be drawn onto; create it with Image::new_canvas_image",
+            )));
+        }
+        Ok(present)
+    }
+
     /// Adds a new `font` with a given `name`.
     #[allow(unused_results)]
     pub fn add_font(&mut self, name: &str, font: FontData) {
@@ -520,9 +632,19 @@ impl GraphicsContext {
     }
 
     /// Returns the size of the window’s underlying drawable in physical pixels as (width, height).
+    ///
+    /// For headless contexts this is the size of the frame image.
     pub fn drawable_size(&self) -> (f32, f32) {
-        let size = self.window.inner_size();
-        (size.width as f32, size.height as f32)
+        match &self.window {
+            Some(window) => {
+                let size = window.inner_size();
+                (size.width as f32, size.height as f32)
+            }
+            None => (
+                self.surface_config.width as f32,
+                self.surface_config.height as f32,
+            ),
+        }
     }
 
     /// Sets the window size (in physical pixels) / resolution to the specified width and height.
@@ -535,19 +657,21 @@ impl GraphicsContext {
 
     /// Sets the window title.
     pub fn set_window_title(&self, title: &str) {
-        self.window.set_title(title);
+        if let Some(window) = &self.window {
+            window.set_title(title);
+        }
     }
 
     /// Returns the position of the system window, including the outer frame.
     pub fn window_position(&self) -> GameResult<PhysicalPosition<i32>> {
-        self.window
+        self.try_window()?
             .outer_position()
             .map_err(|e| GameError::WindowError(e.to_string()))
     }
 
     /// Sets the window position.
     pub fn set_window_position(&self, position: impl Into<winit::dpi::Position>) -> GameResult {
-        self.window.set_outer_position(position);
+        self.try_window()?.set_outer_position(position);
         Ok(())
     }
 
@@ -555,23 +679,45 @@ impl GraphicsContext {
     /// including borders, titlebar, etc.
     /// Returns zeros if the window doesn't exist.
     pub fn size(&self) -> (f32, f32) {
-        let size = self.window.outer_size();
-        (size.width as f32, size.height as f32)
+        self.window.as_ref().map_or((0., 0.), |window| {
+            let size = window.outer_size();
+            (size.width as f32, size.height as f32)
+        })
     }
 
     /// Returns an iterator providing all resolutions supported by the current monitor.
-    pub fn supported_resolutions(&self) -> impl Iterator<Item = winit::dpi::PhysicalSize<u32>> {
+    ///
+    /// The iterator is empty for headless contexts.
+    pub fn supported_resolutions(
+        &self,
+    ) -> impl Iterator<Item = winit::dpi::PhysicalSize<u32>> + '_ {
         self.window
-            .current_monitor()
-            .unwrap() // Unwrap is fine current monitor should always exist
-            .video_modes()
+            .iter()
+            .flat_map(|window| {
+                window
+                    .current_monitor()
+                    .unwrap() // Unwrap is fine current monitor should always exist
+                    .video_modes()
+            })
             .map(|vm| vm.size())
     }
 
     /// Returns a reference to the Winit window.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context is headless, see [`GraphicsContext::is_headless`].
     #[inline]
     pub fn window(&self) -> &winit::window::Window {
-        &self.window
+        self.window
+            .as_ref()
+            .expect("a headless graphics context has no window")
+    }
+
+    pub(crate) fn try_window(&self) -> GameResult<&winit::window::Window> {
+        self.window.as_ref().ok_or_else(|| {
+            GameError::WindowError(String::from("a headless graphics context has no window"))
+        })
     }
 
     /// Sets the window icon. `None` for path removes the icon.
@@ -585,7 +731,7 @@ impl GraphicsContext {
             Some(p) => Some(load_icon(p.as_ref(), filesystem)?),
             None => None,
         };
-        self.window.set_window_icon(icon);
+        self.try_window()?.set_window_icon(icon);
         Ok(())
     }
 
@@ -617,6 +763,10 @@ impl GraphicsContext {
             mode.fullscreen_type = old_fullscreen;
         }
         self.window_mode = mode;
+        if self.window.is_none() {
+            // There is no window to send us a resize event, so the frame image has to be resized right away.
+            self.update_frame_image();
+        }
         result
     }
 
@@ -628,6 +778,31 @@ impl GraphicsContext {
         self.frame_image.as_ref().unwrap(/* invariant */)
     }
 
+    /// Enables or disables measuring GPU frame times, if the device supports it.
+    pub(crate) fn set_gpu_timing(&mut self, enabled: bool) {
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.enabled = enabled;
+        }
+    }
+
+    /// Returns the latest GPU frame time measured, which is usually from a previous frame.
+    pub(crate) fn gpu_frame_time(&self) -> Option<std::time::Duration> {
+        self.gpu_timer.as_ref().and_then(GpuTimer::last)
+    }
+
+    /// Returns the render statistics of the last frame that ended, e.g. to check how many draw calls
+    /// a scene takes.
+    #[inline]
+    pub fn last_frame_stats(&self) -> FrameStats {
+        self.last_stats
+    }
+
+    /// Returns the queue of debug shapes, which are drawn over the frame when it ends.
+    #[inline]
+    pub fn debug_draw(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw
+    }
+
     /// Returns the image format of the window surface.
     #[inline]
     pub fn surface_format(&self) -> ImageFormat {
@@ -649,34 +824,42 @@ impl GraphicsContext {
             )));
         }
 
-        let size = self.window.inner_size();
-        let frame = match self.wgpu.surface.get_current_texture() {
-            Ok(frame) => Ok(frame),
-            Err(_) => {
-                self.surface_config.width = size.width.max(1);
-                self.surface_config.height = size.height.max(1);
-                self.wgpu
-                    .surface
-                    .configure(&self.wgpu.device, &self.surface_config);
-                self.wgpu.surface.get_current_texture().map_err(|_| {
-                    GameError::RenderError(String::from("failed to get next swapchain image"))
-                })
-            }
-        }?;
+        let frame = if let (Some(window), Some(surface)) = (&self.window, &self.wgpu.surface) {
+            let size = window.inner_size();
+            let frame = match surface.get_current_texture() {
+                Ok(frame) => Ok(frame),
+                Err(_) => {
+                    self.surface_config.width = size.width.max(1);
+                    self.surface_config.height = size.height.max(1);
+                    surface.configure(&self.wgpu.device, &self.surface_config);
+                    surface.get_current_texture().map_err(|_| {
+                        GameError::RenderError(String::from("failed to get next swapchain image"))
+                    })
+                }
+            }?;
 
-        let frame_view = frame
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+            let frame_view = frame
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
+
+            Some((frame, frame_view))
+        } else {
+            None
+        };
+
+        let mut cmd = self
+            .wgpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.begin(&mut cmd);
+        }
 
         self.fcx = Some(FrameContext {
-            cmd: self
-                .wgpu
-                .device
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default()),
+            cmd,
             present: self.frame().clone(),
             arenas: FrameArenas::default(),
             frame,
-            frame_view,
         });
 
         self.uniform_arena.free();
@@ -690,60 +873,79 @@ impl GraphicsContext {
     ///
     /// The only situation you need to call this in is when you are rolling your own event loop.
     pub fn end_frame(&mut self) -> GameResult {
+        if self.fcx.is_some() {
+            let mut debug_draw = std::mem::replace(&mut self.debug_draw, DebugDraw::new());
+            debug_draw.flush(self);
+            self.debug_draw = debug_draw;
+        }
+
         if let Some(mut fcx) = self.fcx.take() {
-            let mut present_pass = fcx.cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &fcx.frame_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: true,
+            if let Some((_, frame_view)) = &fcx.frame {
+                let mut present_pass = fcx.cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: frame_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+
+                let sampler = &mut self
+                    .sampler_cache
+                    .get(&self.wgpu.device, Sampler::default());
+
+                let (bind, layout) = self.bind_group(fcx.present.view, sampler.clone());
+
+                let layout = self.pipeline_cache.layout(&self.wgpu.device, &[layout]);
+                let copy = self.pipeline_cache.render_pipeline(
+                    &self.wgpu.device,
+                    &layout,
+                    RenderPipelineInfo {
+                        vs: self.copy_shader.clone(),
+                        fs: self.copy_shader.clone(),
+                        vs_entry: "vs_main".into(),
+                        fs_entry: "fs_main".into(),
+                        samples: 1,
+                        format: self.surface_config.format,
+                        blend: None,
+                        depth: None,
+                        stencil: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                        vertices: false,
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        vertex_layout: Vertex::layout(),
                     },
-                })],
-                depth_stencil_attachment: None,
-            });
+                );
+
+                let copy = fcx.arenas.render_pipelines.alloc(copy);
+                let bind = fcx.arenas.bind_groups.alloc(bind);
+
+                present_pass.set_pipeline(copy);
+                present_pass.set_bind_group(0, bind, &[]);
+                present_pass.draw(0..3, 0..1);
+            }
 
-            let sampler = &mut self
-                .sampler_cache
-                .get(&self.wgpu.device, Sampler::default());
-
-            let (bind, layout) = self.bind_group(fcx.present.view, sampler.clone());
-
-            let layout = self.pipeline_cache.layout(&self.wgpu.device, &[layout]);
-            let copy = self.pipeline_cache.render_pipeline(
-                &self.wgpu.device,
-                &layout,
-                RenderPipelineInfo {
-                    vs: self.copy_shader.clone(),
-                    fs: self.copy_shader.clone(),
-                    vs_entry: "vs_main".into(),
-                    fs_entry: "fs_main".into(),
-                    samples: 1,
-                    format: self.surface_config.format,
-                    blend: None,
-                    depth: false,
-                    vertices: false,
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    vertex_layout: Vertex::layout(),
-                },
-            );
-
-            let copy = fcx.arenas.render_pipelines.alloc(copy);
-            let bind = fcx.arenas.bind_groups.alloc(bind);
-
-            present_pass.set_pipeline(copy);
-            present_pass.set_bind_group(0, bind, &[]);
-            present_pass.draw(0..3, 0..1);
-
-            std::mem::drop(present_pass);
+            if let Some(timer) = &mut self.gpu_timer {
+                timer.end(&mut fcx.cmd);
+            }
 
             self.staging_belt.finish();
             let _ = self.wgpu.queue.submit([fcx.cmd.finish()]);
-            fcx.frame.present();
+            if let Some((frame, _)) = fcx.frame {
+                frame.present();
+            }
 
             self.staging_belt.recall();
 
+            if let Some(timer) = &mut self.gpu_timer {
+                timer.submitted(&self.wgpu.device);
+            }
+            self.last_stats = self.counters.take();
+
             Ok(())
         } else {
             Err(GameError::RenderError(String::from(
@@ -752,14 +954,14 @@ impl GraphicsContext {
         }
     }
 
-    pub(crate) fn resize(&mut self, _new_size: dpi::PhysicalSize<u32>) {
-        let size = self.window.inner_size();
+    pub(crate) fn resize(&mut self, new_size: dpi::PhysicalSize<u32>) {
+        let size = self.window.as_ref().map_or(new_size, |w| w.inner_size());
         let _ = self.wgpu.device.poll(wgpu::Maintain::Wait);
         self.surface_config.width = size.width.max(1);
         self.surface_config.height = size.height.max(1);
-        self.wgpu
-            .surface
-            .configure(&self.wgpu.device, &self.surface_config);
+        if let Some(surface) = &self.wgpu.surface {
+            surface.configure(&self.wgpu.device, &self.surface_config);
+        }
         self.update_frame_image();
     }
 
@@ -775,10 +977,21 @@ impl GraphicsContext {
         let mut frame_msaa = self.frame_msaa.take().unwrap(/* invariant */);
         self.frame_msaa_image = Some(frame_msaa.image(self));
         self.frame_msaa = Some(frame_msaa);
+
+        // the old sizes are likely unused now
+        self.depth_stencils.clear();
     }
 
     pub(crate) fn set_window_mode(&mut self, mode: &WindowMode) -> GameResult {
-        let window = &mut self.window;
+        let window = match &mut self.window {
+            Some(window) => window,
+            None => {
+                let size = mode.actual_size()?.to_physical::<u32>(1.0);
+                self.surface_config.width = size.width.max(1);
+                self.surface_config.height = size.height.max(1);
+                return Ok(());
+            }
+        };
 
         // TODO LATER: find out if single-dimension constraints are possible?
         let min_dimensions = if mode.min_width >= 1.0 && mode.min_height >= 1.0 {
@@ -850,14 +1063,42 @@ impl GraphicsContext {
         self.surface_config.width = size.width.max(1);
         self.surface_config.height = size.height.max(1);
 
-        self.wgpu
-            .surface
-            .configure(&self.wgpu.device, &self.surface_config);
+        if let Some(surface) = &self.wgpu.surface {
+            surface.configure(&self.wgpu.device, &self.surface_config);
+        }
 
         Ok(())
     }
 }
 
+fn request_device(adapter: &wgpu::Adapter) -> GameResult<(wgpu::Device, wgpu::Queue)> {
+    // One instance is 96 bytes, and we allow 1 million of them, for a total of 96MB (default being 128MB).
+    const MAX_INSTANCES: u32 = 1_000_000;
+    const INSTANCE_BUFFER_SIZE: u32 = 96 * MAX_INSTANCES;
+
+    Ok(pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            // used to measure GPU frame times for the profiler
+            features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+            limits: wgpu::Limits {
+                // 1st: DrawParams
+                // 2nd: Texture + Sampler
+                // 3rd: InstanceArray
+                // 4th: ShaderParams
+                max_bind_groups: 4,
+                // InstanceArray uses 2 storage buffers.
+                max_storage_buffers_per_shader_stage: 2,
+                max_storage_buffer_binding_size: INSTANCE_BUFFER_SIZE,
+                max_texture_dimension_1d: 8192,
+                max_texture_dimension_2d: 8192,
+                ..wgpu::Limits::downlevel_webgl2_defaults()
+            },
+        },
+        None,
+    ))?)
+}
+
 // This is kinda awful 'cause it copies a couple times,
 // but still better than
 // having `winit` try to do the image loading for us.
@@ -879,3 +1120,37 @@ pub(crate) fn load_icon(
         GameError::ResourceLoadError(msg)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        context::test_context,
+        graphics::{Canvas, Color, DrawParam, InstanceArray, Quad, Rect, Text},
+    };
+
+    #[test]
+    fn frame_stats() {
+        let mut ctx = test_context("ggez_frame_stats_test");
+        let mut instances = InstanceArray::new(&ctx, None);
+        instances.set((0..10).map(|i| DrawParam::new().dest([i as f32, 0.])));
+
+        for frame in 0..2 {
+            ctx.gfx.begin_frame().unwrap();
+            let mut canvas = Canvas::from_frame(&ctx, Color::BLACK);
+            canvas.draw(&Quad, DrawParam::new().dest_rect(Rect::new(0., 0., 4., 4.)));
+            canvas.draw(&Quad, DrawParam::new().dest_rect(Rect::new(8., 0., 4., 4.)));
+            canvas.draw(&instances, DrawParam::new());
+            canvas.draw(&Text::new("ab"), DrawParam::new());
+            canvas.finish(&mut ctx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+
+            let stats = ctx.gfx.last_frame_stats();
+            assert_eq!(stats.draw_calls, 4);
+            assert_eq!(stats.instances, 14);
+            assert!(stats.pipeline_switches >= 3);
+            assert!(stats.bind_group_changes > 0 && stats.arena_bytes > 0);
+            // the glyphs are cached in the first frame
+            assert_eq!(stats.glyph_cache_misses > 0, frame == 0);
+        }
+    }
+}
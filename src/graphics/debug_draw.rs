@@ -0,0 +1,356 @@
+use super::{
+    Camera2D, Canvas, Color, DrawMode, DrawParam, GraphicsContext, Mesh, MeshBuilder, Rect, Text,
+};
+use crate::GameResult;
+use glam::Vec2;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    Line(mint::Point2<f32>, mint::Point2<f32>),
+    Arrow(mint::Point2<f32>, mint::Point2<f32>),
+    Rect(Rect),
+    Circle(mint::Point2<f32>, f32),
+    Text(mint::Point2<f32>, String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Lifetime {
+    Frames(u32),
+    Until(Instant),
+}
+
+/// A shape queued on [`DebugDraw`]. Its methods change how it is drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugShape {
+    shape: Shape,
+    color: Color,
+    width: f32,
+    fill: bool,
+    screen: bool,
+    lifetime: Lifetime,
+}
+
+impl DebugShape {
+    fn new(shape: Shape, color: Color) -> Self {
+        DebugShape {
+            shape,
+            color,
+            width: 1.,
+            fill: false,
+            screen: false,
+            lifetime: Lifetime::Frames(1),
+        }
+    }
+
+    /// Sets the width of lines and outlines. The default is 1.0.
+    pub fn width(&mut self, width: f32) -> &mut Self {
+        self.width = width;
+        self
+    }
+
+    /// Fills rectangles and circles instead of drawing their outline.
+    pub fn filled(&mut self) -> &mut Self {
+        self.fill = true;
+        self
+    }
+
+    /// Draws the shape in screen pixels, instead of with the [`DebugDraw`] camera.
+    pub fn screen_space(&mut self) -> &mut Self {
+        self.screen = true;
+        self
+    }
+
+    /// Keeps drawing the shape for the given number of frames, instead of only the next one.
+    pub fn frames(&mut self, frames: u32) -> &mut Self {
+        self.lifetime = Lifetime::Frames(frames.max(1));
+        self
+    }
+
+    /// Keeps drawing the shape in every frame until the given time has passed.
+    pub fn duration(&mut self, duration: Duration) -> &mut Self {
+        self.lifetime = Lifetime::Until(Instant::now() + duration);
+        self
+    }
+}
+
+/// An immediate-mode queue of lines, rectangles, circles, arrows and text labels, drawn on top
+/// of the [presented image](GraphicsContext::present) when the frame ends; get it with
+/// [`GraphicsContext::debug_draw`].
+///
+/// Shapes are drawn once, in the frame they were queued in, unless given a longer lifetime.
+/// They are in world space, seen through the camera set with [`DebugDraw::set_camera`], unless
+/// drawn in [screen space](DebugShape::screen_space) or no camera is set.
+///
+/// Debug drawing is enabled in debug builds. When disabled, queuing shapes does nothing, so calls
+/// can be left in release builds at the cost of a branch each.
+///
+/// ```rust,no_run
+/// # use ggez::{graphics::{Color, Rect}, Context};
+/// # use std::time::Duration;
+/// # fn t(ctx: &mut Context) {
+/// let debug = ctx.gfx.debug_draw();
+/// debug.rect(Rect::new(10., 10., 32., 48.), Color::GREEN);
+/// debug.arrow([26., 34.], [60., 34.], Color::YELLOW).width(2.);
+/// debug
+///     .circle([100., 100.], 16., Color::RED)
+///     .filled()
+///     .duration(Duration::from_secs(1));
+/// debug.text([4., 4.], "collision!", Color::WHITE).screen_space();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DebugDraw {
+    enabled: bool,
+    camera: Option<Camera2D>,
+    shapes: Vec<DebugShape>,
+    // handed out instead of a queued shape while disabled
+    discarded: DebugShape,
+}
+
+impl DebugDraw {
+    pub(crate) fn new() -> Self {
+        DebugDraw {
+            enabled: cfg!(debug_assertions),
+            camera: None,
+            shapes: Vec::new(),
+            discarded: DebugShape::new(Shape::Rect(Rect::zero()), Color::WHITE),
+        }
+    }
+
+    /// Returns whether shapes are queued and drawn.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enables or disables debug drawing. Disabling it also removes all queued shapes.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.shapes.clear();
+        }
+    }
+
+    /// Sets the camera world space shapes are seen through, usually the one the world is drawn with.
+    ///
+    /// Without a camera, world space is the same as screen space.
+    pub fn set_camera(&mut self, camera: impl Into<Option<Camera2D>>) {
+        self.camera = camera.into();
+    }
+
+    /// Removes all queued shapes, including those with a lifetime left.
+    pub fn clear(&mut self) {
+        self.shapes.clear();
+    }
+
+    /// Queues a line between two points.
+    pub fn line(
+        &mut self,
+        from: impl Into<mint::Point2<f32>>,
+        to: impl Into<mint::Point2<f32>>,
+        color: Color,
+    ) -> &mut DebugShape {
+        self.push(Shape::Line(from.into(), to.into()), color)
+    }
+
+    /// Queues an arrow pointing from one point to another.
+    pub fn arrow(
+        &mut self,
+        from: impl Into<mint::Point2<f32>>,
+        to: impl Into<mint::Point2<f32>>,
+        color: Color,
+    ) -> &mut DebugShape {
+        self.push(Shape::Arrow(from.into(), to.into()), color)
+    }
+
+    /// Queues a rectangle.
+    pub fn rect(&mut self, rect: Rect, color: Color) -> &mut DebugShape {
+        self.push(Shape::Rect(rect), color)
+    }
+
+    /// Queues a circle.
+    pub fn circle(
+        &mut self,
+        center: impl Into<mint::Point2<f32>>,
+        radius: f32,
+        color: Color,
+    ) -> &mut DebugShape {
+        self.push(Shape::Circle(center.into(), radius), color)
+    }
+
+    /// Queues a text label, with its top left corner at the given position.
+    pub fn text(
+        &mut self,
+        position: impl Into<mint::Point2<f32>>,
+        text: impl Into<String>,
+        color: Color,
+    ) -> &mut DebugShape {
+        self.push(Shape::Text(position.into(), text.into()), color)
+    }
+
+    fn push(&mut self, shape: Shape, color: Color) -> &mut DebugShape {
+        if !self.enabled {
+            self.discarded = DebugShape::new(Shape::Rect(Rect::zero()), color);
+            return &mut self.discarded;
+        }
+        self.shapes.push(DebugShape::new(shape, color));
+        // just pushed
+        self.shapes.last_mut().unwrap()
+    }
+
+    /// Draws the queued shapes onto the image being presented and removes those at the end of
+    /// their lifetime. Errors while drawing are logged, so they don't end the frame.
+    pub(crate) fn flush(&mut self, gfx: &mut GraphicsContext) {
+        if self.shapes.is_empty() {
+            return;
+        }
+
+        if let Err(e) = self.draw(gfx) {
+            error!("Error drawing debug shapes: {e:?}");
+        }
+
+        let now = Instant::now();
+        self.shapes.retain_mut(|shape| match &mut shape.lifetime {
+            Lifetime::Frames(frames) => {
+                *frames -= 1;
+                *frames > 0
+            }
+            Lifetime::Until(until) => *until > now,
+        });
+    }
+
+    fn draw(&self, gfx: &mut GraphicsContext) -> GameResult {
+        let present = gfx.presented_image()?;
+        let mut canvas = Canvas::from_image(gfx, present, None);
+        // screen space shapes come before the camera is set, and their z draws them on top
+        draw_shapes(&mut canvas, gfx, self.shapes.iter().filter(|s| s.screen), 1);
+        if let Some(camera) = &self.camera {
+            canvas.set_camera(camera)?;
+        }
+        draw_shapes(
+            &mut canvas,
+            gfx,
+            self.shapes.iter().filter(|s| !s.screen),
+            0,
+        );
+        canvas.finish(gfx)
+    }
+}
+
+fn draw_shapes<'a>(
+    canvas: &mut Canvas,
+    gfx: &GraphicsContext,
+    shapes: impl Iterator<Item = &'a DebugShape>,
+    z: i32,
+) {
+    let mut builder = MeshBuilder::new();
+    for shape in shapes {
+        let mode = if shape.fill {
+            DrawMode::fill()
+        } else {
+            DrawMode::stroke(shape.width)
+        };
+        // degenerate shapes fail to tessellate, and are simply skipped
+        let _ = match &shape.shape {
+            Shape::Line(from, to) => builder.line(&[*from, *to], shape.width, shape.color),
+            Shape::Arrow(from, to) => {
+                let (from, to) = (Vec2::from(*from), Vec2::from(*to));
+                let length = from.distance(to);
+                let direction = (to - from).normalize_or_zero();
+                let head = (6. + shape.width * 3.).min(length);
+                let base = to - direction * head;
+                let side = direction.perp() * head / 2.;
+                let _ = builder.line(&[from, base], shape.width, shape.color);
+                builder.polygon(
+                    DrawMode::fill(),
+                    &[to, base + side, base - side],
+                    shape.color,
+                )
+            }
+            Shape::Rect(rect) => builder.rectangle(mode, *rect, shape.color),
+            Shape::Circle(center, radius) => {
+                builder.circle(mode, *center, *radius, 0.1, shape.color)
+            }
+            Shape::Text(position, text) => {
+                canvas.draw(
+                    &Text::new(text.as_str()),
+                    DrawParam::new().dest(*position).color(shape.color).z(z),
+                );
+                continue;
+            }
+        };
+    }
+
+    let data = builder.build();
+    if !data.vertices.is_empty() {
+        canvas.draw(&Mesh::from_data(gfx, data), DrawParam::new().z(z));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{test_context, test_pixel, TEST_SIZE};
+    use crate::graphics::Image;
+
+    #[test]
+    fn lifetimes() {
+        let mut ctx = test_context("ggez_debug_draw_test");
+        let debug = ctx.gfx.debug_draw();
+        debug.set_enabled(true);
+        let _ = debug.line([0., 0.], [10., 10.], Color::RED);
+        let _ = debug
+            .rect(Rect::new(0., 0., 8., 8.), Color::RED)
+            .filled()
+            .frames(2);
+        let _ = debug
+            .text([4., 4.], "label", Color::WHITE)
+            .screen_space()
+            .duration(Duration::from_secs(60));
+        assert_eq!(debug.shapes.len(), 3);
+
+        ctx.gfx.begin_frame().unwrap();
+        ctx.gfx.end_frame().unwrap();
+        assert_eq!(ctx.gfx.debug_draw().shapes.len(), 2);
+        let pixels = ctx.gfx.frame().to_pixels(&ctx.gfx).unwrap();
+        assert_ne!(test_pixel(&pixels, 0, 0), [0, 0, 0]);
+
+        ctx.gfx.begin_frame().unwrap();
+        ctx.gfx.end_frame().unwrap();
+        assert_eq!(ctx.gfx.debug_draw().shapes.len(), 1);
+
+        let debug = ctx.gfx.debug_draw();
+        debug.set_enabled(false);
+        let _ = debug.circle([0., 0.], 4., Color::RED).frames(10);
+        assert!(debug.shapes.is_empty());
+    }
+
+    #[test]
+    fn draws_onto_presented_image() {
+        let mut ctx = test_context("ggez_debug_draw_test");
+        let size = TEST_SIZE as u32;
+        let image = Image::new_canvas_image(&ctx, ctx.gfx.surface_format(), size, size, 1);
+        let debug = ctx.gfx.debug_draw();
+        debug.set_enabled(true);
+        let _ = debug.rect(Rect::new(0., 0., 8., 8.), Color::RED).filled();
+
+        ctx.gfx.begin_frame().unwrap();
+        Canvas::from_image(&ctx, image.clone(), Color::BLACK)
+            .finish(&mut ctx)
+            .unwrap();
+        ctx.gfx.present(&image).unwrap();
+        ctx.gfx.end_frame().unwrap();
+        assert!(ctx.gfx.debug_draw().shapes.is_empty());
+        let pixels = image.to_pixels(&ctx).unwrap();
+        assert_ne!(test_pixel(&pixels, 0, 0), [0, 0, 0]);
+
+        // shapes still expire when they can't be drawn
+        let image = Image::from_color(&ctx, size, size, Some(Color::BLACK));
+        let _ = ctx.gfx.debug_draw().line([0., 0.], [8., 8.], Color::RED);
+        ctx.gfx.begin_frame().unwrap();
+        ctx.gfx.present(&image).unwrap();
+        ctx.gfx.end_frame().unwrap();
+        assert!(ctx.gfx.debug_draw().shapes.is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+use crate::graphics::FrameStats;
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+/// Counts render work as it is recorded, from anywhere that has access to the graphics context.
+///
+/// The counts are taken at the end of every frame, so work done between frames (e.g. creating
+/// meshes in `update`) counts towards the next frame.
+#[derive(Debug, Default)]
+pub(crate) struct RenderCounters {
+    pub draw_calls: AtomicU64,
+    pub instances: AtomicU64,
+    pub pipeline_switches: AtomicU64,
+    pub bind_group_changes: AtomicU64,
+    pub vertex_bytes: AtomicU64,
+    pub text_bytes: AtomicU64,
+    pub arena_bytes: AtomicU64,
+    pub glyph_cache_misses: AtomicU64,
+}
+
+impl RenderCounters {
+    pub fn add(counter: &AtomicU64, n: u64) {
+        let _ = counter.fetch_add(n, Relaxed);
+    }
+
+    /// Returns the counts so far and resets them.
+    pub fn take(&self) -> FrameStats {
+        FrameStats {
+            draw_calls: self.draw_calls.swap(0, Relaxed),
+            instances: self.instances.swap(0, Relaxed),
+            pipeline_switches: self.pipeline_switches.swap(0, Relaxed),
+            bind_group_changes: self.bind_group_changes.swap(0, Relaxed),
+            vertex_bytes: self.vertex_bytes.swap(0, Relaxed),
+            text_bytes: self.text_bytes.swap(0, Relaxed),
+            arena_bytes: self.arena_bytes.swap(0, Relaxed),
+            glyph_cache_misses: self.glyph_cache_misses.swap(0, Relaxed),
+        }
+    }
+}
@@ -1,5 +1,7 @@
 pub mod arc;
 pub mod bind_group;
+pub mod counters;
 pub mod growing;
 pub mod pipeline;
 pub mod text;
+pub mod timer;
@@ -1,6 +1,10 @@
 use super::arc::{ArcBindGroupLayout, ArcPipelineLayout, ArcRenderPipeline, ArcShaderModule};
+use crate::graphics::DepthMode;
 use std::collections::{hash_map::DefaultHasher, HashMap};
 
+/// Format of the depth and stencil attachments of render passes.
+pub const DEPTH_STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+
 /// Hashable representation of a render pipeline, used as a key in the HashMap cache.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RenderPipelineInfo {
@@ -11,7 +15,11 @@ pub struct RenderPipelineInfo {
     pub samples: u32,
     pub format: wgpu::TextureFormat,
     pub blend: Option<wgpu::BlendState>,
-    pub depth: bool,
+    /// Depth test and write, if the render pass has a depth attachment.
+    pub depth: Option<DepthMode>,
+    /// Stencil test and operation, if the render pass has a stencil attachment.
+    pub stencil: Option<wgpu::StencilFaceState>,
+    pub write_mask: wgpu::ColorWrites,
     pub vertices: bool,
     pub topology: wgpu::PrimitiveTopology,
     pub vertex_layout: wgpu::VertexBufferLayout<'static>,
@@ -61,12 +69,23 @@ impl PipelineCache {
                             polygon_mode: wgpu::PolygonMode::Fill,
                             conservative: false,
                         },
-                        depth_stencil: if info.depth {
+                        depth_stencil: if info.depth.is_some() || info.stencil.is_some() {
+                            let face = info.stencil.unwrap_or_default();
                             Some(wgpu::DepthStencilState {
-                                format: wgpu::TextureFormat::Depth32Float,
-                                depth_write_enabled: true,
-                                depth_compare: wgpu::CompareFunction::Always,
-                                stencil: Default::default(),
+                                format: DEPTH_STENCIL_FORMAT,
+                                depth_write_enabled: matches!(
+                                    info.depth,
+                                    Some(DepthMode { write: true, .. })
+                                ),
+                                depth_compare: info
+                                    .depth
+                                    .map_or(wgpu::CompareFunction::Always, |d| d.compare),
+                                stencil: wgpu::StencilState {
+                                    front: face,
+                                    back: face,
+                                    read_mask: !0,
+                                    write_mask: !0,
+                                },
                                 bias: Default::default(),
                             })
                         } else {
@@ -83,7 +102,7 @@ impl PipelineCache {
                             targets: &[Some(wgpu::ColorTargetState {
                                 format: info.format,
                                 blend: info.blend,
-                                write_mask: wgpu::ColorWrites::ALL,
+                                write_mask: info.write_mask,
                             })],
                         }),
                         multiview: None,
@@ -1,6 +1,7 @@
 use super::{
     arc::{ArcBindGroup, ArcBindGroupLayout, ArcBuffer, ArcTexture, ArcTextureView},
     bind_group::BindGroupBuilder,
+    counters::RenderCounters,
     growing::GrowingBufferArena,
 };
 use crate::graphics::{context::FrameArenas, LinearColor};
@@ -101,9 +102,11 @@ impl TextRenderer {
         queue: &wgpu::Queue,
         arenas: &'a FrameArenas,
         pass: &mut wgpu::RenderPass<'a>,
+        counters: &RenderCounters,
     ) {
         let res = self.glyph_brush.borrow_mut().process_queued(
             |rect, pixels| {
+                RenderCounters::add(&counters.glyph_cache_misses, 1);
                 queue.write_texture(
                     wgpu::ImageCopyTexture {
                         texture: &self.cache,
@@ -156,6 +159,7 @@ impl TextRenderer {
                     (Self::create_verts_buffer(device, verts.len()), 0)
                 } else {
                     let verts_alloc = self.verts.allocate(device, verts_size as u64);
+                    RenderCounters::add(&counters.arena_bytes, verts_size as u64);
                     (verts_alloc.buffer, verts_alloc.offset)
                 };
 
@@ -168,6 +172,9 @@ impl TextRenderer {
                 // Also note that vertex data is stepped PER INSTANCE.
                 // Therefore we only store ONE VERTEX for ONE GLYPH (and in the vertex shader we generate the quad vertices on the fly).
                 pass.draw(0..4, 0..verts.len() as u32);
+                RenderCounters::add(&counters.draw_calls, 1);
+                RenderCounters::add(&counters.instances, verts.len() as u64);
+                RenderCounters::add(&counters.text_bytes, verts_size as u64);
             }
             Err(glyph_brush::BrushError::TextureTooSmall { suggested }) => {
                 // increase texture size as recommended by glyph_brush
@@ -206,7 +213,7 @@ impl TextRenderer {
                         entries: cache_bind.entries(),
                     }));
 
-                self.draw_queued(device, queue, arenas, pass)
+                self.draw_queued(device, queue, arenas, pass, counters)
             }
             _ => unreachable!(),
         }
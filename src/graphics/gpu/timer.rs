@@ -0,0 +1,115 @@
+use std::{
+    sync::{
+        atomic::{AtomicU8, Ordering::SeqCst},
+        Arc,
+    },
+    time::Duration,
+};
+
+const IDLE: u8 = 0;
+const MAPPING: u8 = 1;
+const MAPPED: u8 = 2;
+
+const SIZE: u64 = 2 * std::mem::size_of::<u64>() as u64;
+
+/// Measures how long the GPU spends on a frame's commands with timestamp queries.
+///
+/// Results are read back asynchronously, so they arrive a frame or more late, and frames are
+/// skipped while a readback is in flight.
+pub(crate) struct GpuTimer {
+    pub enabled: bool,
+    query_set: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    state: Arc<AtomicU8>,
+    recording: bool,
+    period: f32,
+    last: Option<Duration>,
+}
+
+impl GpuTimer {
+    /// Returns `None` if the device doesn't support timestamp queries.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("ggez frame timer"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let buffer = |usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: SIZE,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        Some(GpuTimer {
+            enabled: false,
+            query_set,
+            resolve: buffer(wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC),
+            readback: buffer(wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST),
+            state: Arc::new(AtomicU8::new(IDLE)),
+            recording: false,
+            period: queue.get_timestamp_period(),
+            last: None,
+        })
+    }
+
+    /// Called at the start of a frame's commands.
+    pub fn begin(&mut self, cmd: &mut wgpu::CommandEncoder) {
+        self.recording = self.enabled && self.state.load(SeqCst) == IDLE;
+        if self.recording {
+            cmd.write_timestamp(&self.query_set, 0);
+        }
+    }
+
+    /// Called at the end of a frame's commands.
+    pub fn end(&mut self, cmd: &mut wgpu::CommandEncoder) {
+        if self.recording {
+            cmd.write_timestamp(&self.query_set, 1);
+            cmd.resolve_query_set(&self.query_set, 0..2, &self.resolve, 0);
+            cmd.copy_buffer_to_buffer(&self.resolve, 0, &self.readback, 0, SIZE);
+        }
+    }
+
+    /// Called after the frame's commands were submitted.
+    pub fn submitted(&mut self, device: &wgpu::Device) {
+        if std::mem::take(&mut self.recording) {
+            self.state.store(MAPPING, SeqCst);
+            let state = self.state.clone();
+            self.readback
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    state.store(if result.is_ok() { MAPPED } else { IDLE }, SeqCst);
+                });
+        }
+
+        if self.state.load(SeqCst) == MAPPING {
+            let _ = device.poll(wgpu::Maintain::Poll);
+        }
+        if self.state.load(SeqCst) == MAPPED {
+            {
+                let data = self.readback.slice(..).get_mapped_range();
+                let stamp = |i: usize| {
+                    let mut bytes = [0; 8];
+                    bytes.copy_from_slice(&data[i * 8..i * 8 + 8]);
+                    u64::from_le_bytes(bytes)
+                };
+                let ticks = stamp(1).saturating_sub(stamp(0));
+                self.last = Some(Duration::from_nanos(
+                    (ticks as f64 * f64::from(self.period)) as u64,
+                ));
+            }
+            self.readback.unmap();
+            self.state.store(IDLE, SeqCst);
+        }
+    }
+
+    /// Returns the latest measurement.
+    pub fn last(&self) -> Option<Duration> {
+        self.last
+    }
+}
@@ -158,7 +158,7 @@ impl Image {
         ))
     }
 
-    fn new(
+    pub(crate) fn new(
         wgpu: &WgpuContext,
         format: ImageFormat,
         width: u32,
@@ -226,11 +226,15 @@ impl Image {
             )));
         }
 
-        let block_size = u64::from(self.format.block_size(None).unwrap()); // Unwrap since it only fails with depth formats.
+        let block_size = self.format.block_size(None).unwrap(); // Unwrap since it only fails with depth formats.
+
+        // texture to buffer copies need each row to be aligned to `COPY_BYTES_PER_ROW_ALIGNMENT`
+        let row_size = block_size * self.width;
+        let padded_row_size = wgpu::util::align_to(row_size, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
 
         let buffer = gfx.wgpu.device.create_buffer(&wgpu::BufferDescriptor {
             label: None,
-            size: block_size * u64::from(self.width) * u64::from(self.height),
+            size: u64::from(padded_row_size) * u64::from(self.height),
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
             mapped_at_creation: false,
         });
@@ -246,7 +250,7 @@ impl Image {
                     buffer: &buffer,
                     layout: wgpu::ImageDataLayout {
                         offset: 0,
-                        bytes_per_row: Some(block_size as u32 * self.width),
+                        bytes_per_row: Some(padded_row_size),
                         rows_per_image: None,
                     },
                 },
@@ -272,7 +276,16 @@ impl Image {
             .expect("All senders dropped, this should not be possible.");
         map_result?;
 
-        let out = buffer.slice(..).get_mapped_range().to_vec();
+        let mapped = buffer.slice(..).get_mapped_range();
+        let out = if padded_row_size == row_size {
+            mapped.to_vec()
+        } else {
+            mapped
+                .chunks_exact(padded_row_size as usize)
+                .flat_map(|row| &row[..row_size as usize])
+                .copied()
+                .collect()
+        };
         Ok(out)
     }
 
@@ -442,9 +455,9 @@ impl ScreenImage {
 
     fn size(gfx: &impl Has<GraphicsContext>, (width, height): (f32, f32)) -> (u32, u32) {
         let gfx = gfx.retrieve();
-        let size = gfx.window.inner_size();
-        let width = (size.width as f32 * width) as u32;
-        let height = (size.height as f32 * height) as u32;
+        let size = gfx.drawable_size();
+        let width = (size.0 * width) as u32;
+        let height = (size.1 * height) as u32;
         (width.max(1), height.max(1))
     }
 
@@ -3,7 +3,10 @@ use crate::{context::Has, graphics::gpu::bind_group::BindGroupBuilder, GameError
 use super::{
     context::GraphicsContext,
     draw::{DrawParam, DrawUniforms, Std140DrawUniforms},
-    gpu::arc::{ArcBindGroup, ArcBindGroupLayout, ArcBuffer},
+    gpu::{
+        arc::{ArcBindGroup, ArcBindGroupLayout, ArcBuffer},
+        counters::RenderCounters,
+    },
     internal_canvas::InstanceArrayView,
     transform_rect, Canvas, Draw, Drawable, Image, Mesh, Rect, WgpuContext,
 };
@@ -192,7 +195,7 @@ impl InstanceArray {
         &self.params
     }
 
-    pub(crate) fn flush_wgpu(&self, wgpu: &WgpuContext) -> GameResult {
+    pub(crate) fn flush_wgpu(&self, wgpu: &WgpuContext, counters: &RenderCounters) -> GameResult {
         if !self.dirty.load(SeqCst) {
             return Ok(());
         } else {
@@ -222,6 +225,10 @@ impl InstanceArray {
             0,
             bytemuck::cast_slice(self.uniforms.as_slice()),
         );
+        RenderCounters::add(
+            &counters.vertex_bytes,
+            std::mem::size_of_val(self.uniforms.as_slice()) as u64,
+        );
 
         if self.ordered {
             let mut layers = BTreeMap::<_, Vec<_>>::new();
@@ -234,6 +241,10 @@ impl InstanceArray {
                 0,
                 bytemuck::cast_slice(indices.as_slice()),
             );
+            RenderCounters::add(
+                &counters.vertex_bytes,
+                std::mem::size_of_val(indices.as_slice()) as u64,
+            );
         }
 
         Ok(())
@@ -311,7 +322,7 @@ impl Drawable for InstanceArray {
         if self.instances().is_empty() {
             return;
         }
-        self.flush_wgpu(&canvas.wgpu).unwrap();
+        self.flush_wgpu(&canvas.wgpu, &canvas.counters).unwrap();
         canvas.push_draw(
             Draw::MeshInstances {
                 mesh: canvas.default_resources().mesh.clone(),
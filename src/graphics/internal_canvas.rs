@@ -1,15 +1,17 @@
 use super::{
+    canvas::{DepthMode, MaskState},
     context::{FrameArenas, GraphicsContext},
     draw::{DrawParam, DrawUniforms},
     gpu::{
         arc::{ArcBindGroup, ArcBindGroupLayout, ArcBuffer, ArcShaderModule, ArcTextureView},
         bind_group::{BindGroupBuilder, BindGroupCache, BindGroupLayoutBuilder},
+        counters::RenderCounters,
         growing::{ArenaAllocation, GrowingBufferArena},
-        pipeline::{PipelineCache, RenderPipelineInfo},
+        pipeline::{PipelineCache, RenderPipelineInfo, DEPTH_STENCIL_FORMAT},
         text::{TextRenderer, TextVertex},
     },
     image::Image,
-    mesh::{Mesh, Vertex},
+    mesh::{Mesh, Vertex, Vertex3d, VertexLayout},
     sampler::{Sampler, SamplerCache},
     shader::Shader,
     BlendMode, Color, InstanceArray, LinearColor, Rect, Text, Transform, WgpuContext,
@@ -29,6 +31,7 @@ pub struct InternalCanvas<'a> {
     text_renderer: &'a mut TextRenderer,
     fonts: &'a HashMap<String, glyph_brush::FontId>,
     uniform_arena: &'a mut GrowingBufferArena,
+    counters: &'a RenderCounters,
 
     shader: Shader,
     shader_bind_group: Option<(&'a wgpu::BindGroup, ArcBindGroupLayout, u32)>,
@@ -36,9 +39,14 @@ pub struct InternalCanvas<'a> {
     text_shader_bind_group: Option<(&'a wgpu::BindGroup, ArcBindGroupLayout, u32)>,
 
     shader_ty: Option<ShaderType>,
+    vertex_layout: Option<wgpu::VertexBufferLayout<'static>>,
     dirty_pipeline: bool,
     queuing_text: bool,
     blend_mode: BlendMode,
+    // whether the pass has a depth and stencil attachment
+    depth_stencil: bool,
+    mask: MaskState,
+    depth: Option<DepthMode>,
     pass: wgpu::RenderPass<'a>,
     samples: u32,
     format: wgpu::TextureFormat,
@@ -61,12 +69,13 @@ impl<'a> InternalCanvas<'a> {
         gfx: &'a mut GraphicsContext,
         clear: impl Into<Option<Color>>,
         image: &'a Image,
+        depth_stencil: Option<&'a wgpu::TextureView>,
     ) -> GameResult<Self> {
         if image.samples() > 1 {
             return Err(GameError::RenderError(String::from("non-MSAA rendering requires an image with exactly 1 sample, for this image use Canvas::from_msaa instead")));
         }
 
-        Self::new(gfx, 1, image.format(), |cmd| {
+        Self::new(gfx, 1, image.format(), depth_stencil.is_some(), |cmd| {
             cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -80,7 +89,7 @@ impl<'a> InternalCanvas<'a> {
                         store: true,
                     },
                 })],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: depth_stencil.map(depth_stencil_attachment),
             })
         })
     }
@@ -90,6 +99,7 @@ impl<'a> InternalCanvas<'a> {
         clear: impl Into<Option<Color>>,
         msaa_image: &'a Image,
         resolve_image: &'a Image,
+        depth_stencil: Option<&'a wgpu::TextureView>,
     ) -> GameResult<Self> {
         if msaa_image.samples() == 1 {
             return Err(GameError::RenderError(String::from(
@@ -109,29 +119,37 @@ impl<'a> InternalCanvas<'a> {
             )));
         }
 
-        Self::new(gfx, msaa_image.samples(), msaa_image.format(), |cmd| {
-            cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: msaa_image.view.as_ref(),
-                    resolve_target: Some(resolve_image.view.as_ref()),
-                    ops: wgpu::Operations {
-                        load: match clear.into() {
-                            None => wgpu::LoadOp::Load,
-                            Some(color) => wgpu::LoadOp::Clear(LinearColor::from(color).into()),
+        let samples = msaa_image.samples();
+        Self::new(
+            gfx,
+            samples,
+            msaa_image.format(),
+            depth_stencil.is_some(),
+            |cmd| {
+                cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: msaa_image.view.as_ref(),
+                        resolve_target: Some(resolve_image.view.as_ref()),
+                        ops: wgpu::Operations {
+                            load: match clear.into() {
+                                None => wgpu::LoadOp::Load,
+                                Some(color) => wgpu::LoadOp::Clear(LinearColor::from(color).into()),
+                            },
+                            store: true,
                         },
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            })
-        })
+                    })],
+                    depth_stencil_attachment: depth_stencil.map(depth_stencil_attachment),
+                })
+            },
+        )
     }
 
     pub(crate) fn new(
         gfx: &'a mut GraphicsContext,
         samples: u32,
         format: wgpu::TextureFormat,
+        depth_stencil: bool,
         create_pass: impl FnOnce(&'a mut wgpu::CommandEncoder) -> wgpu::RenderPass<'a>,
     ) -> GameResult<Self> {
         if gfx.fcx.is_none() {
@@ -149,6 +167,7 @@ impl<'a> InternalCanvas<'a> {
         let text_renderer = &mut gfx.text;
         let fonts = &gfx.fonts;
         let uniform_arena = &mut gfx.uniform_arena;
+        let counters = &*gfx.counters;
 
         let (arenas, mut pass) = {
             let fcx = gfx.fcx.as_mut().unwrap(/* see above */);
@@ -172,15 +191,21 @@ impl<'a> InternalCanvas<'a> {
         let shader = Shader {
             vs_module: None,
             fs_module: None,
+            vertex_layout: None,
         };
 
         let text_shader = Shader {
             vs_module: None,
             fs_module: None,
+            vertex_layout: None,
         };
 
         let text_uniforms =
             uniform_arena.allocate(&wgpu.device, TextUniforms::std140_size_static() as _);
+        RenderCounters::add(
+            &counters.arena_bytes,
+            TextUniforms::std140_size_static() as u64,
+        );
 
         wgpu.queue.write_buffer(
             &text_uniforms.buffer,
@@ -201,6 +226,7 @@ impl<'a> InternalCanvas<'a> {
             text_renderer,
             fonts,
             uniform_arena,
+            counters,
 
             shader,
             shader_bind_group: None,
@@ -208,9 +234,13 @@ impl<'a> InternalCanvas<'a> {
             text_shader_bind_group: None,
 
             shader_ty: None,
+            vertex_layout: None,
             dirty_pipeline: true,
             queuing_text: false,
             blend_mode: BlendMode::ALPHA,
+            depth_stencil,
+            mask: MaskState::Off,
+            depth: None,
             pass,
             samples,
             format,
@@ -275,6 +305,24 @@ impl<'a> InternalCanvas<'a> {
         self.blend_mode = blend_mode;
     }
 
+    pub fn set_mask(&mut self, mask: MaskState) {
+        self.flush_text();
+        self.dirty_pipeline = true;
+        self.mask = mask;
+        if let MaskState::Write(reference)
+        | MaskState::Inside(reference)
+        | MaskState::Outside(reference) = mask
+        {
+            self.pass.set_stencil_reference(u32::from(reference));
+        }
+    }
+
+    pub fn set_depth(&mut self, depth: Option<DepthMode>) {
+        self.flush_text();
+        self.dirty_pipeline = true;
+        self.depth = depth;
+    }
+
     pub fn set_premultiplied_text(&mut self, premultiplied_text: bool) {
         self.flush_text();
         self.premul_text = premultiplied_text;
@@ -286,6 +334,10 @@ impl<'a> InternalCanvas<'a> {
         self.text_uniforms = self
             .uniform_arena
             .allocate(&self.wgpu.device, TextUniforms::std140_size_static() as _);
+        RenderCounters::add(
+            &self.counters.arena_bytes,
+            TextUniforms::std140_size_static() as u64,
+        );
         self.wgpu.queue.write_buffer(
             &self.text_uniforms.buffer,
             self.text_uniforms.offset,
@@ -303,12 +355,20 @@ impl<'a> InternalCanvas<'a> {
     }
 
     #[allow(unsafe_code)]
-    pub fn draw_mesh(&mut self, mesh: &'a Mesh, image: &Image, param: DrawParam, scale: bool) {
+    pub fn draw_mesh(
+        &mut self,
+        mesh: &'a Mesh,
+        image: &Image,
+        param: DrawParam,
+        scale: bool,
+    ) -> GameResult {
         self.flush_text();
-        self.update_pipeline(ShaderType::Draw);
+        self.check_vertex_layout(mesh)?;
+        self.update_pipeline(ShaderType::Draw, &mesh.layout);
 
         let alloc_size = DrawUniforms::std140_size_static() as u64;
         let uniform_alloc = self.uniform_arena.allocate(&self.wgpu.device, alloc_size);
+        RenderCounters::add(&self.counters.arena_bytes, alloc_size);
 
         let (uniform_bind_group, _) = BindGroupBuilder::new()
             .buffer(
@@ -348,12 +408,17 @@ impl<'a> InternalCanvas<'a> {
             self.arenas.bind_groups.alloc(uniform_bind_group),
             &[uniform_alloc.offset as u32], // <- the dynamic offset
         );
+        RenderCounters::add(&self.counters.bind_group_changes, 1);
 
         self.pass.set_vertex_buffer(0, mesh.verts.slice(..));
         self.pass
             .set_index_buffer(mesh.inds.slice(..), wgpu::IndexFormat::Uint32);
 
         self.pass.draw_indexed(0..mesh.index_count as _, 0, 0..1);
+        RenderCounters::add(&self.counters.draw_calls, 1);
+        RenderCounters::add(&self.counters.instances, 1);
+
+        Ok(())
     }
 
     pub fn draw_mesh_instances(
@@ -369,9 +434,13 @@ impl<'a> InternalCanvas<'a> {
             return Ok(());
         }
 
-        self.update_pipeline(ShaderType::Instance {
-            ordered: instances.ordered,
-        });
+        self.check_vertex_layout(mesh)?;
+        self.update_pipeline(
+            ShaderType::Instance {
+                ordered: instances.ordered,
+            },
+            &mesh.layout,
+        );
 
         let alloc_size = u64::from(
             self.wgpu
@@ -380,6 +449,7 @@ impl<'a> InternalCanvas<'a> {
                 .min_uniform_buffer_offset_alignment,
         );
         let uniform_alloc = self.uniform_arena.allocate(&self.wgpu.device, alloc_size);
+        RenderCounters::add(&self.counters.arena_bytes, alloc_size);
 
         let (uniform_bind_group, _) = BindGroupBuilder::new()
             .buffer(
@@ -433,6 +503,7 @@ impl<'a> InternalCanvas<'a> {
             &[uniform_alloc.offset as u32],
         );
         self.pass.set_bind_group(2, &instances.bind_group, &[]);
+        RenderCounters::add(&self.counters.bind_group_changes, 2);
 
         self.pass.set_vertex_buffer(0, mesh.verts.slice(..));
         self.pass
@@ -440,6 +511,8 @@ impl<'a> InternalCanvas<'a> {
 
         self.pass
             .draw_indexed(0..mesh.index_count as _, 0, 0..instances.len as _);
+        RenderCounters::add(&self.counters.draw_calls, 1);
+        RenderCounters::add(&self.counters.instances, u64::from(instances.len));
 
         Ok(())
     }
@@ -475,6 +548,7 @@ impl<'a> InternalCanvas<'a> {
             self.arenas.bind_groups.alloc(text_uniforms_bind),
             &[self.text_uniforms.offset as u32],
         );
+        RenderCounters::add(&self.counters.bind_group_changes, 1);
 
         self.queuing_text = true;
 
@@ -489,12 +563,13 @@ impl<'a> InternalCanvas<'a> {
                 premul = true;
                 self.set_blend_mode(BlendMode::PREMULTIPLIED);
             }
-            self.update_pipeline(ShaderType::Text);
+            self.update_pipeline(ShaderType::Text, &TextVertex::layout());
             self.text_renderer.draw_queued(
                 &self.wgpu.device,
                 &self.wgpu.queue,
                 self.arenas,
                 &mut self.pass,
+                self.counters,
             );
             if premul {
                 self.set_blend_mode(BlendMode::ALPHA);
@@ -510,10 +585,37 @@ impl<'a> InternalCanvas<'a> {
         self.flush_text();
     }
 
-    fn update_pipeline(&mut self, ty: ShaderType) {
-        if self.dirty_pipeline || self.shader_ty != Some(ty) {
+    fn check_vertex_layout(&self, mesh: &Mesh) -> GameResult {
+        if matches!(&self.shader.vertex_layout, Some(layout) if *layout != mesh.layout) {
+            return Err(GameError::RenderError(String::from(
+                "mesh vertices don't match the vertex layout of the shader",
+            )));
+        }
+
+        if self.shader.vs_module.is_none()
+            && mesh.layout != Vertex::layout()
+            && mesh.layout != Vertex3d::layout()
+        {
+            return Err(GameError::RenderError(String::from(
+                "meshes with custom vertex layouts must be drawn with a custom vertex shader",
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn update_pipeline(
+        &mut self,
+        ty: ShaderType,
+        vertex_layout: &wgpu::VertexBufferLayout<'static>,
+    ) {
+        if self.dirty_pipeline
+            || self.shader_ty != Some(ty)
+            || self.vertex_layout.as_ref() != Some(vertex_layout)
+        {
             self.dirty_pipeline = false;
             self.shader_ty = Some(ty);
+            self.vertex_layout = Some(vertex_layout.clone());
 
             let texture_layout = BindGroupLayoutBuilder::new()
                 .image(wgpu::ShaderStages::FRAGMENT)
@@ -554,12 +656,14 @@ impl<'a> InternalCanvas<'a> {
                 groups.push(dummy_layout);
                 self.pass
                     .set_bind_group(2, self.arenas.bind_groups.alloc(dummy_group), &[]);
+                RenderCounters::add(&self.counters.bind_group_changes, 1);
             }
 
             let shader = match ty {
                 ShaderType::Draw | ShaderType::Instance { .. } => {
                     if let Some((bind_group, bind_group_layout, offset)) = &self.shader_bind_group {
                         self.pass.set_bind_group(3, bind_group, &[*offset]);
+                        RenderCounters::add(&self.counters.bind_group_changes, 1);
                         groups.push(bind_group_layout.clone());
                     }
 
@@ -570,6 +674,7 @@ impl<'a> InternalCanvas<'a> {
                         &self.text_shader_bind_group
                     {
                         self.pass.set_bind_group(3, bind_group, &[*offset]);
+                        RenderCounters::add(&self.counters.bind_group_changes, 1);
                         groups.push(bind_group_layout.clone());
                     }
 
@@ -610,28 +715,44 @@ impl<'a> InternalCanvas<'a> {
                                 ShaderType::Text => self.text_sm.clone(),
                             }
                         },
-                        vs_entry: "vs_main".into(),
-                        fs_entry: "fs_main".into(),
+                        // custom vertex shaders take any vertex layout in `vs_main`
+                        vs_entry: if shader.vs_module.is_none()
+                            && *vertex_layout == Vertex3d::layout()
+                        {
+                            "vs_main_3d".into()
+                        } else {
+                            "vs_main".into()
+                        },
+                        // the default fragment shaders leave transparent pixels out of masks
+                        fs_entry: if self.mask.writes() && shader.fs_module.is_none() {
+                            "fs_mask".into()
+                        } else {
+                            "fs_main".into()
+                        },
                         samples: self.samples,
                         format: self.format,
                         blend: Some(wgpu::BlendState {
                             color: self.blend_mode.color,
                             alpha: self.blend_mode.alpha,
                         }),
-                        depth: false,
+                        depth: self.depth,
+                        stencil: self.depth_stencil.then(|| self.mask.stencil_state()),
+                        write_mask: if self.mask.writes() {
+                            wgpu::ColorWrites::empty()
+                        } else {
+                            wgpu::ColorWrites::ALL
+                        },
                         vertices: true,
                         topology: match ty {
                             ShaderType::Text => wgpu::PrimitiveTopology::TriangleStrip,
                             _ => wgpu::PrimitiveTopology::TriangleList,
                         },
-                        vertex_layout: match ty {
-                            ShaderType::Text => TextVertex::layout(),
-                            _ => Vertex::layout(),
-                        },
+                        vertex_layout: vertex_layout.clone(),
                     },
                 ));
 
             self.pass.set_pipeline(pipeline);
+            RenderCounters::add(&self.counters.pipeline_switches, 1);
         }
     }
 
@@ -650,6 +771,7 @@ impl<'a> InternalCanvas<'a> {
 
             self.pass
                 .set_bind_group(1, self.arenas.bind_groups.alloc(image_bind), &[]);
+            RenderCounters::add(&self.counters.bind_group_changes, 1);
         }
     }
 
@@ -674,10 +796,49 @@ impl<'a> InternalCanvas<'a> {
 
             self.pass
                 .set_bind_group(1, self.arenas.bind_groups.alloc(image_bind), &[]);
+            RenderCounters::add(&self.counters.bind_group_changes, 1);
         }
     }
 }
 
+fn depth_stencil_attachment(
+    view: &wgpu::TextureView,
+) -> wgpu::RenderPassDepthStencilAttachment<'_> {
+    wgpu::RenderPassDepthStencilAttachment {
+        view,
+        depth_ops: Some(wgpu::Operations {
+            load: wgpu::LoadOp::Clear(1.),
+            store: false,
+        }),
+        stencil_ops: Some(wgpu::Operations {
+            load: wgpu::LoadOp::Clear(0),
+            store: false,
+        }),
+    }
+}
+
+/// Creates a depth and stencil attachment the size of a canvas' target.
+pub(crate) fn create_depth_stencil(
+    device: &wgpu::Device,
+    (width, height, samples): (u32, u32, u32),
+) -> ArcTextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: samples,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_STENCIL_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    ArcTextureView::new(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
 impl<'a> Drop for InternalCanvas<'a> {
     fn drop(&mut self) {
         self.finalize();
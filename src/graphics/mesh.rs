@@ -1,6 +1,7 @@
 use super::{
-    context::GraphicsContext, gpu::arc::ArcBuffer, Canvas, Color, Draw, DrawMode, DrawParam,
-    Drawable, LinearColor, Rect, WgpuContext,
+    context::GraphicsContext,
+    gpu::{arc::ArcBuffer, counters::RenderCounters},
+    Canvas, Color, Draw, DrawMode, DrawParam, Drawable, LinearColor, Rect, WgpuContext,
 };
 use crate::{context::Has, GameError, GameResult};
 use lyon::{math::Point as LPoint, path::Polygon, tessellation as tess};
@@ -18,8 +19,53 @@ pub struct Vertex {
     pub color: [f32; 4],
 }
 
-impl Vertex {
-    pub(crate) const fn layout() -> wgpu::VertexBufferLayout<'static> {
+/// A vertex type that meshes can be created from with [`Mesh::from_vertices`], describing how its
+/// fields are laid out in the vertex buffer.
+///
+/// Besides [`Vertex`] and [`Vertex3d`], which the default shaders draw, this allows custom vertex
+/// types with e.g. normals, tangents or extra UV sets; meshes of those must be drawn with a
+/// [`Shader`](super::Shader) that has a vertex shader taking the attributes at the given
+/// `shader_location`s in `vs_main`.
+///
+/// ```rust
+/// # use ggez::graphics::*;
+/// #[repr(C)]
+/// #[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+/// struct LitVertex {
+///     position: [f32; 3],
+///     normal: [f32; 3],
+/// }
+///
+/// impl VertexLayout for LitVertex {
+///     fn layout() -> wgpu::VertexBufferLayout<'static> {
+///         const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+///             wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+///         wgpu::VertexBufferLayout {
+///             array_stride: std::mem::size_of::<LitVertex>() as u64,
+///             step_mode: wgpu::VertexStepMode::Vertex,
+///             attributes: &ATTRIBUTES,
+///         }
+///     }
+///
+///     fn position(&self) -> Option<mint::Point2<f32>> {
+///         Some([self.position[0], self.position[1]].into())
+///     }
+/// }
+/// ```
+pub trait VertexLayout: bytemuck::Pod {
+    /// Returns the layout of a vertex buffer holding vertices of this type.
+    fn layout() -> wgpu::VertexBufferLayout<'static>;
+
+    /// Returns the 2D position of this vertex, which the bounds of meshes are computed from.
+    ///
+    /// Meshes of vertices that return `None`, as by default, have no bounds.
+    fn position(&self) -> Option<mint::Point2<f32>> {
+        None
+    }
+}
+
+impl VertexLayout for Vertex {
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
         const ATTRIBUTES: [wgpu::VertexAttribute; 3] = [
             wgpu::VertexAttribute {
                 format: wgpu::VertexFormat::Float32x2,
@@ -44,6 +90,59 @@ impl Vertex {
             attributes: &ATTRIBUTES,
         }
     }
+
+    fn position(&self) -> Option<mint::Point2<f32>> {
+        Some(self.position.into())
+    }
+}
+
+/// Vertex format of meshes with 3D positions, created with [`Mesh::from_data_3d`].
+///
+/// The default shaders transform the position by the projection of the canvas, so 3D scenes set a
+/// perspective projection with [`Canvas::set_projection`] and usually a depth test with
+/// [`Canvas::set_depth_mode`]. Custom vertex shaders take the `vec3` position in `vs_main`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+pub struct Vertex3d {
+    /// `vec3` position.
+    pub position: [f32; 3],
+    /// `vec2` UV/texture coordinates.
+    pub uv: [f32; 2],
+    /// `vec4` color.
+    pub color: [f32; 4],
+}
+
+impl VertexLayout for Vertex3d {
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 3] = [
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: 0,
+                shader_location: 0,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x2,
+                offset: 12,
+                shader_location: 1,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 20,
+                shader_location: 2,
+            },
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex3d>() as u64,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &ATTRIBUTES,
+        }
+    }
+
+    fn position(&self) -> Option<mint::Point2<f32>> {
+        let [x, y, _] = self.position;
+        Some([x, y].into())
+    }
 }
 
 /// Mesh data stored on the GPU as a vertex and index buffer. Cheap to clone.
@@ -53,36 +152,80 @@ pub struct Mesh {
     pub(crate) inds: ArcBuffer,
     pub(crate) vertex_count: usize,
     pub(crate) index_count: usize,
-    pub(crate) bounds: Rect,
+    pub(crate) bounds: Option<Rect>,
+    pub(crate) layout: wgpu::VertexBufferLayout<'static>,
 }
 
 impl Mesh {
     /// Create a new mesh from [`MeshData`].
     pub fn from_data(gfx: &impl Has<GraphicsContext>, raw: MeshData) -> Self {
         let gfx = gfx.retrieve();
+        let bytes = std::mem::size_of_val(raw.vertices) + std::mem::size_of_val(raw.indices);
+        RenderCounters::add(&gfx.counters.vertex_bytes, bytes as u64);
         Self::from_data_wgpu(&gfx.wgpu, raw)
     }
 
     pub(crate) fn from_data_wgpu(wgpu: &WgpuContext, raw: MeshData) -> Self {
-        let [minx, miny, maxx, maxy] = raw.vertices.iter().fold(
-            [f32::MAX, f32::MAX, f32::MIN, f32::MIN],
-            |[minx, miny, maxx, maxy], vert| {
-                let [x, y] = vert.position;
-                [minx.min(x), miny.min(y), maxx.max(x), maxy.max(y)]
-            },
-        );
+        Self::from_vertices_wgpu(wgpu, raw.vertices, raw.indices)
+    }
 
-        Mesh {
-            verts: Self::create_verts(wgpu, raw.vertices),
-            inds: Self::create_inds(wgpu, raw.indices),
-            vertex_count: raw.vertices.len(),
-            index_count: raw.indices.len(),
-            bounds: Rect {
+    /// Create a new mesh from vertices with 3D positions and indices into them.
+    ///
+    /// The bounds of the mesh, as used by [`Drawable::dimensions`], cover the `x` and `y`
+    /// coordinates of the vertices.
+    pub fn from_data_3d(
+        gfx: &impl Has<GraphicsContext>,
+        vertices: &[Vertex3d],
+        indices: &[u32],
+    ) -> Self {
+        Self::from_vertices(gfx, vertices, indices)
+    }
+
+    /// Create a new mesh from vertices of any [`VertexLayout`] and indices into them.
+    pub fn from_vertices<V: VertexLayout>(
+        gfx: &impl Has<GraphicsContext>,
+        vertices: &[V],
+        indices: &[u32],
+    ) -> Self {
+        let gfx = gfx.retrieve();
+        let bytes = std::mem::size_of_val(vertices) + std::mem::size_of_val(indices);
+        RenderCounters::add(&gfx.counters.vertex_bytes, bytes as u64);
+        Self::from_vertices_wgpu(&gfx.wgpu, vertices, indices)
+    }
+
+    fn from_vertices_wgpu<V: VertexLayout>(
+        wgpu: &WgpuContext,
+        vertices: &[V],
+        indices: &[u32],
+    ) -> Self {
+        let bounds = vertices
+            .iter()
+            .try_fold(
+                [f32::MAX, f32::MAX, f32::MIN, f32::MIN],
+                |[minx, miny, maxx, maxy], vert| {
+                    let pos = vert.position()?;
+                    Some([
+                        minx.min(pos.x),
+                        miny.min(pos.y),
+                        maxx.max(pos.x),
+                        maxy.max(pos.y),
+                    ])
+                },
+            )
+            .map(|[minx, miny, maxx, maxy]| Rect {
                 x: minx,
                 y: miny,
                 w: maxx - minx,
                 h: maxy - miny,
-            },
+            });
+
+        Mesh {
+            verts: Self::create_verts(wgpu, vertices),
+            inds: Self::create_inds(wgpu, indices),
+            vertex_count: vertices.len(),
+            index_count: indices.len(),
+            bounds,
+            layout: V::layout(),
         }
     }
 
@@ -224,7 +367,7 @@ impl Mesh {
     }
 
     #[allow(unsafe_code)]
-    fn create_verts(wgpu: &WgpuContext, vertices: &[Vertex]) -> ArcBuffer {
+    fn create_verts(wgpu: &WgpuContext, vertices: &[impl bytemuck::Pod]) -> ArcBuffer {
         ArcBuffer::new(
             wgpu.device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -261,7 +404,7 @@ impl Drawable for Mesh {
     }
 
     fn dimensions(&self, _gfx: &impl Has<GraphicsContext>) -> Option<Rect> {
-        Some(self.bounds)
+        self.bounds
     }
 }
 
@@ -21,23 +21,32 @@
 //! [custom shader]:Canvas::set_shader
 //! [blend mode]:Canvas::set_blend_mode
 
+pub(crate) mod animation;
+pub(crate) mod atlas;
+pub(crate) mod camera;
 pub(crate) mod canvas;
 pub(crate) mod context;
+pub(crate) mod debug_draw;
 pub(crate) mod draw;
 pub(crate) mod gpu;
 pub(crate) mod image;
 pub(crate) mod instance;
 pub(crate) mod internal_canvas;
 pub(crate) mod mesh;
+pub(crate) mod nineslice;
+pub(crate) mod particles;
+pub(crate) mod post;
 pub(crate) mod sampler;
 pub(crate) mod shader;
 pub(crate) mod text;
+pub(crate) mod tilemap;
 mod types;
 
 pub use lyon::tessellation::{FillOptions, FillRule, LineCap, LineJoin, StrokeOptions};
 pub use {
-    self::image::*, canvas::*, context::*, draw::*, instance::*, mesh::*, sampler::*, shader::*,
-    text::*, types::*,
+    self::image::*, animation::*, atlas::*, camera::*, canvas::*, context::*, debug_draw::*,
+    draw::*, instance::*, mesh::*, nineslice::*, particles::*, post::*, sampler::*, shader::*,
+    text::*, tilemap::*, types::*,
 };
 
 /// Applies `DrawParam` to `Rect`.
@@ -0,0 +1,333 @@
+use super::{Canvas, DrawParam, Drawable, GraphicsContext, Image, Mesh, MeshData, Rect, Vertex};
+use crate::context::Has;
+
+/// How the edges or the center of a [`NineSlice`] fill the space between its corners.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum SliceMode {
+    /// Stretch the part to fill the space.
+    #[default]
+    Stretch,
+    /// Repeat the part at its original size, cutting off the last repetition.
+    Tile,
+}
+
+/// The widths of the borders of a [`NineSlice`], in pixels of its image.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Insets {
+    /// Width of the left border.
+    pub left: f32,
+    /// Height of the top border.
+    pub top: f32,
+    /// Width of the right border.
+    pub right: f32,
+    /// Height of the bottom border.
+    pub bottom: f32,
+}
+
+impl Insets {
+    /// Creates insets with the given border widths.
+    pub const fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Insets {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    /// Creates insets with the same width on all sides.
+    pub const fn uniform(inset: f32) -> Self {
+        Insets::new(inset, inset, inset, inset)
+    }
+}
+
+/// An [`Image`] cut into nine parts by [`Insets`], which can be drawn at any size without
+/// distorting its borders, for example for UI panels and buttons.
+///
+/// The corners keep their size, the edges are stretched or tiled along their length, and the
+/// center in both directions. If the size is smaller than the borders, the borders shrink.
+/// All parts are drawn at once, as a single mesh which is rebuilt whenever the size or
+/// layout changes.
+///
+/// ```rust,no_run
+/// # use ggez::{graphics::{self, Insets, NineSlice, SliceMode}, GameResult, Context};
+/// # fn t(ctx: &mut Context, canvas: &mut graphics::Canvas) -> GameResult {
+/// let image = graphics::Image::from_path(ctx, "/panel.png")?;
+/// let mut panel = NineSlice::new(ctx, image, Insets::uniform(8.), 200., 120.)
+///     .with_modes(ctx, SliceMode::Tile, SliceMode::Stretch);
+///
+/// // when the panel is resized:
+/// panel.set_size(ctx, 240., 160.);
+/// // in draw:
+/// canvas.draw(&panel, [20., 20.]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct NineSlice {
+    image: Image,
+    insets: Insets,
+    edges: SliceMode,
+    center: SliceMode,
+    width: f32,
+    height: f32,
+    mesh: Mesh,
+}
+
+impl NineSlice {
+    /// Creates a nine-slice of the given size, stretching its edges and center.
+    pub fn new(
+        gfx: &impl Has<GraphicsContext>,
+        image: Image,
+        insets: Insets,
+        width: f32,
+        height: f32,
+    ) -> Self {
+        let (vertices, indices) = slice_geometry(
+            (image.width() as f32, image.height() as f32),
+            insets,
+            (width, height),
+            SliceMode::Stretch,
+            SliceMode::Stretch,
+        );
+        let mesh = Mesh::from_data(
+            gfx,
+            MeshData {
+                vertices: &vertices,
+                indices: &indices,
+            },
+        );
+        NineSlice {
+            image,
+            insets,
+            edges: SliceMode::Stretch,
+            center: SliceMode::Stretch,
+            width: width.max(0.),
+            height: height.max(0.),
+            mesh,
+        }
+    }
+
+    /// Sets how the edges and the center fill their space, returning the nine-slice.
+    #[must_use]
+    pub fn with_modes(
+        mut self,
+        gfx: &impl Has<GraphicsContext>,
+        edges: SliceMode,
+        center: SliceMode,
+    ) -> Self {
+        self.set_modes(gfx, edges, center);
+        self
+    }
+
+    /// Sets how the edges and the center fill their space.
+    pub fn set_modes(
+        &mut self,
+        gfx: &impl Has<GraphicsContext>,
+        edges: SliceMode,
+        center: SliceMode,
+    ) {
+        self.edges = edges;
+        self.center = center;
+        self.rebuild(gfx);
+    }
+
+    /// Sets the size the nine-slice is drawn at, in pixels before any scaling by [`DrawParam`].
+    pub fn set_size(&mut self, gfx: &impl Has<GraphicsContext>, width: f32, height: f32) {
+        self.width = width.max(0.);
+        self.height = height.max(0.);
+        self.rebuild(gfx);
+    }
+
+    /// Sets the widths of the borders.
+    pub fn set_insets(&mut self, gfx: &impl Has<GraphicsContext>, insets: Insets) {
+        self.insets = insets;
+        self.rebuild(gfx);
+    }
+
+    /// Returns the image the nine-slice is cut from.
+    #[inline]
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Returns the widths of the borders.
+    #[inline]
+    pub fn insets(&self) -> Insets {
+        self.insets
+    }
+
+    /// Returns how the edges fill their space.
+    #[inline]
+    pub fn edge_mode(&self) -> SliceMode {
+        self.edges
+    }
+
+    /// Returns how the center fills its space.
+    #[inline]
+    pub fn center_mode(&self) -> SliceMode {
+        self.center
+    }
+
+    /// Returns the width the nine-slice is drawn at.
+    #[inline]
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Returns the height the nine-slice is drawn at.
+    #[inline]
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    fn rebuild(&mut self, gfx: &impl Has<GraphicsContext>) {
+        let (vertices, indices) = slice_geometry(
+            (self.image.width() as f32, self.image.height() as f32),
+            self.insets,
+            (self.width, self.height),
+            self.edges,
+            self.center,
+        );
+        self.mesh = Mesh::from_data(
+            gfx,
+            MeshData {
+                vertices: &vertices,
+                indices: &indices,
+            },
+        );
+    }
+}
+
+impl Drawable for NineSlice {
+    fn draw(&self, canvas: &mut Canvas, param: impl Into<DrawParam>) {
+        canvas.draw_textured_mesh(self.mesh.clone(), self.image.clone(), param);
+    }
+
+    fn dimensions(&self, _gfx: &impl Has<GraphicsContext>) -> Option<Rect> {
+        Some(Rect::new(0., 0., self.width, self.height))
+    }
+}
+
+/// Splits `[0, size]` into three spans for the borders and the middle,
+/// returned as `(destination, source)` ranges.
+fn spans(image: f32, start: f32, end: f32, size: f32) -> [((f32, f32), (f32, f32)); 3] {
+    let start = start.clamp(0., image);
+    let end = end.clamp(0., image - start);
+    let size = size.max(0.);
+    // shrink the borders if they don't fit
+    let scale = if start + end > size {
+        size / (start + end)
+    } else {
+        1.
+    };
+    let (dest_start, dest_end) = (start * scale, size - end * scale);
+    [
+        ((0., dest_start), (0., start)),
+        ((dest_start, dest_end), (start, image - end)),
+        ((dest_end, size), (image - end, image)),
+    ]
+}
+
+/// Cuts a span into pieces of the source's length if tiled.
+fn pieces((dest, src): ((f32, f32), (f32, f32)), mode: SliceMode) -> Vec<((f32, f32), (f32, f32))> {
+    let length = src.1 - src.0;
+    if dest.1 <= dest.0 {
+        return Vec::new();
+    }
+    if mode == SliceMode::Stretch || length <= 0. {
+        return vec![(dest, src)];
+    }
+    let mut pieces = Vec::new();
+    let mut start = dest.0;
+    while start < dest.1 {
+        let piece = length.min(dest.1 - start);
+        pieces.push(((start, start + piece), (src.0, src.0 + piece)));
+        start += length;
+    }
+    pieces
+}
+
+/// Builds the vertices and indices of a nine-slice, with positions in pixels and UVs
+/// normalized to the image.
+fn slice_geometry(
+    (image_w, image_h): (f32, f32),
+    insets: Insets,
+    (width, height): (f32, f32),
+    edges: SliceMode,
+    center: SliceMode,
+) -> (Vec<Vertex>, Vec<u32>) {
+    let columns = spans(image_w, insets.left, insets.right, width);
+    let rows = spans(image_h, insets.top, insets.bottom, height);
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for (row, &row_span) in rows.iter().enumerate() {
+        for (column, &column_span) in columns.iter().enumerate() {
+            // only the middle row and column are tiled, along their length
+            let mode = match (column == 1, row == 1) {
+                (true, true) => center,
+                (true, false) | (false, true) => edges,
+                (false, false) => SliceMode::Stretch,
+            };
+            let x_mode = if column == 1 {
+                mode
+            } else {
+                SliceMode::Stretch
+            };
+            let y_mode = if row == 1 { mode } else { SliceMode::Stretch };
+            for (y, v) in pieces(row_span, y_mode) {
+                for (x, u) in pieces(column_span, x_mode) {
+                    let first = vertices.len() as u32;
+                    for (px, py, pu, pv) in [
+                        (x.0, y.0, u.0, v.0),
+                        (x.1, y.0, u.1, v.0),
+                        (x.1, y.1, u.1, v.1),
+                        (x.0, y.1, u.0, v.1),
+                    ] {
+                        vertices.push(Vertex {
+                            position: [px, py],
+                            uv: [pu / image_w, pv / image_h],
+                            color: [1.; 4],
+                        });
+                    }
+                    indices.extend([0, 1, 2, 0, 2, 3].map(|i| first + i));
+                }
+            }
+        }
+    }
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geometry() {
+        let insets = Insets::new(4., 2., 4., 2.);
+        let stretch = SliceMode::Stretch;
+        let (vertices, indices) = slice_geometry((16., 8.), insets, (40., 20.), stretch, stretch);
+        assert_eq!((vertices.len(), indices.len()), (9 * 4, 9 * 6));
+        // the bottom right corner keeps its size and maps to the image's corner
+        assert_eq!(vertices[8 * 4].position, [36., 18.]);
+        assert_eq!(vertices[8 * 4].uv, [0.75, 0.75]);
+        assert_eq!(vertices[8 * 4 + 2].position, [40., 20.]);
+        assert_eq!(vertices[8 * 4 + 2].uv, [1., 1.]);
+
+        // 34 pixels of 8 pixel wide top and bottom edges, and 16 pixels of 4 pixel high sides
+        let (vertices, _) = slice_geometry((16., 8.), insets, (42., 20.), SliceMode::Tile, stretch);
+        assert_eq!(vertices.len() / 4, 4 + 2 * 5 + 2 * 4 + 1);
+        // the same for the center, with its last column cut off
+        let (vertices, _) = slice_geometry((16., 8.), insets, (42., 20.), stretch, SliceMode::Tile);
+        assert_eq!(vertices.len() / 4, 8 + 5 * 4);
+        assert!(vertices
+            .chunks(4)
+            .any(|quad| quad[0].position == [36., 14.] && quad[2].uv == [6. / 16., 0.75]));
+
+        // borders shrink when they don't fit, and the middle disappears
+        let (vertices, _) = slice_geometry((16., 8.), insets, (4., 4.), stretch, stretch);
+        assert_eq!(vertices.len() / 4, 4);
+        assert_eq!(vertices[4].position, [2., 0.]);
+    }
+}
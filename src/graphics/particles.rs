@@ -0,0 +1,612 @@
+use super::{Canvas, Color, DrawParam, Drawable, GraphicsContext, Image, InstanceArray, Rect};
+use crate::{context::Has, timer::TimeContext};
+use glam::Vec2;
+use std::{f32::consts::TAU, time::Duration};
+
+/// The area in which an [`Emitter`] spawns particles, centered on its position.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum EmitterShape {
+    /// Spawn all particles at the emitter's position.
+    #[default]
+    Point,
+    /// Spawn particles anywhere inside a circle.
+    Circle {
+        /// Radius of the circle.
+        radius: f32,
+    },
+    /// Spawn particles anywhere inside a rectangle.
+    Rect {
+        /// Width of the rectangle.
+        width: f32,
+        /// Height of the rectangle.
+        height: f32,
+    },
+}
+
+/// Values over a particle's lifetime, linearly interpolated between keys.
+///
+/// Keys are pairs of a point in the lifetime, from 0.0 when the particle spawns to 1.0 when it
+/// disappears, and the value at that point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T: Copy> Curve<T> {
+    /// Creates a curve from its keys, which don't need to be sorted.
+    ///
+    /// Panics if there are no keys.
+    pub fn new(keys: impl IntoIterator<Item = (f32, T)>) -> Self {
+        let mut keys: Vec<_> = keys.into_iter().collect();
+        assert!(!keys.is_empty(), "a curve needs at least one key");
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Curve { keys }
+    }
+
+    /// Creates a curve with the same value over the whole lifetime.
+    pub fn constant(value: T) -> Self {
+        Curve {
+            keys: vec![(0., value)],
+        }
+    }
+
+    /// Returns the keys of the curve, sorted by their point in the lifetime.
+    #[inline]
+    pub fn keys(&self) -> &[(f32, T)] {
+        &self.keys
+    }
+
+    fn sample_with(&self, t: f32, lerp: fn(T, T, f32) -> T) -> T {
+        let next = self.keys.partition_point(|key| key.0 <= t);
+        if next == 0 {
+            return self.keys[0].1;
+        }
+        let (start, from) = self.keys[next - 1];
+        match self.keys.get(next) {
+            Some(&(end, to)) => lerp(from, to, (t - start) / (end - start)),
+            None => from,
+        }
+    }
+}
+
+impl Curve<f32> {
+    /// Returns the value at the given point in the lifetime.
+    pub fn sample(&self, t: f32) -> f32 {
+        self.sample_with(t, |a, b, t| a + (b - a) * t)
+    }
+}
+
+impl Curve<Color> {
+    /// Returns the color at the given point in the lifetime.
+    pub fn sample(&self, t: f32) -> Color {
+        self.sample_with(t, |a, b, t| {
+            Color::new(
+                a.r + (b.r - a.r) * t,
+                a.g + (b.g - a.g) * t,
+                a.b + (b.b - a.b) * t,
+                a.a + (b.a - a.a) * t,
+            )
+        })
+    }
+}
+
+/// A single particle of a [`ParticleSystem`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Particle {
+    /// Position of the particle's center.
+    pub position: mint::Point2<f32>,
+    /// Velocity in pixels per second.
+    pub velocity: mint::Vector2<f32>,
+    /// Rotation in radians.
+    pub rotation: f32,
+    /// Angular velocity in radians per second.
+    pub spin: f32,
+    /// Scale of the particle's image.
+    pub scale: f32,
+    /// Color the particle's image is multiplied with.
+    pub color: Color,
+    /// Seconds since the particle spawned.
+    pub age: f32,
+    /// Seconds the particle lives in total.
+    pub lifetime: f32,
+}
+
+impl Particle {
+    /// Returns how far the particle is through its lifetime, from 0.0 to 1.0.
+    #[inline]
+    pub fn life(&self) -> f32 {
+        if self.lifetime > 0. {
+            (self.age / self.lifetime).min(1.)
+        } else {
+            1.
+        }
+    }
+}
+
+/// Changes the particles of a [`ParticleSystem`] every time it advances.
+///
+/// Modifiers are applied in the order they were added, before the particles move.
+#[derive(Debug, Clone)]
+pub enum Modifier {
+    /// Accelerates particles, in pixels per second squared.
+    Gravity(mint::Vector2<f32>),
+    /// Slows particles down by this fraction of their velocity per second.
+    Drag(f32),
+    /// Sets the color of particles over their lifetime.
+    Color(Curve<Color>),
+    /// Sets the scale of particles over their lifetime.
+    Scale(Curve<f32>),
+    /// Calls a function with each particle and the time step in seconds.
+    Custom(fn(&mut Particle, f32)),
+}
+
+impl Modifier {
+    fn apply(&self, particle: &mut Particle, dt: f32) {
+        match self {
+            Modifier::Gravity(gravity) => {
+                let velocity = Vec2::from(particle.velocity) + Vec2::from(*gravity) * dt;
+                particle.velocity = velocity.into();
+            }
+            Modifier::Drag(drag) => {
+                let velocity = Vec2::from(particle.velocity) * (1. - drag * dt).max(0.);
+                particle.velocity = velocity.into();
+            }
+            Modifier::Color(curve) => particle.color = curve.sample(particle.life()),
+            Modifier::Scale(curve) => particle.scale = curve.sample(particle.life()),
+            Modifier::Custom(modify) => modify(particle, dt),
+        }
+    }
+}
+
+/// Spawns particles for a [`ParticleSystem`], continuously at some rate and/or in bursts.
+///
+/// Values given as a range are picked at random for each particle.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Emitter {
+    position: mint::Point2<f32>,
+    shape: EmitterShape,
+    rate: f32,
+    lifetime: (f32, f32),
+    speed: (f32, f32),
+    direction: f32,
+    spread: f32,
+    rotation: (f32, f32),
+    spin: (f32, f32),
+    scale: f32,
+    color: Color,
+    accumulated: f32,
+    pending: usize,
+}
+
+impl Emitter {
+    /// Creates an emitter at the origin that doesn't spawn anything yet.
+    ///
+    /// Particles live for one second, don't move or rotate and are white by default.
+    pub fn new(shape: EmitterShape) -> Self {
+        Emitter {
+            position: [0., 0.].into(),
+            shape,
+            rate: 0.,
+            lifetime: (1., 1.),
+            speed: (0., 0.),
+            direction: 0.,
+            spread: TAU,
+            rotation: (0., 0.),
+            spin: (0., 0.),
+            scale: 1.,
+            color: Color::WHITE,
+            accumulated: 0.,
+            pending: 0,
+        }
+    }
+
+    /// Sets the position of the emitter.
+    #[must_use]
+    pub fn with_position(mut self, position: impl Into<mint::Point2<f32>>) -> Self {
+        self.set_position(position);
+        self
+    }
+
+    /// Sets how many particles are spawned per second.
+    #[must_use]
+    pub fn with_rate(mut self, rate: f32) -> Self {
+        self.set_rate(rate);
+        self
+    }
+
+    /// Sets the range of particle lifetimes, in seconds.
+    #[must_use]
+    pub fn with_lifetime(mut self, min: f32, max: f32) -> Self {
+        self.lifetime = (min, max);
+        self
+    }
+
+    /// Sets the range of initial particle speeds, in pixels per second.
+    #[must_use]
+    pub fn with_speed(mut self, min: f32, max: f32) -> Self {
+        self.speed = (min, max);
+        self
+    }
+
+    /// Sets the direction particles move in, as an angle in radians, and the size of the arc
+    /// around it they are spread over. By default, particles move in all directions.
+    #[must_use]
+    pub fn with_direction(mut self, direction: f32, spread: f32) -> Self {
+        self.direction = direction;
+        self.spread = spread;
+        self
+    }
+
+    /// Sets the range of initial particle rotations, in radians.
+    #[must_use]
+    pub fn with_rotation(mut self, min: f32, max: f32) -> Self {
+        self.rotation = (min, max);
+        self
+    }
+
+    /// Sets the range of particle angular velocities, in radians per second.
+    #[must_use]
+    pub fn with_spin(mut self, min: f32, max: f32) -> Self {
+        self.spin = (min, max);
+        self
+    }
+
+    /// Sets the initial scale of particles.
+    #[must_use]
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets the initial color of particles.
+    #[must_use]
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Returns the position of the emitter.
+    #[inline]
+    pub fn position(&self) -> mint::Point2<f32> {
+        self.position
+    }
+
+    /// Moves the emitter. Particles already spawned stay where they are.
+    pub fn set_position(&mut self, position: impl Into<mint::Point2<f32>>) {
+        self.position = position.into();
+    }
+
+    /// Returns the area particles are spawned in.
+    #[inline]
+    pub fn shape(&self) -> EmitterShape {
+        self.shape
+    }
+
+    /// Sets the area particles are spawned in.
+    pub fn set_shape(&mut self, shape: EmitterShape) {
+        self.shape = shape;
+    }
+
+    /// Returns how many particles are spawned per second.
+    #[inline]
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    /// Sets how many particles are spawned per second. Zero stops spawning.
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.max(0.);
+    }
+
+    /// Spawns the given number of particles at once, the next time the system advances.
+    pub fn burst(&mut self, count: usize) {
+        self.pending += count;
+    }
+
+    fn spawn(&self, rng: &mut Rng) -> Particle {
+        let offset = match self.shape {
+            EmitterShape::Point => Vec2::ZERO,
+            EmitterShape::Circle { radius } => {
+                // the square root spreads particles evenly over the area
+                Vec2::from_angle(rng.next() * TAU) * radius * rng.next().sqrt()
+            }
+            EmitterShape::Rect { width, height } => {
+                Vec2::new((rng.next() - 0.5) * width, (rng.next() - 0.5) * height)
+            }
+        };
+        let direction = self.direction + (rng.next() - 0.5) * self.spread;
+        Particle {
+            position: (Vec2::from(self.position) + offset).into(),
+            velocity: (Vec2::from_angle(direction) * rng.range(self.speed)).into(),
+            rotation: rng.range(self.rotation),
+            spin: rng.range(self.spin),
+            scale: self.scale,
+            color: self.color,
+            age: 0.,
+            lifetime: rng.range(self.lifetime),
+        }
+    }
+}
+
+/// Particles spawned by [`Emitter`]s and changed by [`Modifier`]s, drawn in one draw call.
+///
+/// Useful for effects like explosions, smoke, sparks and trails. All particles are drawn with a
+/// single [`InstanceArray`], so even tens of thousands of them only cost one draw call.
+///
+/// Particles are drawn centered on their position with the system's image, or as single pixels
+/// if it has none. Their positions are independent of the emitters, so particles stay behind
+/// when an emitter moves, and the [`DrawParam`] the system is drawn with applies to all of them.
+///
+/// ```rust,no_run
+/// # use ggez::{graphics::{self, Color, Curve, Emitter, EmitterShape, Modifier, ParticleSystem}, GameResult, Context};
+/// # fn t(ctx: &mut Context, canvas: &mut graphics::Canvas) -> GameResult {
+/// let spark = graphics::Image::from_path(ctx, "/spark.png")?;
+/// let mut sparks = ParticleSystem::new(ctx, spark)
+///     .with_emitter(
+///         Emitter::new(EmitterShape::Circle { radius: 4. })
+///             .with_rate(200.)
+///             .with_lifetime(0.5, 1.)
+///             .with_speed(50., 120.)
+///             .with_direction(-std::f32::consts::FRAC_PI_2, 0.5),
+///     )
+///     .with_modifier(Modifier::Gravity([0., 200.].into()))
+///     .with_modifier(Modifier::Color(Curve::new([
+///         (0., Color::YELLOW),
+///         (1., Color::new(1., 0., 0., 0.)),
+///     ])));
+///
+/// // move the emitter along with something:
+/// sparks.emitters_mut()[0].set_position([100., 100.]);
+/// // an explosion:
+/// sparks.emitters_mut()[0].burst(500);
+/// // in update:
+/// sparks.update(ctx);
+/// // in draw:
+/// canvas.draw(&sparks, graphics::DrawParam::new());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ParticleSystem {
+    emitters: Vec<Emitter>,
+    modifiers: Vec<Modifier>,
+    particles: Vec<Particle>,
+    max_particles: usize,
+    instances: InstanceArray,
+    rng: Rng,
+}
+
+impl ParticleSystem {
+    /// Creates an empty particle system drawing its particles with the given image.
+    ///
+    /// By default, there can be up to 10,000 particles at a time.
+    pub fn new(gfx: &impl Has<GraphicsContext>, image: impl Into<Option<Image>>) -> Self {
+        ParticleSystem {
+            emitters: Vec::new(),
+            modifiers: Vec::new(),
+            particles: Vec::new(),
+            max_particles: 10_000,
+            instances: InstanceArray::new(gfx, image),
+            rng: Rng::new(0),
+        }
+    }
+
+    /// Adds an emitter, returning the system.
+    #[must_use]
+    pub fn with_emitter(mut self, emitter: Emitter) -> Self {
+        self.emitters.push(emitter);
+        self
+    }
+
+    /// Adds a modifier, returning the system.
+    #[must_use]
+    pub fn with_modifier(mut self, modifier: Modifier) -> Self {
+        self.modifiers.push(modifier);
+        self
+    }
+
+    /// Sets how many particles there can be at a time, returning the system.
+    ///
+    /// Emitters skip particles while the limit is reached.
+    #[must_use]
+    pub fn with_max_particles(mut self, max_particles: usize) -> Self {
+        self.max_particles = max_particles;
+        self
+    }
+
+    /// Seeds the random numbers used to spawn particles, returning the system.
+    ///
+    /// Systems with the same seed and settings advanced by the same steps spawn the same particles.
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    /// Returns the emitters.
+    #[inline]
+    pub fn emitters(&self) -> &[Emitter] {
+        &self.emitters
+    }
+
+    /// Returns the emitters, for example to move them or trigger bursts.
+    #[inline]
+    pub fn emitters_mut(&mut self) -> &mut Vec<Emitter> {
+        &mut self.emitters
+    }
+
+    /// Returns the modifiers.
+    #[inline]
+    pub fn modifiers(&self) -> &[Modifier] {
+        &self.modifiers
+    }
+
+    /// Returns the modifiers, to add, change or remove them.
+    #[inline]
+    pub fn modifiers_mut(&mut self) -> &mut Vec<Modifier> {
+        &mut self.modifiers
+    }
+
+    /// Returns the live particles.
+    #[inline]
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    /// Returns how many particles there can be at a time.
+    #[inline]
+    pub fn max_particles(&self) -> usize {
+        self.max_particles
+    }
+
+    /// Removes all particles, as well as pending bursts.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        for emitter in &mut self.emitters {
+            emitter.accumulated = 0.;
+            emitter.pending = 0;
+        }
+        self.instances.clear();
+    }
+
+    /// Advances the system by the last frame's [`TimeContext::delta`].
+    ///
+    /// Call this once per `update`.
+    pub fn update(&mut self, time: &impl Has<TimeContext>) {
+        self.advance(time.retrieve().delta());
+    }
+
+    /// Advances the system by the given amount of time: spawns new particles, applies the
+    /// modifiers, moves the particles and removes those at the end of their lifetime.
+    pub fn advance(&mut self, dt: Duration) {
+        let dt = dt.as_secs_f32();
+
+        for emitter in &mut self.emitters {
+            emitter.accumulated += emitter.rate * dt;
+            let count = emitter.accumulated as usize + emitter.pending;
+            emitter.accumulated = emitter.accumulated.fract();
+            emitter.pending = 0;
+
+            let count = count.min(self.max_particles.saturating_sub(self.particles.len()));
+            for _ in 0..count {
+                self.particles.push(emitter.spawn(&mut self.rng));
+            }
+        }
+
+        let modifiers = &self.modifiers;
+        self.particles.retain_mut(|particle| {
+            particle.age += dt;
+            if particle.age >= particle.lifetime {
+                return false;
+            }
+            for modifier in modifiers {
+                modifier.apply(particle, dt);
+            }
+            let velocity = Vec2::from(particle.velocity);
+            particle.position = (Vec2::from(particle.position) + velocity * dt).into();
+            particle.rotation += particle.spin * dt;
+            true
+        });
+
+        self.instances.set(self.particles.iter().map(|particle| {
+            DrawParam::new()
+                .dest(particle.position)
+                .rotation(particle.rotation)
+                .scale([particle.scale, particle.scale])
+                .offset([0.5, 0.5])
+                .color(particle.color)
+        }));
+    }
+}
+
+impl Drawable for ParticleSystem {
+    fn draw(&self, canvas: &mut Canvas, param: impl Into<DrawParam>) {
+        self.instances.draw(canvas, param);
+    }
+
+    fn dimensions(&self, gfx: &impl Has<GraphicsContext>) -> Option<Rect> {
+        self.instances.dimensions(gfx)
+    }
+}
+
+/// A small xorshift generator, so particle systems can be seeded without an extra dependency.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // splitmix64, which never turns a seed into the invalid all-zero state
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Rng((z ^ (z >> 31)) | 1)
+    }
+
+    /// Returns a number in `[0, 1)`.
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, (min, max): (f32, f32)) -> f32 {
+        min + (max - min) * self.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::test_context;
+
+    #[test]
+    fn curves() {
+        let scale = Curve::new([(1., 0.), (0., 2.), (0.5, 1.)]);
+        assert_eq!(scale.sample(-1.), 2.);
+        assert_eq!(scale.sample(0.25), 1.5);
+        assert_eq!(scale.sample(0.75), 0.5);
+        assert_eq!(scale.sample(2.), 0.);
+        assert_eq!(Curve::constant(3.).sample(0.5), 3.);
+        let color = Curve::new([(0., Color::WHITE), (1., Color::new(0., 0., 0., 0.))]);
+        assert_eq!(color.sample(0.5), Color::new(0.5, 0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn emitting() {
+        let ctx = test_context("ggez_particles_test");
+        let emitter = Emitter::new(EmitterShape::Rect {
+            width: 10.,
+            height: 10.,
+        })
+        .with_position([100., 100.])
+        .with_rate(100.)
+        .with_lifetime(0.45, 0.45)
+        .with_speed(10., 10.)
+        .with_direction(0., 0.);
+        let mut particles = ParticleSystem::new(&ctx, None)
+            .with_emitter(emitter)
+            .with_modifier(Modifier::Gravity([0., 100.].into()))
+            .with_modifier(Modifier::Scale(Curve::new([(0., 1.), (1., 3.)])))
+            .with_max_particles(60);
+
+        let step = Duration::from_millis(100);
+        particles.advance(step);
+        assert_eq!(particles.particles().len(), 10);
+        let particle = particles.particles()[0];
+        assert!((94. ..=106.).contains(&particle.position.x));
+        assert_eq!(particle.velocity, [10., 10.].into());
+        assert!((particle.scale - (1. + 2. * 0.1 / 0.45)).abs() < 1e-5);
+
+        // the lifetime limits the particles to four steps worth, and bursts to the maximum
+        for _ in 0..10 {
+            particles.advance(step);
+        }
+        assert_eq!(particles.particles().len(), 40);
+        particles.emitters_mut()[0].burst(100);
+        particles.advance(step);
+        assert_eq!(particles.particles().len(), 50);
+        particles.clear();
+        assert!(particles.particles().is_empty());
+    }
+}
@@ -0,0 +1,516 @@
+use super::{
+    BlendMode, Canvas, DrawParam, GraphicsContext, Image, Sampler, ScreenImage, Shader,
+    ShaderBuilder, ShaderParams, ShaderParamsBuilder,
+};
+use crate::{
+    context::{Has, HasMut},
+    Context, GameResult,
+};
+use crevice::std140::AsStd140;
+use std::any::Any;
+
+pub use settings::{Bloom, Blur, ColorGrade, Crt, Vignette};
+
+/// [`ShaderParams`] with their uniform type erased.
+trait PassParams {
+    fn bind(&self, canvas: &mut Canvas);
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<Uniforms: AsStd140 + 'static> PassParams for ShaderParams<Uniforms> {
+    fn bind(&self, canvas: &mut Canvas) {
+        canvas.set_shader_params(self);
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A full screen fragment shader pass of a [`PostProcessChain`].
+pub struct PostPass {
+    shader: Shader,
+    params: Option<Box<dyn PassParams>>,
+    enabled: bool,
+}
+
+impl std::fmt::Debug for PostPass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostPass")
+            .field("shader", &self.shader)
+            .field("params", &self.params.is_some())
+            .field("enabled", &self.enabled)
+            .finish()
+    }
+}
+
+impl PostPass {
+    /// Creates a pass drawing the previous pass' output with a shader.
+    ///
+    /// Only the fragment shader is usually given; the image to process is bound to
+    /// `@group(1) @binding(0)` and its sampler to `@group(1) @binding(1)`.
+    pub fn new(shader: Shader) -> Self {
+        PostPass {
+            shader,
+            params: None,
+            enabled: true,
+        }
+    }
+
+    /// Sets the parameters bound to group 3 of the shader.
+    #[must_use]
+    pub fn with_params<Uniforms: AsStd140 + 'static>(
+        mut self,
+        params: ShaderParams<Uniforms>,
+    ) -> Self {
+        self.params = Some(Box::new(params));
+        self
+    }
+
+    /// Returns the shader of the pass.
+    #[inline]
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    /// Returns the parameters of the pass to update them, if they have the given uniform type.
+    pub fn params_mut<Uniforms: AsStd140 + 'static>(
+        &mut self,
+    ) -> Option<&mut ShaderParams<Uniforms>> {
+        self.params
+            .as_mut()
+            .and_then(|params| params.as_any_mut().downcast_mut())
+    }
+
+    /// Returns whether the pass is applied.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enables or disables the pass. Disabled passes are skipped.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Creates a pass making bright parts of the frame glow.
+    pub fn bloom(ctx: &mut Context, bloom: Bloom) -> GameResult<Self> {
+        Self::built_in(ctx, include_str!("shader/post_bloom.wgsl"), &bloom)
+    }
+
+    /// Creates the two passes of a gaussian blur, one blurring horizontally and one vertically.
+    pub fn blur(ctx: &mut Context, blur: Blur) -> GameResult<[Self; 2]> {
+        let source = include_str!("shader/post_blur.wgsl");
+        let direction = |x: f32, y: f32| {
+            format!("const DIRECTION: vec2<f32> = vec2<f32>({x:.1}, {y:.1});\n{source}")
+        };
+        Ok([
+            Self::built_in(ctx, &direction(1., 0.), &blur)?,
+            Self::built_in(ctx, &direction(0., 1.), &blur)?,
+        ])
+    }
+
+    /// Creates a pass imitating a curved CRT screen with scanlines.
+    pub fn crt(ctx: &mut Context, crt: Crt) -> GameResult<Self> {
+        Self::built_in(ctx, include_str!("shader/post_crt.wgsl"), &crt)
+    }
+
+    /// Creates a pass grading the colors of the frame with a lookup table.
+    ///
+    /// The lookup table is an image of `N` slices of `N` by `N` pixels side by side, e.g. 256 by
+    /// 16 pixels, one slice per level of blue, with red increasing to the right and green
+    /// downwards in each slice. A table which doesn't change colors can be made with
+    /// [`PostPass::identity_lut`] and edited in an image editor along with a screenshot.
+    pub fn color_grade(ctx: &mut Context, lut: &Image, grade: ColorGrade) -> GameResult<Self> {
+        let shader = ShaderBuilder::new()
+            .fragment_code(include_str!("shader/post_lut.wgsl"))
+            .build(ctx)?;
+        let params = ShaderParamsBuilder::new(&grade)
+            .images(&[lut], &[Sampler::linear_clamp()], false)
+            .build(ctx);
+        Ok(PostPass::new(shader).with_params(params))
+    }
+
+    /// Creates a pass darkening the edges of the frame.
+    pub fn vignette(ctx: &mut Context, vignette: Vignette) -> GameResult<Self> {
+        Self::built_in(ctx, include_str!("shader/post_vignette.wgsl"), &vignette)
+    }
+
+    /// Creates a color grading lookup table of the given size which leaves colors unchanged.
+    pub fn identity_lut(gfx: &impl Has<GraphicsContext>, size: u32) -> Image {
+        let size = size.max(2);
+        let mut pixels = Vec::with_capacity((size * size * size * 4) as usize);
+        let level = |i: u32| (i * 255 / (size - 1)) as u8;
+        for green in 0..size {
+            for blue in 0..size {
+                for red in 0..size {
+                    pixels.extend_from_slice(&[level(red), level(green), level(blue), 255]);
+                }
+            }
+        }
+        Image::from_pixels(
+            gfx,
+            &pixels,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            size * size,
+            size,
+        )
+    }
+
+    fn built_in<Uniforms: AsStd140 + 'static>(
+        ctx: &mut Context,
+        source: &str,
+        uniforms: &Uniforms,
+    ) -> GameResult<Self> {
+        let shader = ShaderBuilder::new().fragment_code(source).build(ctx)?;
+        let params = ShaderParamsBuilder::new(uniforms).build(ctx);
+        Ok(PostPass::new(shader).with_params(params))
+    }
+
+    fn draw(&self, gfx: &mut GraphicsContext, input: &Image, output: &Image) -> GameResult {
+        let mut canvas = Canvas::from_image(gfx, output.clone(), None);
+        canvas.set_blend_mode(BlendMode::REPLACE);
+        canvas.set_shader(&self.shader);
+        if let Some(params) = &self.params {
+            params.bind(&mut canvas);
+        }
+        canvas.draw(input, DrawParam::new());
+        canvas.finish(gfx)
+    }
+}
+
+/// A chain of [`PostPass`]es applied to the frame in order.
+///
+/// The chain is applied to the frame after everything was drawn onto it with
+/// [`Canvas::from_frame`], and before the frame is presented. Each pass is a fragment shader that
+/// reads the output of the previous pass as the image in group 1 (like any shader drawing an
+/// image), and can have [`ShaderParams`] of its own in group 3. Built-in passes are available for
+/// bloom, blur, CRT screens, color grading and vignettes.
+///
+/// The chain renders into two images the size of the frame, which are resized along with the
+/// window.
+///
+/// ```rust,no_run
+/// # use ggez::{graphics::{Bloom, Canvas, Color, PostPass, PostProcessChain, Vignette}, Context, GameResult};
+/// # fn t(ctx: &mut Context) -> GameResult {
+/// let mut post = PostProcessChain::new(ctx)
+///     .with_pass(PostPass::bloom(ctx, Bloom::default())?)
+///     .with_pass(PostPass::vignette(ctx, Vignette::default())?);
+///
+/// // in draw:
+/// let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+/// // draw the scene...
+/// canvas.finish(ctx)?;
+/// post.apply(ctx)?;
+///
+/// // change the settings of a pass:
+/// let vignette = Vignette { intensity: 0.5, ..Vignette::default() };
+/// if let Some(params) = post.passes_mut()[1].params_mut::<Vignette>() {
+///     params.set_uniforms(ctx, &vignette);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PostProcessChain {
+    passes: Vec<PostPass>,
+    targets: [ScreenImage; 2],
+}
+
+impl PostProcessChain {
+    /// Creates a chain without any passes.
+    pub fn new(gfx: &impl Has<GraphicsContext>) -> Self {
+        let target = || ScreenImage::new(gfx, None, 1., 1., 1);
+        PostProcessChain {
+            passes: Vec::new(),
+            targets: [target(), target()],
+        }
+    }
+
+    /// Adds a pass to the end of the chain.
+    #[must_use]
+    pub fn with_pass(mut self, pass: PostPass) -> Self {
+        self.passes.push(pass);
+        self
+    }
+
+    /// Adds passes to the end of the chain, e.g. the two passes of [`PostPass::blur`].
+    #[must_use]
+    pub fn with_passes(mut self, passes: impl IntoIterator<Item = PostPass>) -> Self {
+        self.passes.extend(passes);
+        self
+    }
+
+    /// Returns the passes of the chain.
+    #[inline]
+    pub fn passes(&self) -> &[PostPass] {
+        &self.passes
+    }
+
+    /// Returns the passes of the chain, to add, remove, reorder or change them.
+    #[inline]
+    pub fn passes_mut(&mut self) -> &mut Vec<PostPass> {
+        &mut self.passes
+    }
+
+    /// Applies the enabled passes to the frame.
+    ///
+    /// Call this after drawing everything that should be processed onto the frame, and before the
+    /// frame ends. Anything drawn onto the frame afterwards is drawn over the processed frame,
+    /// with or without MSAA.
+    pub fn apply(&mut self, gfx: &mut impl HasMut<GraphicsContext>) -> GameResult {
+        let gfx = gfx.retrieve_mut();
+        let passes: Vec<_> = self.passes.iter().filter(|pass| pass.enabled).collect();
+        if passes.is_empty() {
+            return Ok(());
+        }
+
+        let frame = gfx.frame().clone();
+        let msaa = gfx
+            .frame_msaa_image
+            .clone()
+            .filter(|image| image.samples() > 1);
+        let targets = [self.targets[0].image(gfx), self.targets[1].image(gfx)];
+
+        // the frame is copied first, so that the last pass can draw onto it
+        let mut copy = Canvas::from_image(gfx, targets[0].clone(), None);
+        copy.set_blend_mode(BlendMode::REPLACE);
+        copy.draw(&frame, DrawParam::new());
+        copy.finish(gfx)?;
+
+        for (i, pass) in passes.iter().enumerate() {
+            let output = if i + 1 == passes.len() && msaa.is_none() {
+                &frame
+            } else {
+                &targets[(i + 1) % 2]
+            };
+            pass.draw(gfx, &targets[i % 2], output)?;
+        }
+
+        // with MSAA, drawing onto the frame later resolves the MSAA image over the frame, so the
+        // result goes into both
+        if let Some(msaa) = msaa {
+            let mut copy = Canvas::from_msaa(gfx, msaa, frame, None);
+            copy.set_blend_mode(BlendMode::REPLACE);
+            copy.draw(&targets[passes.len() % 2], DrawParam::new());
+            copy.finish(gfx)?;
+        }
+        Ok(())
+    }
+}
+
+// the `AsStd140` derive generates undocumented public types, which stay private in this module
+mod settings {
+    use crevice::std140::AsStd140;
+
+    /// Settings of [`PostPass::bloom`](super::PostPass::bloom).
+    #[derive(Debug, Copy, Clone, PartialEq, AsStd140)]
+    pub struct Bloom {
+        /// Brightness from which on pixels glow, from 0.0 to 1.0. The default is 0.7.
+        pub threshold: f32,
+        /// Strength of the glow. The default is 0.8.
+        pub intensity: f32,
+        /// How far the glow reaches, in pixels. The default is 12.0.
+        pub radius: f32,
+    }
+
+    impl Default for Bloom {
+        fn default() -> Self {
+            Bloom {
+                threshold: 0.7,
+                intensity: 0.8,
+                radius: 12.,
+            }
+        }
+    }
+
+    /// Settings of [`PostPass::blur`](super::PostPass::blur).
+    #[derive(Debug, Copy, Clone, PartialEq, AsStd140)]
+    pub struct Blur {
+        /// How far the blur reaches, in pixels. The default is 4.0.
+        pub radius: f32,
+    }
+
+    impl Default for Blur {
+        fn default() -> Self {
+            Blur { radius: 4. }
+        }
+    }
+
+    /// Settings of [`PostPass::crt`](super::PostPass::crt).
+    #[derive(Debug, Copy, Clone, PartialEq, AsStd140)]
+    pub struct Crt {
+        /// How much the screen bulges out. The default is 0.1.
+        pub curvature: f32,
+        /// How dark the gaps between scanlines are, from 0.0 to 1.0. The default is 0.25.
+        pub scanlines: f32,
+        /// Number of scanlines over the height of the screen. The default is 240.0.
+        pub line_count: f32,
+    }
+
+    impl Default for Crt {
+        fn default() -> Self {
+            Crt {
+                curvature: 0.1,
+                scanlines: 0.25,
+                line_count: 240.,
+            }
+        }
+    }
+
+    /// Settings of [`PostPass::color_grade`](super::PostPass::color_grade).
+    #[derive(Debug, Copy, Clone, PartialEq, AsStd140)]
+    pub struct ColorGrade {
+        /// How much of the graded colors is mixed into the frame, from 0.0 to 1.0. The default is 1.0.
+        pub intensity: f32,
+    }
+
+    impl Default for ColorGrade {
+        fn default() -> Self {
+            ColorGrade { intensity: 1. }
+        }
+    }
+
+    /// Settings of [`PostPass::vignette`](super::PostPass::vignette).
+    #[derive(Debug, Copy, Clone, PartialEq, AsStd140)]
+    pub struct Vignette {
+        /// Distance from the center at which the darkening starts, where 1.0 is the distance to the
+        /// corners. The default is 0.6.
+        pub radius: f32,
+        /// Distance over which the darkening fades in. The default is 0.4.
+        pub softness: f32,
+        /// How dark the edges get, from 0.0 to 1.0. The default is 0.6.
+        pub intensity: f32,
+    }
+
+    impl Default for Vignette {
+        fn default() -> Self {
+            Vignette {
+                radius: 0.6,
+                softness: 0.4,
+                intensity: 0.6,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        conf::{NumSamples, WindowMode, WindowSetup},
+        context::{test_context, test_pixel},
+        graphics::{Color, Quad, Rect},
+    };
+
+    const INVERT: &str = "
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t, s, in.uv);
+    return vec4<f32>(1.0 - color.rgb, color.a);
+}
+";
+
+    #[test]
+    fn passes() {
+        let mut ctx = test_context("ggez_post_test");
+        let invert = ShaderBuilder::new()
+            .fragment_code(INVERT)
+            .build(&ctx)
+            .unwrap();
+        let lut = PostPass::identity_lut(&ctx, 16);
+        let mut post = PostProcessChain::new(&ctx)
+            .with_pass(PostPass::new(invert))
+            .with_passes(PostPass::blur(&mut ctx, Blur::default()).unwrap())
+            .with_pass(PostPass::bloom(&mut ctx, Bloom::default()).unwrap())
+            .with_pass(PostPass::crt(&mut ctx, Crt::default()).unwrap())
+            .with_pass(PostPass::color_grade(&mut ctx, &lut, ColorGrade::default()).unwrap())
+            .with_pass(PostPass::vignette(&mut ctx, Vignette::default()).unwrap());
+        assert!(post.passes_mut()[2].params_mut::<Blur>().is_some());
+        assert!(post.passes_mut()[2].params_mut::<Bloom>().is_none());
+
+        let mut frame = |post: &mut PostProcessChain| {
+            ctx.gfx.begin_frame().unwrap();
+            Canvas::from_frame(&ctx, Color::from_rgb(200, 0, 0))
+                .finish(&mut ctx)
+                .unwrap();
+            post.apply(&mut ctx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+            let pixels = ctx.gfx.frame().to_pixels(&ctx).unwrap();
+            (test_pixel(&pixels, 32, 32), test_pixel(&pixels, 8, 8))
+        };
+
+        // only the inverted colors, darkened towards the corners
+        for (i, pass) in post.passes_mut().iter_mut().enumerate() {
+            pass.set_enabled(i == 0 || i == 6);
+        }
+        let (center, corner) = frame(&mut post);
+        assert!(center[1] > 200);
+        assert!(corner[1] < center[1]);
+
+        // everything at once
+        for pass in post.passes_mut() {
+            pass.set_enabled(true);
+        }
+        let (center, _) = frame(&mut post);
+        assert!(center[1] > 100);
+    }
+
+    #[test]
+    fn msaa() {
+        let mut ctx = crate::ContextBuilder::new("ggez_post_msaa_test", "ggez")
+            .with_conf_file(false)
+            .window_setup(WindowSetup::default().samples(NumSamples::Four))
+            .window_mode(WindowMode::default().dimensions(64., 64.))
+            .build_headless()
+            .unwrap();
+        let invert = ShaderBuilder::new()
+            .fragment_code(INVERT)
+            .build(&ctx)
+            .unwrap();
+        let mut post = PostProcessChain::new(&ctx).with_pass(PostPass::new(invert));
+
+        // some software adapters never resolve MSAA images, there's nothing to test then
+        ctx.gfx.begin_frame().unwrap();
+        Canvas::from_frame(&ctx, Color::RED)
+            .finish(&mut ctx)
+            .unwrap();
+        ctx.gfx.end_frame().unwrap();
+        let pixels = ctx.gfx.frame().to_pixels(&ctx).unwrap();
+        if test_pixel(&pixels, 32, 32) != [255, 0, 0] {
+            return;
+        }
+
+        ctx.gfx.begin_frame().unwrap();
+        Canvas::from_frame(&ctx, Color::from_rgb(200, 0, 0))
+            .finish(&mut ctx)
+            .unwrap();
+        post.apply(&mut ctx).unwrap();
+        // drawing afterwards keeps the processed frame
+        let mut canvas = Canvas::from_frame(&ctx, None);
+        canvas.draw(
+            &Quad,
+            DrawParam::new()
+                .dest_rect(Rect::new(0., 0., 8., 8.))
+                .color(Color::BLUE),
+        );
+        canvas.finish(&mut ctx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        let pixels = ctx.gfx.frame().to_pixels(&ctx).unwrap();
+        assert_eq!(test_pixel(&pixels, 32, 32)[1..], [255, 255]);
+        assert_eq!(test_pixel(&pixels, 4, 4), [0, 0, 255]);
+    }
+}
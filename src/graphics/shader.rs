@@ -11,6 +11,7 @@ use super::{
         growing::GrowingBufferArena,
     },
     image::Image,
+    mesh::VertexLayout,
     sampler::Sampler,
 };
 use crevice::std140::Std140;
@@ -27,6 +28,7 @@ enum ShaderSource<'a> {
 pub struct ShaderBuilder<'a> {
     fs: ShaderSource<'a>,
     vs: ShaderSource<'a>,
+    vertex_layout: Option<wgpu::VertexBufferLayout<'static>>,
 }
 
 impl<'a> ShaderBuilder<'a> {
@@ -35,6 +37,7 @@ impl<'a> ShaderBuilder<'a> {
         ShaderBuilder {
             fs: ShaderSource::None,
             vs: ShaderSource::None,
+            vertex_layout: None,
         }
     }
 
@@ -43,6 +46,7 @@ impl<'a> ShaderBuilder<'a> {
         ShaderBuilder {
             fs: ShaderSource::Code(source),
             vs: ShaderSource::Code(source),
+            vertex_layout: None,
         }
     }
 
@@ -51,6 +55,7 @@ impl<'a> ShaderBuilder<'a> {
         ShaderBuilder {
             fs: ShaderSource::Path(path),
             vs: ShaderSource::Path(path),
+            vertex_layout: None,
         }
     }
 
@@ -60,6 +65,7 @@ impl<'a> ShaderBuilder<'a> {
         ShaderBuilder {
             fs: ShaderSource::Code(source),
             vs: self.vs,
+            ..self
         }
     }
     /// Use this wgsl code resource path for the fragment shader.
@@ -68,6 +74,7 @@ impl<'a> ShaderBuilder<'a> {
         ShaderBuilder {
             fs: ShaderSource::Path(path),
             vs: self.vs,
+            ..self
         }
     }
 
@@ -77,6 +84,7 @@ impl<'a> ShaderBuilder<'a> {
         ShaderBuilder {
             fs: self.vs,
             vs: ShaderSource::Code(source),
+            ..self
         }
     }
 
@@ -86,6 +94,20 @@ impl<'a> ShaderBuilder<'a> {
         ShaderBuilder {
             fs: self.vs,
             vs: ShaderSource::Path(path),
+            ..self
+        }
+    }
+
+    /// Declares the [`VertexLayout`] of the meshes this shader draws, which the vertex shader
+    /// takes in `vs_main`.
+    ///
+    /// Drawing a mesh of another vertex type with the shader then fails with an error, rather than
+    /// with a pipeline validation error. Shaders without a declared layout draw any mesh.
+    #[must_use]
+    pub fn vertex_layout<V: VertexLayout>(self) -> Self {
+        ShaderBuilder {
+            vertex_layout: Some(V::layout()),
+            ..self
         }
     }
 
@@ -119,11 +141,13 @@ impl<'a> ShaderBuilder<'a> {
             Shader {
                 vs_module: module.clone(),
                 fs_module: module,
+                vertex_layout: self.vertex_layout,
             }
         } else {
             Shader {
                 vs_module: load_any(self.vs)?,
                 fs_module: load_any(self.fs)?,
+                vertex_layout: self.vertex_layout,
             }
         })
     }
@@ -150,6 +174,10 @@ impl Default for ShaderBuilder<'_> {
 /// ```
 /// if the fragment module is left unspecified (default).
 ///
+/// The vertex module takes the vertex attributes of the meshes it draws: those of
+/// [`Vertex`](super::Vertex) for most meshes, or of a custom [`VertexLayout`] declared with
+/// [`ShaderBuilder::vertex_layout`].
+///
 /// Produce a Shader using [`ShaderBuilder`].
 ///
 /// Adapted from the `shader.rs` example:
@@ -191,6 +219,7 @@ impl Default for ShaderBuilder<'_> {
 pub struct Shader {
     pub(crate) vs_module: Option<ArcShaderModule>,
     pub(crate) fs_module: Option<ArcShaderModule>,
+    pub(crate) vertex_layout: Option<wgpu::VertexBufferLayout<'static>>,
 }
 
 use crevice::std140::AsStd140;
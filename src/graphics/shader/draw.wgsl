@@ -19,20 +19,44 @@ var t: texture_2d<f32>;
 @group(1) @binding(1)
 var s: sampler;
 
+fn vertex(position: vec3<f32>, uv: vec2<f32>, color: vec4<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = uniforms.transform * vec4<f32>(position, 1.0);
+    out.uv = mix(uniforms.src_rect.xy, uniforms.src_rect.zw, uv);
+    out.color = uniforms.color * color;
+    return out;
+}
+
 @vertex
 fn vs_main(
     @location(0) position: vec2<f32>,
     @location(1) uv: vec2<f32>,
     @location(2) color: vec4<f32>,
 ) -> VertexOutput {
-    var out: VertexOutput;
-    out.position = uniforms.transform * vec4<f32>(position, 0.0, 1.0);
-    out.uv = mix(uniforms.src_rect.xy, uniforms.src_rect.zw, uv);
-    out.color = uniforms.color * color;
-    return out;
+    return vertex(vec3<f32>(position, 0.0), uv, color);
+}
+
+// used for meshes of `Vertex3d`
+@vertex
+fn vs_main_3d(
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+) -> VertexOutput {
+    return vertex(position, uv, color);
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return in.color * textureSample(t, s, in.uv);
-}
\ No newline at end of file
+}
+
+// used while drawing masks, so that transparent pixels are left out of them
+@fragment
+fn fs_mask(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = in.color * textureSample(t, s, in.uv);
+    if (color.a < 0.5) {
+        discard;
+    }
+    return color;
+}
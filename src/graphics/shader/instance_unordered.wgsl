@@ -32,12 +32,11 @@ var s: sampler;
 @group(2) @binding(0)
 var<storage, read> instances: InstanceArray;
 
-@vertex
-fn vs_main(
-    @builtin(instance_index) in_instance_index: u32,
-    @location(0) position: vec2<f32>,
-    @location(1) uv: vec2<f32>,
-    @location(2) color: vec4<f32>,
+fn vertex(
+    in_instance_index: u32,
+    position: vec3<f32>,
+    uv: vec2<f32>,
+    color: vec4<f32>,
 ) -> VertexOutput {
     var index = in_instance_index;
     var instance = instances.instances[index];
@@ -64,12 +63,33 @@ fn vs_main(
     );
 
     var out: VertexOutput;
-    out.position = uniforms.transform * instance.transform * scale_mat * vec4<f32>(position, 0.0, 1.0);
+    out.position = uniforms.transform * instance.transform * scale_mat * vec4<f32>(position, 1.0);
     out.uv = mix(instance.src_rect.xy, instance.src_rect.zw, uv);
     out.color = uniforms.color * instance.color * color;
     return out;
 }
 
+@vertex
+fn vs_main(
+    @builtin(instance_index) in_instance_index: u32,
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+) -> VertexOutput {
+    return vertex(in_instance_index, vec3<f32>(position, 0.0), uv, color);
+}
+
+// used for meshes of `Vertex3d`
+@vertex
+fn vs_main_3d(
+    @builtin(instance_index) in_instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+) -> VertexOutput {
+    return vertex(in_instance_index, position, uv, color);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return in.color * textureSample(t, s, in.uv);
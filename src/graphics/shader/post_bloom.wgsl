@@ -0,0 +1,48 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct Bloom {
+    threshold: f32,
+    intensity: f32,
+    radius: f32,
+}
+
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
+@group(3) @binding(0)
+var<uniform> bloom: Bloom;
+
+fn bright(uv: vec2<f32>) -> vec3<f32> {
+    let color = textureSampleLevel(t, s, uv, 0.0).rgb;
+    let luma = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    return color * smoothstep(bloom.threshold, bloom.threshold + 0.1, luma);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t));
+    let color = textureSampleLevel(t, s, in.uv, 0.0);
+
+    // gathers the bright parts of the surroundings on rings of 8 samples
+    var glow = bright(in.uv);
+    var weights = 1.0;
+    for (var ring = 1; ring <= 4; ring += 1) {
+        let dist = f32(ring) / 4.0;
+        let weight = exp(-dist * dist * 2.0);
+        for (var i = 0; i < 8; i += 1) {
+            let angle = (f32(i) + f32(ring) * 0.5) * 0.785398;
+            let offset = vec2<f32>(cos(angle), sin(angle)) * dist * bloom.radius * texel;
+            glow += bright(in.uv + offset) * weight;
+            weights += weight;
+        }
+    }
+
+    return vec4<f32>(color.rgb + glow / weights * bloom.intensity, color.a);
+}
@@ -0,0 +1,35 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct Blur {
+    radius: f32,
+}
+
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
+@group(3) @binding(0)
+var<uniform> blur: Blur;
+
+// DIRECTION, the axis to blur along, is prepended when the shader is built
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t));
+    let delta = DIRECTION * texel * blur.radius / 4.0;
+    var sum = vec4<f32>(0.0);
+    var weights = 0.0;
+    for (var i = -4; i <= 4; i += 1) {
+        let x = f32(i);
+        let weight = exp(-x * x / 8.0);
+        sum += textureSampleLevel(t, s, in.uv + delta * x, 0.0) * weight;
+        weights += weight;
+    }
+    return sum / weights;
+}
@@ -0,0 +1,35 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct Crt {
+    curvature: f32,
+    scanlines: f32,
+    line_count: f32,
+}
+
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
+@group(3) @binding(0)
+var<uniform> crt: Crt;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // bulges the picture out like the glass of a tube
+    let centered = in.uv * 2.0 - 1.0;
+    let bent = centered * (1.0 + crt.curvature * dot(centered, centered) * 0.25);
+    let uv = bent * 0.5 + 0.5;
+
+    var color = textureSampleLevel(t, s, uv, 0.0);
+    let line = 0.5 + 0.5 * sin(uv.y * crt.line_count * 6.283185);
+    color = vec4<f32>(color.rgb * (1.0 - crt.scanlines * (1.0 - line)), color.a);
+
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    return select(vec4<f32>(0.0, 0.0, 0.0, 1.0), color, inside);
+}
@@ -0,0 +1,51 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct ColorGrade {
+    intensity: f32,
+}
+
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
+@group(3) @binding(0)
+var<uniform> grade: ColorGrade;
+
+// a strip of N slices of N * N pixels, one slice per blue level
+@group(3) @binding(1)
+var lut: texture_2d<f32>;
+
+@group(3) @binding(2)
+var lut_sampler: sampler;
+
+fn lookup(rg: vec2<f32>, slice: f32, size: f32) -> vec3<f32> {
+    // samples the centers of the outer texels, so the slices don't bleed into each other
+    let xy = (rg * (size - 1.0) + 0.5) / size;
+    let uv = vec2<f32>((slice + xy.x) / size, xy.y);
+    return textureSampleLevel(lut, lut_sampler, uv, 0.0).rgb;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(t, s, in.uv, 0.0);
+    let size = f32(textureDimensions(lut).y);
+
+    // LUTs are made for gamma encoded colors, and the LUT image decodes its output
+    let encoded = pow(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(1.0 / 2.2));
+    let blue = encoded.b * (size - 1.0);
+    let low = floor(blue);
+    let high = min(low + 1.0, size - 1.0);
+    let graded = mix(
+        lookup(encoded.rg, low, size),
+        lookup(encoded.rg, high, size),
+        blue - low,
+    );
+
+    return vec4<f32>(mix(color.rgb, graded, grade.intensity), color.a);
+}
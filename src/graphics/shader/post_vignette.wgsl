@@ -0,0 +1,29 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct Vignette {
+    radius: f32,
+    softness: f32,
+    intensity: f32,
+}
+
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
+@group(3) @binding(0)
+var<uniform> vignette: Vignette;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(t, s, in.uv, 0.0);
+    // 1.0 in the corners
+    let dist = length(in.uv - 0.5) * 1.414214;
+    let shade = smoothstep(vignette.radius, vignette.radius + vignette.softness, dist);
+    return vec4<f32>(color.rgb * (1.0 - shade * vignette.intensity), color.a);
+}
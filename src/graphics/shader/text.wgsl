@@ -62,3 +62,13 @@ fn vs_main(
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return in.color * textureSample(t, s, in.uv).rrrr;
 }
+
+// used while drawing masks, so that transparent pixels are left out of them
+@fragment
+fn fs_mask(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = in.color * textureSample(t, s, in.uv).rrrr;
+    if (color.a < 0.5) {
+        discard;
+    }
+    return color;
+}
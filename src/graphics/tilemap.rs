@@ -0,0 +1,1030 @@
+use super::{Canvas, Color, DrawParam, Drawable, GraphicsContext, Image, InstanceArray, Rect};
+use crate::{context::Has, GameError, GameResult};
+use glam::{Mat4, Vec2, Vec4};
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+mod json;
+mod xml;
+
+/// Size of the square chunks, in tiles, that tile layers are split into for drawing.
+const CHUNK_SIZE: i32 = 16;
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const ROTATED_HEXAGONAL: u32 = 0x1000_0000;
+
+/// The custom properties of a map, layer, tile or object, by name.
+pub type Properties = HashMap<String, Property>;
+
+/// The value of a custom property set in Tiled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Property {
+    /// A `bool` property.
+    Bool(bool),
+    /// An `int` property.
+    Int(i64),
+    /// A `float` property.
+    Float(f64),
+    /// A `string` property.
+    String(String),
+    /// A `color` property.
+    Color(Color),
+    /// A `file` property, holding a path relative to the file it was set in.
+    File(String),
+    /// An `object` property, holding the id of the referenced object, or 0.
+    Object(u32),
+}
+
+/// A tile placed in a tile layer or as a tile object, possibly flipped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Tile {
+    /// The global id of the tile; see [`TileMap::tileset`] to find its tileset.
+    pub gid: u32,
+    /// Whether the tile is flipped horizontally.
+    pub flip_h: bool,
+    /// Whether the tile is flipped vertically.
+    pub flip_v: bool,
+    /// Whether the tile is flipped diagonally, swapping its x and y axes.
+    /// This is applied before the horizontal and vertical flips.
+    pub flip_d: bool,
+}
+
+impl Tile {
+    /// Decodes a global tile id with flip flags as stored by Tiled; 0 is no tile.
+    fn from_raw(raw: u32) -> Option<Self> {
+        let gid = raw
+            & !(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL);
+        (gid != 0).then_some(Tile {
+            gid,
+            flip_h: raw & FLIPPED_HORIZONTALLY != 0,
+            flip_v: raw & FLIPPED_VERTICALLY != 0,
+            flip_d: raw & FLIPPED_DIAGONALLY != 0,
+        })
+    }
+}
+
+/// A tileset of a [`TileMap`]: an image split into equally sized tiles.
+#[derive(Debug, Clone)]
+pub struct Tileset {
+    /// Name of the tileset.
+    pub name: String,
+    /// The global id of the first tile of the tileset.
+    pub first_gid: u32,
+    /// Width of a tile in pixels.
+    pub tile_width: u32,
+    /// Height of a tile in pixels.
+    pub tile_height: u32,
+    /// Number of tiles.
+    pub tile_count: u32,
+    /// Number of tile columns in the image.
+    pub columns: u32,
+    /// Space between tiles in the image, in pixels.
+    pub spacing: u32,
+    /// Space around the tiles at the edges of the image, in pixels.
+    pub margin: u32,
+    /// Offset in pixels applied when drawing tiles of this tileset.
+    pub offset: mint::Vector2<f32>,
+    /// The tileset image.
+    pub image: Image,
+    /// The custom properties of individual tiles, by their id within the tileset.
+    pub tile_properties: HashMap<u32, Properties>,
+}
+
+impl Tileset {
+    /// Returns whether the tile with the given global id belongs to this tileset.
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid - self.first_gid < self.tile_count
+    }
+
+    /// Returns the rectangle of the tile with the given global id in the tileset image, in pixels,
+    /// or `None` if the id comes before the first one of this tileset.
+    pub fn tile_rect(&self, gid: u32) -> Option<Rect> {
+        let id = gid.checked_sub(self.first_gid)?;
+        let columns = self.columns.max(1);
+        let (column, row) = (id % columns, id / columns);
+        Some(Rect::new(
+            (self.margin + column * (self.tile_width + self.spacing)) as f32,
+            (self.margin + row * (self.tile_height + self.spacing)) as f32,
+            self.tile_width as f32,
+            self.tile_height as f32,
+        ))
+    }
+
+    /// Returns the custom properties of the tile with the given global id, if it has any.
+    pub fn properties(&self, gid: u32) -> Option<&Properties> {
+        self.tile_properties.get(&gid.checked_sub(self.first_gid)?)
+    }
+}
+
+/// The shape of a [`MapObject`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectShape {
+    /// A rectangle of the object's size.
+    Rectangle,
+    /// An ellipse filling the object's size.
+    Ellipse,
+    /// A point at the object's position.
+    Point,
+    /// A closed polygon, with points relative to the object's position.
+    Polygon(Vec<mint::Point2<f32>>),
+    /// An open line, with points relative to the object's position.
+    Polyline(Vec<mint::Point2<f32>>),
+    /// A tile, drawn with its bottom-left corner at the object's position.
+    Tile(Tile),
+    /// A text box of the object's size.
+    Text(String),
+}
+
+/// An object of an object layer, as placed in Tiled, for example a spawn point or a trigger area.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapObject {
+    /// Unique id of the object in the map.
+    pub id: u32,
+    /// Name of the object.
+    pub name: String,
+    /// Class (or type, in older versions of Tiled) of the object.
+    pub class: String,
+    /// X coordinate in pixels.
+    pub x: f32,
+    /// Y coordinate in pixels.
+    pub y: f32,
+    /// Width in pixels.
+    pub width: f32,
+    /// Height in pixels.
+    pub height: f32,
+    /// Clockwise rotation around the object's position, in radians.
+    pub rotation: f32,
+    /// Whether the object is visible in Tiled.
+    pub visible: bool,
+    /// The shape of the object.
+    pub shape: ObjectShape,
+    /// Custom properties of the object.
+    pub properties: Properties,
+}
+
+/// The tiles of a tile layer.
+#[derive(Debug)]
+pub struct TileLayer {
+    chunks: Vec<Chunk>,
+    by_position: HashMap<(i32, i32), usize>,
+}
+
+#[derive(Debug)]
+struct Chunk {
+    /// Area covered by the tiles' images, in pixels relative to the layer.
+    bounds: Rect,
+    tiles: Vec<Option<Tile>>,
+    batches: Vec<InstanceArray>,
+}
+
+impl TileLayer {
+    /// Returns the tile at the given tile coordinates, if there is one.
+    pub fn tile(&self, x: i32, y: i32) -> Option<Tile> {
+        let chunk = self.by_position.get(&(
+            x.div_euclid(CHUNK_SIZE) * CHUNK_SIZE,
+            y.div_euclid(CHUNK_SIZE) * CHUNK_SIZE,
+        ))?;
+        let (cx, cy) = (x.rem_euclid(CHUNK_SIZE), y.rem_euclid(CHUNK_SIZE));
+        self.chunks[*chunk].tiles[(cy * CHUNK_SIZE + cx) as usize]
+    }
+
+    /// Returns all tiles of the layer along with their tile coordinates, in no particular order.
+    pub fn tiles(&self) -> impl Iterator<Item = (i32, i32, Tile)> + '_ {
+        self.by_position.iter().flat_map(move |(&(x, y), &chunk)| {
+            self.chunks[chunk]
+                .tiles
+                .iter()
+                .enumerate()
+                .filter_map(move |(i, tile)| {
+                    let i = i as i32;
+                    tile.map(|tile| (x + i % CHUNK_SIZE, y + i / CHUNK_SIZE, tile))
+                })
+        })
+    }
+}
+
+/// What a [`Layer`] contains.
+#[derive(Debug)]
+pub enum LayerKind {
+    /// A layer of tiles.
+    Tiles(TileLayer),
+    /// A layer of objects, which aren't drawn by the map.
+    Objects(Vec<MapObject>),
+}
+
+/// A layer of a [`TileMap`].
+///
+/// Layers in groups are flattened into the map's list of layers,
+/// with the group's offset, opacity and visibility applied.
+#[derive(Debug)]
+pub struct Layer {
+    name: String,
+    visible: bool,
+    opacity: f32,
+    offset: Vec2,
+    properties: Properties,
+    kind: LayerKind,
+}
+
+impl Layer {
+    /// Returns the name of the layer.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns whether the layer is drawn.
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Sets whether the layer is drawn.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Returns the opacity of the layer, from 0.0 to 1.0.
+    #[inline]
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Returns the offset of the layer in pixels.
+    #[inline]
+    pub fn offset(&self) -> mint::Vector2<f32> {
+        self.offset.into()
+    }
+
+    /// Returns the custom properties of the layer.
+    #[inline]
+    pub fn properties(&self) -> &Properties {
+        &self.properties
+    }
+
+    /// Returns the contents of the layer.
+    #[inline]
+    pub fn kind(&self) -> &LayerKind {
+        &self.kind
+    }
+
+    /// Returns the tiles of the layer, if it is a tile layer.
+    pub fn tiles(&self) -> Option<&TileLayer> {
+        match &self.kind {
+            LayerKind::Tiles(tiles) => Some(tiles),
+            LayerKind::Objects(_) => None,
+        }
+    }
+
+    /// Returns the objects of the layer, which is empty unless it is an object layer.
+    pub fn objects(&self) -> &[MapObject] {
+        match &self.kind {
+            LayerKind::Tiles(_) => &[],
+            LayerKind::Objects(objects) => objects,
+        }
+    }
+}
+
+/// An orthogonal tile map made with the [Tiled](https://www.mapeditor.org) map editor.
+///
+/// Maps can be loaded from `.tmx` (XML) and `.tmj` (JSON) files, with tilesets embedded
+/// or in external `.tsx` or `.tsj` files. Tilesets must consist of a single image; tile layer
+/// data may be CSV or base64 encoded, optionally compressed with zlib or gzip.
+/// Infinite maps are supported, image layers are skipped.
+///
+/// Tile layers are split into chunks, which are only drawn if they are visible on the canvas,
+/// judging by its [screen coordinates](Canvas::screen_coordinates). When drawing with a
+/// [`Camera2D`](super::Camera2D), use [`TileMap::draw_visible`] with the camera's
+/// [visible rectangle](super::Camera2D::visible_rect) instead.
+///
+/// ```rust,no_run
+/// # use ggez::{graphics::{self, Color, ObjectShape, TileMap}, Context, GameResult};
+/// # fn t(ctx: &mut Context) -> GameResult {
+/// let map = TileMap::from_path(ctx, "/levels/level1.tmx")?;
+/// for object in map.layers().iter().flat_map(|layer| layer.objects()) {
+///     if object.class == "enemy" {
+///         // spawn an enemy at (object.x, object.y)...
+///     }
+/// }
+///
+/// let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
+/// canvas.draw(&map, [0., 0.]);
+/// canvas.finish(ctx)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TileMap {
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    background: Option<Color>,
+    properties: Properties,
+    tilesets: Vec<Tileset>,
+    layers: Vec<Layer>,
+}
+
+impl TileMap {
+    /// Loads a map from a `.tmx` or `.tmj` file in the filesystem,
+    /// along with its external tilesets and tileset images.
+    pub fn from_path(gfx: &impl Has<GraphicsContext>, path: impl AsRef<Path>) -> GameResult<Self> {
+        let gfx = gfx.retrieve();
+        let path = path.as_ref();
+        let map = match extension(path).as_str() {
+            "tmx" | "xml" => xml::parse_map(&read_to_string(gfx, path)?)?,
+            "tmj" | "json" => json::parse_map(&read_to_string(gfx, path)?)?,
+            _ => {
+                return Err(load_error(format!(
+                    "{path:?} is not a Tiled map; expected a .tmx or .tmj file"
+                )))
+            }
+        };
+        if map.orientation != "orthogonal" {
+            return Err(load_error(format!(
+                "{path:?} has {} orientation, only orthogonal maps are supported",
+                map.orientation
+            )));
+        }
+
+        let mut tilesets = Vec::with_capacity(map.tilesets.len());
+        for tileset in map.tilesets {
+            let (data, base) = match tileset {
+                TilesetSource::Embedded(data) => (data, path.to_path_buf()),
+                TilesetSource::External { first_gid, source } => {
+                    let tileset_path = resolve(path, &source);
+                    let mut data = match extension(&tileset_path).as_str() {
+                        "tsx" | "xml" => xml::parse_tileset(&read_to_string(gfx, &tileset_path)?)?,
+                        "tsj" | "json" => {
+                            json::parse_tileset(&read_to_string(gfx, &tileset_path)?)?
+                        }
+                        _ => {
+                            return Err(load_error(format!(
+                            "{tileset_path:?} is not a Tiled tileset; expected a .tsx or .tsj file"
+                        )))
+                        }
+                    };
+                    data.first_gid = first_gid;
+                    (data, tileset_path)
+                }
+            };
+            let image = data.image.ok_or_else(|| {
+                load_error(format!(
+                    "tileset {:?} has no image; image collection tilesets aren't supported",
+                    data.name
+                ))
+            })?;
+            tilesets.push(Tileset {
+                name: data.name,
+                first_gid: data.first_gid,
+                tile_width: data.tile_width,
+                tile_height: data.tile_height,
+                tile_count: data.tile_count,
+                columns: data.columns,
+                spacing: data.spacing,
+                margin: data.margin,
+                offset: data.offset.into(),
+                image: Image::from_path(gfx, resolve(&base, &image))?,
+                tile_properties: data.tile_properties,
+            });
+        }
+        tilesets.sort_by_key(|t| t.first_gid);
+
+        let mut tile_map = TileMap {
+            width: map.width,
+            height: map.height,
+            tile_width: map.tile_width,
+            tile_height: map.tile_height,
+            background: map.background,
+            properties: map.properties,
+            tilesets,
+            layers: Vec::new(),
+        };
+        let mut layers = Vec::new();
+        flatten_layers(map.layers, Vec2::ZERO, 1., true, &mut layers);
+        for layer in layers {
+            let kind = match layer.kind {
+                LayerDataKind::Tiles(chunks) => {
+                    LayerKind::Tiles(tile_map.build_tile_layer(gfx, chunks, layer.opacity))
+                }
+                LayerDataKind::Objects(objects) => LayerKind::Objects(objects),
+                LayerDataKind::Group(_) => continue,
+            };
+            tile_map.layers.push(Layer {
+                name: layer.name,
+                visible: layer.visible,
+                opacity: layer.opacity,
+                offset: layer.offset,
+                properties: layer.properties,
+                kind,
+            });
+        }
+        Ok(tile_map)
+    }
+
+    /// Returns the width of the map in tiles; for infinite maps, the width set in Tiled.
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the map in tiles; for infinite maps, the height set in Tiled.
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the size of a map cell in pixels, as (width, height).
+    #[inline]
+    pub fn tile_size(&self) -> (u32, u32) {
+        (self.tile_width, self.tile_height)
+    }
+
+    /// Returns the background color of the map, if one was set.
+    #[inline]
+    pub fn background_color(&self) -> Option<Color> {
+        self.background
+    }
+
+    /// Returns the custom properties of the map.
+    #[inline]
+    pub fn properties(&self) -> &Properties {
+        &self.properties
+    }
+
+    /// Returns the tilesets of the map, ordered by their first global tile id.
+    #[inline]
+    pub fn tilesets(&self) -> &[Tileset] {
+        &self.tilesets
+    }
+
+    /// Returns the tileset the tile with the given global id belongs to.
+    pub fn tileset(&self, gid: u32) -> Option<&Tileset> {
+        self.tilesets.iter().rev().find(|t| t.first_gid <= gid)
+    }
+
+    /// Returns the layers of the map, from bottom to top.
+    #[inline]
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Returns the layers of the map mutably, for example to hide some.
+    #[inline]
+    pub fn layers_mut(&mut self) -> &mut [Layer] {
+        &mut self.layers
+    }
+
+    /// Returns the layer with the given name.
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|l| l.name == name)
+    }
+
+    /// Converts a position in pixels, relative to the map, to tile coordinates.
+    pub fn pixel_to_tile(&self, point: impl Into<mint::Point2<f32>>) -> (i32, i32) {
+        let point = point.into();
+        (
+            (point.x / self.tile_width as f32).floor() as i32,
+            (point.y / self.tile_height as f32).floor() as i32,
+        )
+    }
+
+    /// Draws the parts of the visible layers that fall into `visible`, a rectangle in the
+    /// coordinates the map is drawn into, such as [`Camera2D::visible_rect`](super::Camera2D::visible_rect).
+    pub fn draw_visible(&self, canvas: &mut Canvas, param: impl Into<DrawParam>, visible: Rect) {
+        let param = param.into();
+        let transform = Mat4::from(param.transform.to_bare_matrix());
+        let visible = transform_bounds(transform.inverse(), visible);
+        self.draw_culled(canvas, param, transform, Some(visible));
+    }
+
+    fn draw_culled(
+        &self,
+        canvas: &mut Canvas,
+        param: DrawParam,
+        transform: Mat4,
+        visible: Option<Rect>,
+    ) {
+        for layer in &self.layers {
+            let tiles = match (&layer.kind, layer.visible) {
+                (LayerKind::Tiles(tiles), true) => tiles,
+                _ => continue,
+            };
+            let param =
+                param.transform(transform * Mat4::from_translation(layer.offset.extend(0.)));
+            let visible = visible.map(|mut v| {
+                v.translate(-layer.offset);
+                v
+            });
+            for chunk in &tiles.chunks {
+                if matches!(visible, Some(v) if !v.overlaps(&chunk.bounds)) {
+                    continue;
+                }
+                for batch in &chunk.batches {
+                    canvas.draw(batch, param);
+                }
+            }
+        }
+    }
+
+    fn build_tile_layer(
+        &self,
+        gfx: &GraphicsContext,
+        data: Vec<ChunkData>,
+        opacity: f32,
+    ) -> TileLayer {
+        // regroup the tiles into aligned chunks of our own size
+        let mut layer = TileLayer {
+            chunks: Vec::new(),
+            by_position: HashMap::new(),
+        };
+        for chunk in data {
+            for (i, &raw) in chunk.gids.iter().enumerate() {
+                let tile = match Tile::from_raw(raw) {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+                let x = chunk.x + (i as u32 % chunk.width) as i32;
+                let y = chunk.y + (i as u32 / chunk.width) as i32;
+                let (cx, cy) = (
+                    x.div_euclid(CHUNK_SIZE) * CHUNK_SIZE,
+                    y.div_euclid(CHUNK_SIZE) * CHUNK_SIZE,
+                );
+                let index = *layer.by_position.entry((cx, cy)).or_insert_with(|| {
+                    layer.chunks.push(Chunk {
+                        bounds: Rect::new(0., 0., 0., 0.),
+                        tiles: vec![None; (CHUNK_SIZE * CHUNK_SIZE) as usize],
+                        batches: Vec::new(),
+                    });
+                    layer.chunks.len() - 1
+                });
+                layer.chunks[index].tiles[((y - cy) * CHUNK_SIZE + (x - cx)) as usize] = Some(tile);
+            }
+        }
+
+        let color = Color::new(1., 1., 1., opacity);
+        for (&(cx, cy), &index) in &layer.by_position {
+            let chunk = &mut layer.chunks[index];
+            let mut batches: Vec<Option<InstanceArray>> =
+                self.tilesets.iter().map(|_| None).collect();
+            let mut bounds: Option<Rect> = None;
+            for (i, tile) in chunk.tiles.iter().enumerate() {
+                let tile = match tile {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+                let ts = match self.tilesets.iter().rposition(|t| t.first_gid <= tile.gid) {
+                    Some(ts) => ts,
+                    None => continue,
+                };
+                let tileset = &self.tilesets[ts];
+                let (x, y) = (cx + i as i32 % CHUNK_SIZE, cy + i as i32 / CHUNK_SIZE);
+                // tiles are aligned to the bottom left corner of their cell
+                let dest = Rect::new(
+                    (x * self.tile_width as i32) as f32 + tileset.offset.x,
+                    ((y + 1) * self.tile_height as i32 - tileset.tile_height as i32) as f32
+                        + tileset.offset.y,
+                    tileset.tile_width as f32,
+                    tileset.tile_height as f32,
+                );
+                bounds = Some(bounds.map_or(dest, |b| b.combine_with(dest)));
+
+                let src = match tileset.tile_rect(tile.gid) {
+                    Some(src) => src,
+                    None => continue,
+                };
+                let (w, h) = (tileset.image.width() as f32, tileset.image.height() as f32);
+                let param = DrawParam::new()
+                    .src(Rect::new(src.x / w, src.y / h, src.w / w, src.h / h))
+                    .transform(tile_transform(*tile, dest))
+                    .color(color);
+                batches[ts]
+                    .get_or_insert_with(|| InstanceArray::new(gfx, tileset.image.clone()))
+                    .push(param);
+            }
+            chunk.bounds = bounds.unwrap_or_default();
+            chunk.batches = batches.into_iter().flatten().collect();
+        }
+        layer
+    }
+}
+
+impl Drawable for TileMap {
+    /// Draws the visible layers, skipping the chunks outside of the canvas' screen coordinates.
+    fn draw(&self, canvas: &mut Canvas, param: impl Into<DrawParam>) {
+        let param = param.into();
+        let transform = Mat4::from(param.transform.to_bare_matrix());
+        let visible = canvas
+            .screen_coordinates()
+            .map(|screen| transform_bounds(transform.inverse(), screen));
+        self.draw_culled(canvas, param, transform, visible);
+    }
+
+    fn dimensions(&self, _gfx: &impl Has<GraphicsContext>) -> Option<Rect> {
+        self.layers
+            .iter()
+            .filter_map(Layer::tiles)
+            .flat_map(|tiles| &tiles.chunks)
+            .map(|chunk| chunk.bounds)
+            .reduce(Rect::combine_with)
+    }
+}
+
+/// Returns the transform of a tile drawn into `dest`, the rectangle it covers unflipped.
+fn tile_transform(tile: Tile, dest: Rect) -> Mat4 {
+    let (w, h) = (dest.w, dest.h);
+    // the flips as a linear map, applied in Tiled's order
+    let mut x_axis = Vec2::X;
+    let mut y_axis = Vec2::Y;
+    let mut size = Vec2::new(w, h);
+    if tile.flip_d {
+        (x_axis, y_axis) = (Vec2::Y, Vec2::X);
+        size = Vec2::new(h, w);
+    }
+    if tile.flip_h {
+        x_axis.x = -x_axis.x;
+        y_axis.x = -y_axis.x;
+    }
+    if tile.flip_v {
+        x_axis.y = -x_axis.y;
+        y_axis.y = -y_axis.y;
+    }
+    // flip around the center of the tile, keeping its bottom left corner in place
+    let center = Vec2::new(w, h) / 2.;
+    let corner = Vec2::new(dest.x, dest.y + h - size.y);
+    let translation = corner + size / 2. - (x_axis * center.x + y_axis * center.y);
+    Mat4::from_cols(
+        x_axis.extend(0.).extend(0.),
+        y_axis.extend(0.).extend(0.),
+        Vec4::Z,
+        translation.extend(0.).extend(1.),
+    )
+}
+
+/// Returns the axis-aligned bounding box of the transformed rectangle.
+fn transform_bounds(transform: Mat4, rect: Rect) -> Rect {
+    let corners = [
+        (rect.left(), rect.top()),
+        (rect.right(), rect.top()),
+        (rect.left(), rect.bottom()),
+        (rect.right(), rect.bottom()),
+    ]
+    .map(|(x, y)| {
+        transform
+            .transform_point3(glam::Vec3::new(x, y, 0.))
+            .truncate()
+    });
+    let min = corners.iter().fold(Vec2::splat(f32::MAX), |a, &c| a.min(c));
+    let max = corners.iter().fold(Vec2::splat(f32::MIN), |a, &c| a.max(c));
+    Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+}
+
+fn flatten_layers(
+    layers: Vec<LayerData>,
+    offset: Vec2,
+    opacity: f32,
+    visible: bool,
+    out: &mut Vec<LayerData>,
+) {
+    for mut layer in layers {
+        layer.offset += offset;
+        layer.opacity *= opacity;
+        layer.visible &= visible;
+        if let LayerDataKind::Group(children) = layer.kind {
+            flatten_layers(children, layer.offset, layer.opacity, layer.visible, out);
+        } else {
+            out.push(layer);
+        }
+    }
+}
+
+// What the parsers read from map and tileset files, before loading any images.
+
+#[derive(Debug)]
+struct MapData {
+    orientation: String,
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    background: Option<Color>,
+    properties: Properties,
+    tilesets: Vec<TilesetSource>,
+    layers: Vec<LayerData>,
+}
+
+#[derive(Debug)]
+enum TilesetSource {
+    Embedded(TilesetData),
+    External { first_gid: u32, source: String },
+}
+
+#[derive(Debug, Default)]
+struct TilesetData {
+    first_gid: u32,
+    name: String,
+    tile_width: u32,
+    tile_height: u32,
+    tile_count: u32,
+    columns: u32,
+    spacing: u32,
+    margin: u32,
+    offset: Vec2,
+    image: Option<String>,
+    tile_properties: HashMap<u32, Properties>,
+}
+
+#[derive(Debug)]
+struct LayerData {
+    name: String,
+    visible: bool,
+    opacity: f32,
+    offset: Vec2,
+    properties: Properties,
+    kind: LayerDataKind,
+}
+
+#[derive(Debug)]
+enum LayerDataKind {
+    Tiles(Vec<ChunkData>),
+    Objects(Vec<MapObject>),
+    Group(Vec<LayerData>),
+}
+
+#[derive(Debug)]
+struct ChunkData {
+    x: i32,
+    y: i32,
+    width: u32,
+    gids: Vec<u32>,
+}
+
+fn load_error(msg: String) -> GameError {
+    GameError::ResourceLoadError(msg)
+}
+
+fn read_to_string(gfx: &GraphicsContext, path: &Path) -> GameResult<String> {
+    let mut s = String::new();
+    let _ = gfx.fs.open(path)?.read_to_string(&mut s)?;
+    Ok(s)
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Resolves a path relative to the file at `base`, as stored in Tiled files.
+fn resolve(base: &Path, relative: &str) -> PathBuf {
+    let mut path = if relative.starts_with('/') {
+        PathBuf::from("/")
+    } else {
+        base.parent()
+            .map_or_else(|| PathBuf::from("/"), Path::to_path_buf)
+    };
+    for component in Path::new(relative).components() {
+        match component {
+            Component::Normal(c) => path.push(c),
+            Component::ParentDir => {
+                let _ = path.pop();
+            }
+            _ => (),
+        }
+    }
+    path
+}
+
+/// Parses a `#RRGGBB` or `#AARRGGBB` color.
+fn parse_color(s: &str) -> GameResult<Color> {
+    let hex = s.trim_start_matches('#');
+    let value =
+        u32::from_str_radix(hex, 16).map_err(|_| load_error(format!("invalid color {s:?}")))?;
+    Ok(match hex.len() {
+        6 => Color::from_rgb_u32(value),
+        8 => {
+            let [a, r, g, b] = value.to_be_bytes();
+            Color::from_rgba(r, g, b, a)
+        }
+        _ => return Err(load_error(format!("invalid color {s:?}"))),
+    })
+}
+
+/// Decodes the tile data of a layer or chunk.
+fn decode_tiles(
+    data: &str,
+    encoding: Option<&str>,
+    compression: Option<&str>,
+) -> GameResult<Vec<u32>> {
+    match encoding {
+        Some("csv") => data
+            .split(',')
+            .map(|gid| {
+                gid.trim()
+                    .parse()
+                    .map_err(|_| load_error(format!("invalid tile id {gid:?}")))
+            })
+            .collect(),
+        Some("base64") => {
+            let bytes = decode_base64(data)?;
+            let bytes = match compression {
+                None | Some("") => bytes,
+                Some("zlib") => inflate(flate2::read::ZlibDecoder::new(&bytes[..]))?,
+                Some("gzip") => inflate(flate2::read::GzDecoder::new(&bytes[..]))?,
+                Some(other) => {
+                    return Err(load_error(format!(
+                        "unsupported tile layer compression {other:?}"
+                    )))
+                }
+            };
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect())
+        }
+        other => Err(load_error(format!(
+            "unsupported tile layer encoding {other:?}"
+        ))),
+    }
+}
+
+fn inflate(mut decoder: impl Read) -> GameResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let _ = decoder.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn decode_base64(data: &str) -> GameResult<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in data.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return Err(load_error(String::from("invalid base64 tile data"))),
+        };
+        buffer = buffer << 6 | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_data() {
+        assert_eq!(
+            decode_tiles("1, 2,\n0,3", Some("csv"), None).unwrap(),
+            [1, 2, 0, 3]
+        );
+        // [1, 0x80000002] as little endian bytes
+        assert_eq!(
+            decode_tiles(" AQAAAAIAAIA= ", Some("base64"), None).unwrap(),
+            [1, 0x8000_0002]
+        );
+        let tile = Tile::from_raw(0x8000_0002).unwrap();
+        assert_eq!((tile.gid, tile.flip_h, tile.flip_v), (2, true, false));
+        assert_eq!(Tile::from_raw(0), None);
+        assert_eq!(
+            resolve(Path::new("/maps/level.tmx"), "../tiles/./ground.png"),
+            PathBuf::from("/tiles/ground.png")
+        );
+    }
+
+    #[test]
+    fn tileset_ids() {
+        let ctx = crate::context::test_context("ggez_tilemap_test");
+        let tileset = Tileset {
+            name: String::from("tiles"),
+            first_gid: 10,
+            tile_width: 8,
+            tile_height: 8,
+            tile_count: 4,
+            columns: 2,
+            spacing: 1,
+            margin: 2,
+            offset: mint::Vector2 { x: 0., y: 0. },
+            image: Image::from_color(&ctx, 19, 19, None),
+            tile_properties: HashMap::from([(3, Properties::new())]),
+        };
+        assert_eq!(tileset.tile_rect(13), Some(Rect::new(11., 11., 8., 8.)));
+        assert_eq!(tileset.tile_rect(9), None);
+        assert!(tileset.properties(13).is_some());
+        assert!(tileset.properties(9).is_none());
+        assert!(!tileset.contains(9) && tileset.contains(10) && !tileset.contains(14));
+    }
+
+    #[test]
+    fn flipped_tiles() {
+        let dest = Rect::new(10., 20., 16., 32.);
+        let corners = |tile| {
+            let m = tile_transform(tile, dest);
+            // where the top left and bottom right corners of the tile image end up
+            [
+                m.transform_point3(glam::Vec3::ZERO).truncate(),
+                m.transform_point3(glam::Vec3::new(16., 32., 0.)).truncate(),
+            ]
+        };
+        let tile = Tile {
+            gid: 1,
+            flip_h: false,
+            flip_v: false,
+            flip_d: false,
+        };
+        assert_eq!(corners(tile), [Vec2::new(10., 20.), Vec2::new(26., 52.)]);
+        let flip_h = Tile {
+            flip_h: true,
+            ..tile
+        };
+        assert_eq!(corners(flip_h), [Vec2::new(26., 20.), Vec2::new(10., 52.)]);
+        // transposed, and still sitting on the bottom of the cell
+        let flip_d = Tile {
+            flip_d: true,
+            ..tile
+        };
+        assert_eq!(corners(flip_d), [Vec2::new(10., 36.), Vec2::new(42., 52.)]);
+    }
+
+    #[test]
+    fn formats() {
+        let tmx = r##"<?xml version="1.0" encoding="UTF-8"?>
+<map orientation="orthogonal" width="2" height="2" tilewidth="8" tileheight="8" backgroundcolor="#ff0000">
+ <tileset firstgid="1" source="tiles.tsx"/>
+ <group name="group" opacity="0.5">
+  <layer name="ground" width="2" height="2">
+   <data encoding="csv">1,2,0,2147483651</data>
+  </layer>
+ </group>
+ <objectgroup name="things">
+  <object id="4" name="spawn" x="8" y="4" rotation="90">
+   <properties><property name="hp" type="int" value="3"/></properties>
+   <polygon points="0,0 8,0 8,8"/>
+  </object>
+ </objectgroup>
+</map>"##;
+        let tmj = r##"{
+ "orientation": "orthogonal", "width": 2, "height": 2, "tilewidth": 8, "tileheight": 8,
+ "backgroundcolor": "#ff0000",
+ "tilesets": [{ "firstgid": 1, "source": "tiles.tsx" }],
+ "layers": [
+  { "type": "group", "name": "group", "opacity": 0.5, "layers": [
+   { "type": "tilelayer", "name": "ground", "width": 2, "height": 2, "data": [1, 2, 0, 2147483651] }
+  ]},
+  { "type": "objectgroup", "name": "things", "objects": [
+   { "id": 4, "name": "spawn", "x": 8, "y": 4, "rotation": 90,
+     "properties": [{ "name": "hp", "type": "int", "value": 3 }],
+     "polygon": [{ "x": 0, "y": 0 }, { "x": 8, "y": 0 }, { "x": 8, "y": 8 }] }
+  ]}
+ ]
+}"##;
+        for map in [xml::parse_map(tmx).unwrap(), json::parse_map(tmj).unwrap()] {
+            assert_eq!((map.width, map.tile_height), (2, 8));
+            assert_eq!(map.background, Some(Color::RED));
+            assert!(matches!(
+                &map.tilesets[..],
+                [TilesetSource::External { first_gid: 1, source }] if source == "tiles.tsx"
+            ));
+
+            let group = &map.layers[0];
+            assert_eq!((group.name.as_str(), group.opacity), ("group", 0.5));
+            let ground = match &group.kind {
+                LayerDataKind::Group(layers) => &layers[0],
+                _ => panic!("expected a group"),
+            };
+            match &ground.kind {
+                LayerDataKind::Tiles(chunks) => {
+                    assert_eq!((chunks.len(), chunks[0].width), (1, 2));
+                    assert_eq!(chunks[0].gids, [1, 2, 0, 0x8000_0003]);
+                }
+                _ => panic!("expected tiles"),
+            }
+
+            let object = match &map.layers[1].kind {
+                LayerDataKind::Objects(objects) => &objects[0],
+                _ => panic!("expected objects"),
+            };
+            assert_eq!((object.id, object.name.as_str()), (4, "spawn"));
+            assert_eq!(object.rotation, 90f32.to_radians());
+            assert_eq!(object.properties["hp"], Property::Int(3));
+            assert!(matches!(&object.shape, ObjectShape::Polygon(points) if points.len() == 3));
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Parsing of Tiled's JSON map (`.tmj`) and tileset (`.tsj`) formats.
+
+use super::{
+    decode_tiles, load_error, parse_color, ChunkData, LayerData, LayerDataKind, MapData, MapObject,
+    ObjectShape, Properties, Property, Tile, TilesetData, TilesetSource,
+};
+use crate::GameResult;
+use glam::Vec2;
+use serde_json::{Map, Value};
+
+type Object = Map<String, Value>;
+
+pub(super) fn parse_map(text: &str) -> GameResult<MapData> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|e| load_error(format!("invalid map: {e}")))?;
+    let map = object(&value, "map")?;
+
+    let tilesets = array(map, "tilesets")
+        .iter()
+        .map(|tileset| {
+            let tileset = object(tileset, "tileset")?;
+            let first_gid = uint(tileset.get("firstgid"), 1);
+            Ok(match tileset.get("source").and_then(Value::as_str) {
+                Some(source) => TilesetSource::External {
+                    first_gid,
+                    source: source.to_string(),
+                },
+                None => TilesetSource::Embedded(TilesetData {
+                    first_gid,
+                    ..parse_tileset_object(tileset)?
+                }),
+            })
+        })
+        .collect::<GameResult<_>>()?;
+
+    Ok(MapData {
+        orientation: string(map, "orientation"),
+        width: uint(map.get("width"), 0),
+        height: uint(map.get("height"), 0),
+        tile_width: uint(map.get("tilewidth"), 0),
+        tile_height: uint(map.get("tileheight"), 0),
+        background: map
+            .get("backgroundcolor")
+            .and_then(Value::as_str)
+            .map(parse_color)
+            .transpose()?,
+        properties: properties(map)?,
+        tilesets,
+        layers: layers(map)?,
+    })
+}
+
+pub(super) fn parse_tileset(text: &str) -> GameResult<TilesetData> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|e| load_error(format!("invalid tileset: {e}")))?;
+    parse_tileset_object(object(&value, "tileset")?)
+}
+
+fn parse_tileset_object(tileset: &Object) -> GameResult<TilesetData> {
+    let mut tile_properties = std::collections::HashMap::new();
+    for tile in array(tileset, "tiles") {
+        let tile = object(tile, "tile")?;
+        let properties = properties(tile)?;
+        if !properties.is_empty() {
+            let _ = tile_properties.insert(uint(tile.get("id"), 0), properties);
+        }
+    }
+    let offset = tileset.get("tileoffset").and_then(Value::as_object);
+    Ok(TilesetData {
+        first_gid: 0,
+        name: string(tileset, "name"),
+        tile_width: uint(tileset.get("tilewidth"), 0),
+        tile_height: uint(tileset.get("tileheight"), 0),
+        tile_count: uint(tileset.get("tilecount"), 0),
+        columns: uint(tileset.get("columns"), 0),
+        spacing: uint(tileset.get("spacing"), 0),
+        margin: uint(tileset.get("margin"), 0),
+        offset: offset.map_or(Vec2::ZERO, |o| {
+            Vec2::new(float(o.get("x"), 0.), float(o.get("y"), 0.))
+        }),
+        image: tileset
+            .get("image")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        tile_properties,
+    })
+}
+
+fn layers(parent: &Object) -> GameResult<Vec<LayerData>> {
+    let mut parsed = Vec::new();
+    for layer in array(parent, "layers") {
+        let layer = object(layer, "layer")?;
+        let kind = match layer.get("type").and_then(Value::as_str) {
+            Some("tilelayer") => LayerDataKind::Tiles(tile_chunks(layer)?),
+            Some("objectgroup") => LayerDataKind::Objects(
+                array(layer, "objects")
+                    .iter()
+                    .map(|o| parse_object(object(o, "object")?))
+                    .collect::<GameResult<_>>()?,
+            ),
+            Some("group") => LayerDataKind::Group(layers(layer)?),
+            _ => continue,
+        };
+        parsed.push(LayerData {
+            name: string(layer, "name"),
+            visible: layer
+                .get("visible")
+                .and_then(Value::as_bool)
+                .unwrap_or(true),
+            opacity: float(layer.get("opacity"), 1.),
+            offset: Vec2::new(
+                float(layer.get("offsetx"), 0.),
+                float(layer.get("offsety"), 0.),
+            ),
+            properties: properties(layer)?,
+            kind,
+        });
+    }
+    Ok(parsed)
+}
+
+fn tile_chunks(layer: &Object) -> GameResult<Vec<ChunkData>> {
+    let encoding = layer.get("encoding").and_then(Value::as_str);
+    let compression = layer.get("compression").and_then(Value::as_str);
+    let chunk = |chunk: &Object| -> GameResult<ChunkData> {
+        let gids = match chunk.get("data") {
+            Some(Value::Array(gids)) => gids.iter().map(|gid| uint(Some(gid), 0)).collect(),
+            Some(Value::String(data)) => decode_tiles(data, encoding, compression)?,
+            _ => Vec::new(),
+        };
+        Ok(ChunkData {
+            x: int(chunk.get("x")),
+            y: int(chunk.get("y")),
+            width: uint(chunk.get("width"), 1).max(1),
+            gids,
+        })
+    };
+    match layer.get("chunks").and_then(Value::as_array) {
+        Some(chunks) => chunks.iter().map(|c| chunk(object(c, "chunk")?)).collect(),
+        None => Ok(vec![chunk(layer)?]),
+    }
+}
+
+fn parse_object(o: &Object) -> GameResult<MapObject> {
+    let points = |key| {
+        array(o, key)
+            .iter()
+            .filter_map(Value::as_object)
+            .map(|p| mint::Point2 {
+                x: float(p.get("x"), 0.),
+                y: float(p.get("y"), 0.),
+            })
+            .collect()
+    };
+    let shape = if let Some(gid) = o.get("gid") {
+        match Tile::from_raw(uint(Some(gid), 0)) {
+            Some(tile) => ObjectShape::Tile(tile),
+            None => ObjectShape::Rectangle,
+        }
+    } else if o.contains_key("polygon") {
+        ObjectShape::Polygon(points("polygon"))
+    } else if o.contains_key("polyline") {
+        ObjectShape::Polyline(points("polyline"))
+    } else if flag(o, "ellipse") {
+        ObjectShape::Ellipse
+    } else if flag(o, "point") {
+        ObjectShape::Point
+    } else if let Some(text) = o.get("text").and_then(Value::as_object) {
+        ObjectShape::Text(string(text, "text"))
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    let class = match o.get("class").and_then(Value::as_str) {
+        Some(class) => class.to_string(),
+        None => string(o, "type"),
+    };
+    Ok(MapObject {
+        id: uint(o.get("id"), 0),
+        name: string(o, "name"),
+        class,
+        x: float(o.get("x"), 0.),
+        y: float(o.get("y"), 0.),
+        width: float(o.get("width"), 0.),
+        height: float(o.get("height"), 0.),
+        rotation: float(o.get("rotation"), 0.).to_radians(),
+        visible: o.get("visible").and_then(Value::as_bool).unwrap_or(true),
+        shape,
+        properties: properties(o)?,
+    })
+}
+
+fn properties(parent: &Object) -> GameResult<Properties> {
+    let mut properties = Properties::new();
+    for property in array(parent, "properties") {
+        let property = object(property, "property")?;
+        let value = property.get("value");
+        let value = match property.get("type").and_then(Value::as_str) {
+            Some("bool") => Property::Bool(value.and_then(Value::as_bool).unwrap_or_default()),
+            Some("int") => Property::Int(value.and_then(Value::as_i64).unwrap_or_default()),
+            Some("float") => Property::Float(value.and_then(Value::as_f64).unwrap_or_default()),
+            Some("color") => match value.and_then(Value::as_str) {
+                Some(color) if !color.is_empty() => Property::Color(parse_color(color)?),
+                _ => continue,
+            },
+            Some("file") => Property::File(string(property, "value")),
+            Some("object") => Property::Object(uint(value, 0)),
+            Some("class") => continue,
+            _ => Property::String(string(property, "value")),
+        };
+        let _ = properties.insert(string(property, "name"), value);
+    }
+    Ok(properties)
+}
+
+fn object<'a>(value: &'a Value, what: &str) -> GameResult<&'a Object> {
+    value
+        .as_object()
+        .ok_or_else(|| load_error(format!("invalid {what}: expected a JSON object")))
+}
+
+fn array<'a>(parent: &'a Object, key: &str) -> &'a [Value] {
+    parent
+        .get(key)
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+fn string(parent: &Object, key: &str) -> String {
+    parent
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn uint(value: Option<&Value>, default: u32) -> u32 {
+    value.and_then(Value::as_u64).map_or(default, |v| v as u32)
+}
+
+fn int(value: Option<&Value>) -> i32 {
+    value.and_then(Value::as_i64).unwrap_or_default() as i32
+}
+
+fn float(value: Option<&Value>, default: f32) -> f32 {
+    value.and_then(Value::as_f64).map_or(default, |v| v as f32)
+}
+
+fn flag(parent: &Object, key: &str) -> bool {
+    parent.get(key).and_then(Value::as_bool).unwrap_or_default()
+}
@@ -0,0 +1,251 @@
+//! Parsing of Tiled's XML map (`.tmx`) and tileset (`.tsx`) formats.
+
+use super::{
+    decode_tiles, load_error, parse_color, ChunkData, LayerData, LayerDataKind, MapData, MapObject,
+    ObjectShape, Properties, Property, Tile, TilesetData, TilesetSource,
+};
+use crate::GameResult;
+use glam::Vec2;
+use roxmltree::{Document, Node};
+use std::{collections::HashMap, str::FromStr};
+
+pub(super) fn parse_map(text: &str) -> GameResult<MapData> {
+    let document = Document::parse(text).map_err(|e| load_error(format!("invalid map: {e}")))?;
+    let map = document.root_element();
+    if !map.has_tag_name("map") {
+        return Err(load_error(String::from("invalid map: expected a <map>")));
+    }
+
+    let tilesets = children(map, "tileset")
+        .map(|tileset| {
+            let first_gid = attribute(tileset, "firstgid", 1);
+            Ok(match tileset.attribute("source") {
+                Some(source) => TilesetSource::External {
+                    first_gid,
+                    source: source.to_string(),
+                },
+                None => TilesetSource::Embedded(TilesetData {
+                    first_gid,
+                    ..parse_tileset_node(tileset)?
+                }),
+            })
+        })
+        .collect::<GameResult<_>>()?;
+
+    Ok(MapData {
+        orientation: string(map, "orientation"),
+        width: attribute(map, "width", 0),
+        height: attribute(map, "height", 0),
+        tile_width: attribute(map, "tilewidth", 0),
+        tile_height: attribute(map, "tileheight", 0),
+        background: map
+            .attribute("backgroundcolor")
+            .map(parse_color)
+            .transpose()?,
+        properties: properties(map)?,
+        tilesets,
+        layers: layers(map)?,
+    })
+}
+
+pub(super) fn parse_tileset(text: &str) -> GameResult<TilesetData> {
+    let document =
+        Document::parse(text).map_err(|e| load_error(format!("invalid tileset: {e}")))?;
+    let tileset = document.root_element();
+    if !tileset.has_tag_name("tileset") {
+        return Err(load_error(String::from(
+            "invalid tileset: expected a <tileset>",
+        )));
+    }
+    parse_tileset_node(tileset)
+}
+
+fn parse_tileset_node(tileset: Node) -> GameResult<TilesetData> {
+    let mut tile_properties = HashMap::new();
+    for tile in children(tileset, "tile") {
+        let properties = properties(tile)?;
+        if !properties.is_empty() {
+            let _ = tile_properties.insert(attribute(tile, "id", 0), properties);
+        }
+    }
+    let offset = children(tileset, "tileoffset").next();
+    Ok(TilesetData {
+        first_gid: 0,
+        name: string(tileset, "name"),
+        tile_width: attribute(tileset, "tilewidth", 0),
+        tile_height: attribute(tileset, "tileheight", 0),
+        tile_count: attribute(tileset, "tilecount", 0),
+        columns: attribute(tileset, "columns", 0),
+        spacing: attribute(tileset, "spacing", 0),
+        margin: attribute(tileset, "margin", 0),
+        offset: offset.map_or(Vec2::ZERO, |o| {
+            Vec2::new(attribute(o, "x", 0.), attribute(o, "y", 0.))
+        }),
+        image: children(tileset, "image")
+            .next()
+            .and_then(|image| image.attribute("source"))
+            .map(str::to_string),
+        tile_properties,
+    })
+}
+
+fn layers(parent: Node) -> GameResult<Vec<LayerData>> {
+    let mut parsed = Vec::new();
+    for layer in parent.children().filter(Node::is_element) {
+        let kind = match layer.tag_name().name() {
+            "layer" => LayerDataKind::Tiles(tile_chunks(layer)?),
+            "objectgroup" => LayerDataKind::Objects(
+                children(layer, "object")
+                    .map(parse_object)
+                    .collect::<GameResult<_>>()?,
+            ),
+            "group" => LayerDataKind::Group(layers(layer)?),
+            _ => continue,
+        };
+        parsed.push(LayerData {
+            name: string(layer, "name"),
+            visible: attribute(layer, "visible", 1) != 0,
+            opacity: attribute(layer, "opacity", 1.),
+            offset: Vec2::new(
+                attribute(layer, "offsetx", 0.),
+                attribute(layer, "offsety", 0.),
+            ),
+            properties: properties(layer)?,
+            kind,
+        });
+    }
+    Ok(parsed)
+}
+
+fn tile_chunks(layer: Node) -> GameResult<Vec<ChunkData>> {
+    let data = match children(layer, "data").next() {
+        Some(data) => data,
+        None => return Ok(Vec::new()),
+    };
+    let encoding = data.attribute("encoding");
+    let compression = data.attribute("compression");
+    let chunk = |chunk: Node, x, y, width| -> GameResult<ChunkData> {
+        let gids = match encoding {
+            // the deprecated plain XML encoding, one element per tile
+            None => children(chunk, "tile")
+                .map(|tile| attribute(tile, "gid", 0))
+                .collect(),
+            Some(_) => decode_tiles(chunk.text().unwrap_or_default(), encoding, compression)?,
+        };
+        Ok(ChunkData {
+            x,
+            y,
+            width: u32::max(width, 1),
+            gids,
+        })
+    };
+
+    if children(data, "chunk").next().is_some() {
+        children(data, "chunk")
+            .map(|c| {
+                chunk(
+                    c,
+                    attribute(c, "x", 0),
+                    attribute(c, "y", 0),
+                    attribute(c, "width", 0),
+                )
+            })
+            .collect()
+    } else {
+        Ok(vec![chunk(data, 0, 0, attribute(layer, "width", 0))?])
+    }
+}
+
+fn parse_object(o: Node) -> GameResult<MapObject> {
+    let points = |node: Node| {
+        node.attribute("points")
+            .unwrap_or_default()
+            .split_whitespace()
+            .filter_map(|point| {
+                let (x, y) = point.split_once(',')?;
+                Some(mint::Point2 {
+                    x: x.parse().ok()?,
+                    y: y.parse().ok()?,
+                })
+            })
+            .collect()
+    };
+    let child = |name| children(o, name).next();
+    let shape = if let Some(gid) = o.attribute("gid") {
+        match gid.parse().ok().and_then(Tile::from_raw) {
+            Some(tile) => ObjectShape::Tile(tile),
+            None => ObjectShape::Rectangle,
+        }
+    } else if let Some(polygon) = child("polygon") {
+        ObjectShape::Polygon(points(polygon))
+    } else if let Some(polyline) = child("polyline") {
+        ObjectShape::Polyline(points(polyline))
+    } else if child("ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if child("point").is_some() {
+        ObjectShape::Point
+    } else if let Some(text) = child("text") {
+        ObjectShape::Text(text.text().unwrap_or_default().to_string())
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    let class = match o.attribute("class") {
+        Some(class) => class.to_string(),
+        None => string(o, "type"),
+    };
+    Ok(MapObject {
+        id: attribute(o, "id", 0),
+        name: string(o, "name"),
+        class,
+        x: attribute(o, "x", 0.),
+        y: attribute(o, "y", 0.),
+        width: attribute(o, "width", 0.),
+        height: attribute(o, "height", 0.),
+        rotation: attribute(o, "rotation", 0f32).to_radians(),
+        visible: attribute(o, "visible", 1) != 0,
+        shape,
+        properties: properties(o)?,
+    })
+}
+
+fn properties(parent: Node) -> GameResult<Properties> {
+    let mut properties = Properties::new();
+    for property in children(parent, "properties").flat_map(|p| children(p, "property")) {
+        // multi-line strings are stored as the element's text
+        let value = property
+            .attribute("value")
+            .or_else(|| property.text())
+            .unwrap_or_default();
+        let value = match property.attribute("type") {
+            Some("bool") => Property::Bool(value == "true"),
+            Some("int") => Property::Int(value.parse().unwrap_or_default()),
+            Some("float") => Property::Float(value.parse().unwrap_or_default()),
+            Some("color") if value.is_empty() => continue,
+            Some("color") => Property::Color(parse_color(value)?),
+            Some("file") => Property::File(value.to_string()),
+            Some("object") => Property::Object(value.parse().unwrap_or_default()),
+            Some("class") => continue,
+            _ => Property::String(value.to_string()),
+        };
+        let _ = properties.insert(string(property, "name"), value);
+    }
+    Ok(properties)
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |n| n.has_tag_name(name))
+}
+
+fn string(node: Node, name: &str) -> String {
+    node.attribute(name).unwrap_or_default().to_string()
+}
+
+fn attribute<T: FromStr>(node: Node, name: &str, default: T) -> T {
+    node.attribute(name)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
@@ -0,0 +1,592 @@
+//! Named input actions and axes that can be bound to keys, mouse buttons and gamepad inputs.
+//!
+//! Instead of checking for specific keys all over the game, declare the actions it understands
+//! once in an [`ActionMap`], then ask the map whether an action is pressed. Since bindings
+//! are plain data they can be changed at runtime and stored in a `/bindings.toml`
+//! in the user config directory, right next to `conf.toml`.
+//!
+//! ```rust,no_run
+//! use ggez::input::actions::{ActionMap, AxisBinding, Binding};
+//! use ggez::input::keyboard::KeyCode;
+//! use ggez::{Context, GameResult};
+//!
+//! fn setup(ctx: &Context) -> ActionMap {
+//!     let mut actions = ActionMap::new()
+//!         .with_action("jump", [Binding::Key(KeyCode::Space), Binding::Key(KeyCode::W)])
+//!         .with_axis("move", [AxisBinding::buttons(Binding::Key(KeyCode::A), Binding::Key(KeyCode::D))]);
+//!     // Players' own bindings replace the defaults above, if there are any.
+//!     let _ = actions.read_bindings(ctx);
+//!     actions
+//! }
+//!
+//! fn update(ctx: &Context, actions: &mut ActionMap) -> GameResult {
+//!     actions.update(ctx);
+//!     if actions.is_action_just_pressed("jump") {
+//!         // ...
+//!     }
+//!     let speed = actions.axis_value("move") * 100.;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{
+    context::{Context, Has},
+    error::{GameError, GameResult},
+    filesystem::Filesystem,
+    input::{keyboard::KeyCode, keyboard::ScanCode, mouse::MouseButton},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io, path,
+};
+
+const BINDINGS_NAME: &str = "/bindings.toml";
+
+/// A single physical input that can trigger an action.
+///
+/// In TOML, bindings are written as tables with a single entry, such as `{ key = "Space" }`,
+/// `{ scancode = 57 }`, `{ mouse = "Left" }`, `{ mouse_other = 4 }` or `{ gamepad_button = "South" }`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "BindingRepr", try_from = "BindingRepr")]
+pub enum Binding {
+    /// A key, by what it means in the current keyboard layout.
+    Key(KeyCode),
+    /// A key, by its location on the keyboard.
+    /// See the [`keyboard`](crate::input::keyboard) module on when to use which.
+    ScanCode(ScanCode),
+    /// A mouse button.
+    Mouse(MouseButton),
+    /// A button on any connected gamepad.
+    #[cfg(feature = "gamepad")]
+    GamepadButton(gilrs::Button),
+}
+
+/// An input producing a value between -1.0 and 1.0 for an axis.
+///
+/// In TOML, axis bindings are written as `{ negative = { key = "A" }, positive = { key = "D" } }`
+/// or `{ gamepad_axis = "LeftStickX", inverted = false }`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "AxisBindingRepr", try_from = "AxisBindingRepr")]
+pub enum AxisBinding {
+    /// Two digital inputs, producing -1.0 while `negative` is pressed and 1.0 while `positive` is pressed.
+    Buttons {
+        /// Input moving the axis towards -1.0.
+        negative: Binding,
+        /// Input moving the axis towards 1.0.
+        positive: Binding,
+    },
+    /// An analog axis on any connected gamepad.
+    #[cfg(feature = "gamepad")]
+    GamepadAxis {
+        /// The axis to read.
+        axis: gilrs::Axis,
+        /// Whether to flip the sign of the axis' value.
+        inverted: bool,
+    },
+}
+
+impl AxisBinding {
+    /// Creates an axis from two digital inputs.
+    pub fn buttons(negative: Binding, positive: Binding) -> Self {
+        AxisBinding::Buttons { negative, positive }
+    }
+
+    /// Creates an axis from an analog axis on any connected gamepad.
+    #[cfg(feature = "gamepad")]
+    pub fn gamepad_axis(axis: gilrs::Axis) -> Self {
+        AxisBinding::GamepadAxis {
+            axis,
+            inverted: false,
+        }
+    }
+}
+
+// The TOML serializer only supports unit enum variants, so bindings are stored as flat tables instead.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BindingRepr {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<KeyCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scancode: Option<ScanCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mouse: Option<MouseButton>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mouse_other: Option<u16>,
+    #[cfg(feature = "gamepad")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gamepad_button: Option<gilrs::Button>,
+}
+
+impl From<Binding> for BindingRepr {
+    fn from(binding: Binding) -> Self {
+        let mut repr = BindingRepr::default();
+        match binding {
+            Binding::Key(key) => repr.key = Some(key),
+            Binding::ScanCode(code) => repr.scancode = Some(code),
+            Binding::Mouse(MouseButton::Other(button)) => repr.mouse_other = Some(button),
+            Binding::Mouse(button) => repr.mouse = Some(button),
+            #[cfg(feature = "gamepad")]
+            Binding::GamepadButton(button) => repr.gamepad_button = Some(button),
+        }
+        repr
+    }
+}
+
+impl TryFrom<BindingRepr> for Binding {
+    type Error = String;
+
+    fn try_from(repr: BindingRepr) -> Result<Self, Self::Error> {
+        let mut bindings = [
+            repr.key.map(Binding::Key),
+            repr.scancode.map(Binding::ScanCode),
+            repr.mouse.map(Binding::Mouse),
+            repr.mouse_other
+                .map(|b| Binding::Mouse(MouseButton::Other(b))),
+            #[cfg(feature = "gamepad")]
+            repr.gamepad_button.map(Binding::GamepadButton),
+        ]
+        .into_iter()
+        .flatten();
+        match (bindings.next(), bindings.next()) {
+            (Some(binding), None) => Ok(binding),
+            _ => Err(String::from("a binding needs exactly one input")),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AxisBindingRepr {
+    // plain values have to come before the tables
+    #[cfg(feature = "gamepad")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gamepad_axis: Option<gilrs::Axis>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    inverted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    negative: Option<Binding>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    positive: Option<Binding>,
+}
+
+impl From<AxisBinding> for AxisBindingRepr {
+    fn from(binding: AxisBinding) -> Self {
+        match binding {
+            AxisBinding::Buttons { negative, positive } => AxisBindingRepr {
+                negative: Some(negative),
+                positive: Some(positive),
+                ..Default::default()
+            },
+            #[cfg(feature = "gamepad")]
+            AxisBinding::GamepadAxis { axis, inverted } => AxisBindingRepr {
+                gamepad_axis: Some(axis),
+                inverted,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl TryFrom<AxisBindingRepr> for AxisBinding {
+    type Error = String;
+
+    fn try_from(repr: AxisBindingRepr) -> Result<Self, Self::Error> {
+        #[cfg(feature = "gamepad")]
+        if let (Some(axis), None, None) = (repr.gamepad_axis, repr.negative, repr.positive) {
+            return Ok(AxisBinding::GamepadAxis {
+                axis,
+                inverted: repr.inverted,
+            });
+        }
+        match (repr.negative, repr.positive) {
+            (Some(negative), Some(positive)) => Ok(AxisBinding::Buttons { negative, positive }),
+            _ => Err(String::from(
+                "an axis binding needs either a negative and a positive input or a gamepad axis",
+            )),
+        }
+    }
+}
+
+/// The bindings of an [`ActionMap`], as stored in `/bindings.toml`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+struct Bindings {
+    #[serde(default)]
+    actions: BTreeMap<String, Vec<Binding>>,
+    #[serde(default)]
+    axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+#[cfg(feature = "gamepad")]
+const GAMEPAD_BUTTONS: [gilrs::Button; 19] = {
+    use gilrs::Button::*;
+    [
+        South,
+        East,
+        North,
+        West,
+        C,
+        Z,
+        LeftTrigger,
+        LeftTrigger2,
+        RightTrigger,
+        RightTrigger2,
+        Select,
+        Start,
+        Mode,
+        LeftThumb,
+        RightThumb,
+        DPadUp,
+        DPadDown,
+        DPadLeft,
+        DPadRight,
+    ]
+};
+
+/// A set of named actions and axes along with their bindings, and their state as of the last
+/// [`ActionMap::update`].
+///
+/// An action is pressed while any of its bindings is pressed. The value of an axis is the value
+/// of whichever of its bindings is pushed furthest.
+#[derive(Debug, Clone, Default)]
+pub struct ActionMap {
+    bindings: Bindings,
+    pressed: HashSet<String>,
+    previously_pressed: HashSet<String>,
+    axis_values: HashMap<String, f32>,
+    last_pressed: Option<Binding>,
+    #[cfg(feature = "gamepad")]
+    gamepad_buttons: HashSet<gilrs::Button>,
+}
+
+impl ActionMap {
+    /// Creates a new map without any actions or axes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares an action with the given bindings, replacing any previous bindings of that action.
+    #[must_use]
+    pub fn with_action(
+        mut self,
+        action: impl Into<String>,
+        bindings: impl IntoIterator<Item = Binding>,
+    ) -> Self {
+        self.set_bindings(action, bindings);
+        self
+    }
+
+    /// Declares an axis with the given bindings, replacing any previous bindings of that axis.
+    #[must_use]
+    pub fn with_axis(
+        mut self,
+        axis: impl Into<String>,
+        bindings: impl IntoIterator<Item = AxisBinding>,
+    ) -> Self {
+        self.set_axis_bindings(axis, bindings);
+        self
+    }
+
+    /// Replaces the bindings of an action, declaring it if necessary.
+    pub fn set_bindings(
+        &mut self,
+        action: impl Into<String>,
+        bindings: impl IntoIterator<Item = Binding>,
+    ) {
+        let _ = self
+            .bindings
+            .actions
+            .insert(action.into(), bindings.into_iter().collect());
+    }
+
+    /// Adds a binding to an action, declaring it if necessary.
+    pub fn bind(&mut self, action: impl Into<String>, binding: Binding) {
+        let bindings = self.bindings.actions.entry(action.into()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes a binding from an action.
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.bindings.actions.get_mut(action) {
+            bindings.retain(|&b| b != binding);
+        }
+    }
+
+    /// Returns the bindings of an action, or an empty slice if there is no such action.
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.bindings.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the names of all declared actions.
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.bindings.actions.keys().map(String::as_str)
+    }
+
+    /// Replaces the bindings of an axis, declaring it if necessary.
+    pub fn set_axis_bindings(
+        &mut self,
+        axis: impl Into<String>,
+        bindings: impl IntoIterator<Item = AxisBinding>,
+    ) {
+        let _ = self
+            .bindings
+            .axes
+            .insert(axis.into(), bindings.into_iter().collect());
+    }
+
+    /// Adds a binding to an axis, declaring it if necessary.
+    pub fn bind_axis(&mut self, axis: impl Into<String>, binding: AxisBinding) {
+        let bindings = self.bindings.axes.entry(axis.into()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes a binding from an axis.
+    pub fn unbind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        if let Some(bindings) = self.bindings.axes.get_mut(axis) {
+            bindings.retain(|&b| b != binding);
+        }
+    }
+
+    /// Returns the bindings of an axis, or an empty slice if there is no such axis.
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.bindings.axes.get(axis).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the names of all declared axes.
+    pub fn axes(&self) -> impl Iterator<Item = &str> {
+        self.bindings.axes.keys().map(String::as_str)
+    }
+
+    /// Checks if an action is currently pressed.
+    pub fn is_action_pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action)
+    }
+
+    /// Checks if an action has been pressed since the previous update.
+    pub fn is_action_just_pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action) && !self.previously_pressed.contains(action)
+    }
+
+    /// Checks if an action has been released since the previous update.
+    pub fn is_action_just_released(&self, action: &str) -> bool {
+        !self.pressed.contains(action) && self.previously_pressed.contains(action)
+    }
+
+    /// Returns the value of an axis, between -1.0 and 1.0, or 0.0 if there is no such axis.
+    pub fn axis_value(&self, axis: &str) -> f32 {
+        self.axis_values.get(axis).copied().unwrap_or(0.)
+    }
+
+    /// Returns an input that has been pressed since the previous update, if any.
+    ///
+    /// Useful for rebinding: once the player chose the action to change, wait for this
+    /// to return something and [`bind`](ActionMap::bind) it.
+    pub fn last_pressed_binding(&self) -> Option<Binding> {
+        self.last_pressed
+    }
+
+    /// Reads the current state of the keyboard, mouse and gamepads and updates the state of all
+    /// actions and axes accordingly.
+    ///
+    /// Call this once at the start of every `update`, before querying any actions.
+    pub fn update(&mut self, ctx: &Context) {
+        let pressed = |binding: Binding| match binding {
+            Binding::Key(key) => ctx.keyboard.is_key_pressed(key),
+            Binding::ScanCode(code) => ctx.keyboard.is_scancode_pressed(code),
+            Binding::Mouse(button) => ctx.mouse.button_pressed(button),
+            #[cfg(feature = "gamepad")]
+            Binding::GamepadButton(button) => ctx
+                .gamepad
+                .gamepads()
+                .any(|(_, gamepad)| gamepad.is_pressed(button)),
+        };
+        let analog = |binding: AxisBinding| match binding {
+            #[cfg(feature = "gamepad")]
+            AxisBinding::GamepadAxis { axis, inverted } => {
+                let value = ctx
+                    .gamepad
+                    .gamepads()
+                    .map(|(_, gamepad)| gamepad.value(axis))
+                    .fold(0., |a: f32, b| if b.abs() > a.abs() { b } else { a });
+                if inverted {
+                    -value
+                } else {
+                    value
+                }
+            }
+            AxisBinding::Buttons { .. } => 0.,
+        };
+        self.sample(pressed, analog);
+
+        self.last_pressed = ctx
+            .keyboard
+            .pressed_keys()
+            .iter()
+            .find(|&&key| ctx.keyboard.is_key_just_pressed(key))
+            .map(|&key| Binding::Key(key))
+            .or_else(|| {
+                ctx.keyboard
+                    .pressed_scancodes()
+                    .iter()
+                    .find(|&&code| ctx.keyboard.is_scancode_just_pressed(code))
+                    .map(|&code| Binding::ScanCode(code))
+            })
+            .or_else(|| {
+                [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+                    .into_iter()
+                    .find(|&button| ctx.mouse.button_just_pressed(button))
+                    .map(Binding::Mouse)
+            });
+
+        #[cfg(feature = "gamepad")]
+        {
+            let buttons: HashSet<_> = GAMEPAD_BUTTONS
+                .into_iter()
+                .filter(|&button| pressed(Binding::GamepadButton(button)))
+                .collect();
+            if self.last_pressed.is_none() {
+                self.last_pressed = buttons
+                    .difference(&self.gamepad_buttons)
+                    .next()
+                    .map(|&button| Binding::GamepadButton(button));
+            }
+            self.gamepad_buttons = buttons;
+        }
+    }
+
+    fn sample(&mut self, pressed: impl Fn(Binding) -> bool, analog: impl Fn(AxisBinding) -> f32) {
+        std::mem::swap(&mut self.pressed, &mut self.previously_pressed);
+        self.pressed.clear();
+        for (action, bindings) in &self.bindings.actions {
+            if bindings.iter().any(|&b| pressed(b)) {
+                let _ = self.pressed.insert(action.clone());
+            }
+        }
+
+        self.axis_values.clear();
+        for (axis, bindings) in &self.bindings.axes {
+            let value = bindings
+                .iter()
+                .map(|&binding| match binding {
+                    AxisBinding::Buttons { negative, positive } => {
+                        (pressed(positive) as i8 - pressed(negative) as i8) as f32
+                    }
+                    #[allow(unreachable_patterns)]
+                    _ => analog(binding).clamp(-1., 1.),
+                })
+                .fold(0., |a: f32, b| if b.abs() > a.abs() { b } else { a });
+            let _ = self.axis_values.insert(axis.clone(), value);
+        }
+    }
+
+    /// Loads bindings from the given `Read` object, which should contain TOML as
+    /// written by [`ActionMap::to_toml_file`].
+    ///
+    /// Actions and axes mentioned in the file get their bindings replaced;
+    /// all others keep their current bindings.
+    pub fn load_toml_file<R: io::Read>(&mut self, file: &mut R) -> GameResult {
+        let mut s = String::new();
+        let _ = file.read_to_string(&mut s)?;
+        let bindings: Bindings = toml::from_str(&s)?;
+        self.bindings.actions.extend(bindings.actions);
+        self.bindings.axes.extend(bindings.axes);
+        Ok(())
+    }
+
+    /// Saves all bindings to the given `Write` object, formatted as TOML.
+    pub fn to_toml_file<W: io::Write>(&self, file: &mut W) -> GameResult {
+        let s = toml::to_vec(&self.bindings)?;
+        file.write_all(&s)?;
+        Ok(())
+    }
+
+    /// Looks for a file named `/bindings.toml` in any resource directory and loads the bindings
+    /// in it, see [`ActionMap::load_toml_file`].
+    ///
+    /// Returns an error if there is no such file.
+    pub fn read_bindings(&mut self, fs: &impl Has<Filesystem>) -> GameResult {
+        let fs = fs.retrieve();
+        let path = path::Path::new(BINDINGS_NAME);
+        if fs.is_file(path) {
+            let mut file = fs.open(path)?;
+            self.load_toml_file(&mut file)
+        } else {
+            Err(GameError::ConfigError(String::from(
+                "Bindings file not found",
+            )))
+        }
+    }
+
+    /// Saves all bindings to `/bindings.toml` in the user config directory,
+    /// overwriting any file already there.
+    pub fn write_bindings(&self, fs: &impl Has<Filesystem>) -> GameResult {
+        let mut file = fs.retrieve().create(BINDINGS_NAME)?;
+        self.to_toml_file(&mut file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn actions() -> ActionMap {
+        ActionMap::new()
+            .with_action(
+                "jump",
+                [
+                    Binding::Key(KeyCode::Space),
+                    Binding::Mouse(MouseButton::Left),
+                    Binding::Mouse(MouseButton::Other(4)),
+                ],
+            )
+            .with_action("crouch", [Binding::ScanCode(31)])
+            .with_axis(
+                "move",
+                [AxisBinding::buttons(
+                    Binding::Key(KeyCode::A),
+                    Binding::Key(KeyCode::D),
+                )],
+            )
+    }
+
+    #[test]
+    fn action_state() {
+        let mut actions = actions();
+        let mut down = vec![Binding::Key(KeyCode::Space), Binding::Key(KeyCode::D)];
+        actions.sample(|b| down.contains(&b), |_| 0.);
+        assert!(actions.is_action_pressed("jump"));
+        assert!(actions.is_action_just_pressed("jump"));
+        assert!(!actions.is_action_pressed("crouch"));
+        assert_eq!(actions.axis_value("move"), 1.);
+
+        down.push(Binding::Key(KeyCode::A));
+        actions.sample(|b| down.contains(&b), |_| 0.);
+        assert!(actions.is_action_pressed("jump"));
+        assert!(!actions.is_action_just_pressed("jump"));
+        assert_eq!(actions.axis_value("move"), 0.);
+
+        actions.unbind("jump", Binding::Key(KeyCode::Space));
+        actions.sample(|b| down.contains(&b), |_| 0.);
+        assert!(actions.is_action_just_released("jump"));
+        assert_eq!(actions.axis_value("missing"), 0.);
+    }
+
+    #[test]
+    fn bindings_round_trip() {
+        let mut file = Vec::new();
+        actions().to_toml_file(&mut file).unwrap();
+
+        let mut loaded = ActionMap::new().with_action("pause", [Binding::Key(KeyCode::Escape)]);
+        loaded.load_toml_file(&mut file.as_slice()).unwrap();
+        assert_eq!(loaded.bindings, {
+            let mut expected = actions().bindings;
+            let _ = expected
+                .actions
+                .insert("pause".into(), vec![Binding::Key(KeyCode::Escape)]);
+            expected
+        });
+    }
+}
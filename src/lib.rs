@@ -194,6 +194,7 @@ extern crate log;
 pub use glam;
 pub use mint;
 
+pub mod assets;
 pub mod audio;
 pub mod conf;
 pub mod context;
@@ -202,6 +203,7 @@ pub mod event;
 pub mod filesystem;
 pub mod graphics;
 pub mod input;
+pub mod profiler;
 pub mod timer;
 mod vfs;
 
@@ -0,0 +1,446 @@
+//! Frame profiling, to find out where the time of each frame goes.
+//!
+//! When enabled, the [`Profiler`] in [`Context::profiler`](crate::Context::profiler) records
+//! a [`FrameProfile`] for each frame run by [`event::run`](crate::event::run): how long `update`,
+//! `draw` and presenting the frame took on the CPU, how long the frame took on the GPU if the
+//! adapter supports timestamp queries, and its [`FrameStats`]: how many draw calls, pipeline
+//! switches and bind group changes it took and how much data was uploaded. Parts of your own code can be timed with
+//! [`Profiler::begin_scope`] and [`Profiler::end_scope`].
+//!
+//! The recorded frames can be shown as a graph over the frame with [`Profiler::set_overlay`],
+//! and saved for `chrome://tracing` or [Perfetto](https://ui.perfetto.dev) with
+//! [`Profiler::write_chrome_trace`].
+//!
+//! ```rust,no_run
+//! # use ggez::{Context, GameResult};
+//! # fn t(ctx: &mut Context) -> GameResult {
+//! ctx.profiler.set_overlay(true);
+//!
+//! // in update:
+//! ctx.profiler.begin_scope("physics");
+//! // ...
+//! ctx.profiler.end_scope();
+//!
+//! // when done:
+//! let file = ctx.fs.create("/trace.json")?;
+//! ctx.profiler.write_chrome_trace(file)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    graphics::{
+        Canvas, Color, DrawMode, DrawParam, FrameStats, GraphicsContext, Mesh, MeshBuilder, Rect,
+        Text,
+    },
+    GameResult,
+};
+use serde_json::json;
+use std::{
+    collections::VecDeque,
+    io::Write,
+    time::{Duration, Instant},
+};
+
+/// A part of your own code timed with [`Profiler::begin_scope`] and [`Profiler::end_scope`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileScope {
+    /// Name given to [`Profiler::begin_scope`].
+    pub name: String,
+    /// When the scope began, relative to when the profiler was created.
+    pub start: Duration,
+    /// How long the scope took.
+    pub duration: Duration,
+    /// How many other scopes the scope is nested in.
+    pub depth: usize,
+}
+
+/// Timings and render counts of one frame.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameProfile {
+    /// Number of the frame, counting all frames recorded by the profiler.
+    pub frame: u64,
+    /// When the frame began, relative to when the profiler was created.
+    pub start: Duration,
+    /// Time spent in `fixed_update` and `update`.
+    pub update: Duration,
+    /// Time spent in `draw`.
+    pub draw: Duration,
+    /// Time spent submitting and presenting the frame, including the overlay.
+    pub present: Duration,
+    /// Total CPU time of the frame; the sum of the above.
+    pub total: Duration,
+    /// Latest GPU time of a frame, if the adapter supports timestamp queries.
+    ///
+    /// GPU timings are read back asynchronously, so this is usually from one of the previous frames.
+    pub gpu: Option<Duration>,
+    /// Render statistics of the frame.
+    pub stats: FrameStats,
+    /// Scopes timed during the frame.
+    pub scopes: Vec<ProfileScope>,
+}
+
+/// The part of a frame that just ended, for [`Profiler::mark`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Phase {
+    Update,
+    Draw,
+    Present,
+}
+
+/// Records [`FrameProfile`]s of the most recent frames.
+///
+/// The profiler is disabled by default. See the [module documentation](self) for an example.
+#[derive(Debug)]
+pub struct Profiler {
+    enabled: bool,
+    overlay: bool,
+    epoch: Instant,
+    capacity: usize,
+    frames: VecDeque<FrameProfile>,
+    current: FrameProfile,
+    last_mark: Instant,
+    open_scopes: Vec<(String, Instant)>,
+    next_frame: u64,
+}
+
+impl Profiler {
+    pub(crate) fn new() -> Self {
+        let now = Instant::now();
+        Profiler {
+            enabled: false,
+            overlay: false,
+            epoch: now,
+            capacity: 300,
+            frames: VecDeque::new(),
+            current: FrameProfile::default(),
+            last_mark: now,
+            open_scopes: Vec::new(),
+            next_frame: 0,
+        }
+    }
+
+    /// Returns whether frames are being recorded.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Starts or stops recording frames. Stopping also hides the overlay.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.overlay = false;
+            self.open_scopes.clear();
+        }
+    }
+
+    /// Returns whether the overlay is drawn.
+    #[inline]
+    pub fn overlay(&self) -> bool {
+        self.overlay
+    }
+
+    /// Shows or hides a graph of the recorded frame times and the latest counts over each frame.
+    /// Showing it enables the profiler.
+    pub fn set_overlay(&mut self, overlay: bool) {
+        if overlay {
+            self.enabled = true;
+        }
+        self.overlay = overlay;
+    }
+
+    /// Sets how many of the most recent frames are kept. The default is 300.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.frames.len() > self.capacity {
+            let _ = self.frames.pop_front();
+        }
+    }
+
+    /// Returns the recorded frames, oldest first.
+    #[inline]
+    pub fn frames(&self) -> &VecDeque<FrameProfile> {
+        &self.frames
+    }
+
+    /// Returns the most recently recorded frame.
+    #[inline]
+    pub fn last_frame(&self) -> Option<&FrameProfile> {
+        self.frames.back()
+    }
+
+    /// Removes all recorded frames.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Starts timing a scope of your own code, which ends with the next call to [`Profiler::end_scope`].
+    ///
+    /// Scopes can be nested. Does nothing while the profiler is disabled.
+    pub fn begin_scope(&mut self, name: impl Into<String>) {
+        if self.enabled {
+            self.open_scopes.push((name.into(), Instant::now()));
+        }
+    }
+
+    /// Ends the scope most recently begun with [`Profiler::begin_scope`].
+    pub fn end_scope(&mut self) {
+        if let Some((name, start)) = self.open_scopes.pop() {
+            self.current.scopes.push(ProfileScope {
+                name,
+                start: start - self.epoch,
+                duration: start.elapsed(),
+                depth: self.open_scopes.len(),
+            });
+        }
+    }
+
+    /// Writes the recorded frames as a JSON trace for `chrome://tracing` or Perfetto.
+    ///
+    /// Frames, their phases and scopes become duration events; render counts and GPU times
+    /// become counter events.
+    pub fn write_chrome_trace(&self, writer: impl Write) -> GameResult {
+        let micros = |d: Duration| d.as_secs_f64() * 1e6;
+        let span = |name: &str, category: &str, start: Duration, duration: Duration| {
+            json!({
+                "name": name,
+                "cat": category,
+                "ph": "X",
+                "ts": micros(start),
+                "dur": micros(duration),
+                "pid": 1,
+                "tid": 1,
+            })
+        };
+        let counter = |name: &str, start: Duration, args: serde_json::Value| json!({ "name": name, "ph": "C", "ts": micros(start), "pid": 1, "args": args });
+
+        let mut events = Vec::new();
+        for frame in &self.frames {
+            let name = format!("frame {}", frame.frame);
+            events.push(span(&name, "frame", frame.start, frame.total));
+            let mut start = frame.start;
+            for (name, duration) in [
+                ("update", frame.update),
+                ("draw", frame.draw),
+                ("present", frame.present),
+            ] {
+                events.push(span(name, "phase", start, duration));
+                start += duration;
+            }
+            for scope in &frame.scopes {
+                events.push(span(&scope.name, "scope", scope.start, scope.duration));
+            }
+
+            let stats = &frame.stats;
+            let draws = json!({
+                "draw calls": stats.draw_calls,
+                "instances": stats.instances,
+                "pipeline switches": stats.pipeline_switches,
+                "bind group changes": stats.bind_group_changes,
+                "glyph cache misses": stats.glyph_cache_misses,
+            });
+            events.push(counter("draws", frame.start, draws));
+            let uploads = json!({
+                "vertex": stats.vertex_bytes,
+                "text": stats.text_bytes,
+                "arena": stats.arena_bytes,
+            });
+            events.push(counter("upload bytes", frame.start, uploads));
+            if let Some(gpu) = frame.gpu {
+                let gpu = json!({ "ms": gpu.as_secs_f64() * 1e3 });
+                events.push(counter("gpu time", frame.start, gpu));
+            }
+        }
+
+        let trace = json!({ "traceEvents": events, "displayTimeUnit": "ms" });
+        serde_json::to_writer(writer, &trace).map_err(std::io::Error::from)?;
+        Ok(())
+    }
+
+    /// Called at the start of each frame.
+    pub(crate) fn begin_frame(&mut self) {
+        let now = Instant::now();
+        self.current.start = now - self.epoch;
+        self.last_mark = now;
+    }
+
+    /// Records the time since the previous mark as the time spent in a phase.
+    pub(crate) fn mark(&mut self, phase: Phase) {
+        let now = Instant::now();
+        let duration = now - self.last_mark;
+        self.last_mark = now;
+        match phase {
+            Phase::Update => self.current.update = duration,
+            Phase::Draw => self.current.draw = duration,
+            Phase::Present => self.current.present = duration,
+        }
+    }
+
+    /// Called at the end of each frame, after it was presented.
+    pub(crate) fn end_frame(&mut self, gfx: &GraphicsContext) {
+        let mut frame = std::mem::take(&mut self.current);
+        if !self.enabled {
+            return;
+        }
+        frame.frame = self.next_frame;
+        frame.total = frame.update + frame.draw + frame.present;
+        frame.gpu = gfx.gpu_frame_time();
+        frame.stats = gfx.last_frame_stats();
+
+        self.next_frame += 1;
+        if self.frames.len() == self.capacity {
+            let _ = self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// Draws the overlay onto the image being presented, if it's shown.
+    pub(crate) fn draw_overlay(&self, gfx: &mut GraphicsContext) -> GameResult {
+        const BAR: f32 = 2.;
+        const PIXELS_PER_MS: f32 = 3.;
+        const HEIGHT: f32 = 100.;
+        if !self.overlay {
+            return Ok(());
+        }
+
+        let bars = self.capacity.min(150);
+        let width = bars as f32 * BAR;
+        let mut builder = MeshBuilder::new();
+        let _ = builder.rectangle(
+            DrawMode::fill(),
+            Rect::new(8., 8., width, HEIGHT),
+            Color::new(0., 0., 0., 0.6),
+        )?;
+        let bottom = 8. + HEIGHT;
+        for (i, frame) in self.frames.iter().rev().take(bars).enumerate() {
+            let x = 8. + width - (i + 1) as f32 * BAR;
+            let mut y = bottom;
+            for (duration, color) in [
+                (frame.update, Color::new(0.3, 0.6, 1., 1.)),
+                (frame.draw, Color::new(0.3, 0.9, 0.4, 1.)),
+                (frame.present, Color::new(1., 0.6, 0.2, 1.)),
+            ] {
+                let height = (duration.as_secs_f32() * 1e3 * PIXELS_PER_MS).min(y - 8.);
+                if height > 0. {
+                    y -= height;
+                    let _ =
+                        builder.rectangle(DrawMode::fill(), Rect::new(x, y, BAR, height), color)?;
+                }
+            }
+        }
+        // the budget of a frame at 60 fps
+        let target = bottom - 1e3 / 60. * PIXELS_PER_MS;
+        let _ = builder.line(&[[8., target], [8. + width, target]], 1., Color::WHITE)?;
+
+        let label = match self.frames.back() {
+            Some(frame) => {
+                let ms = |d: Duration| d.as_secs_f64() * 1e3;
+                let gpu = frame
+                    .gpu
+                    .map_or_else(|| String::from("-"), |gpu| format!("{:.2}", ms(gpu)));
+                format!(
+                    "cpu {:.2} ms (update {:.2}, draw {:.2}, present {:.2}), gpu {gpu} ms\n\
+                     {} draw calls, {} instances, {} pipeline switches, {} bind group changes\n\
+                     {} KiB vertices, {} KiB text, {} KiB arenas, {} glyph cache misses",
+                    ms(frame.total),
+                    ms(frame.update),
+                    ms(frame.draw),
+                    ms(frame.present),
+                    frame.stats.draw_calls,
+                    frame.stats.instances,
+                    frame.stats.pipeline_switches,
+                    frame.stats.bind_group_changes,
+                    frame.stats.vertex_bytes / 1024,
+                    frame.stats.text_bytes / 1024,
+                    frame.stats.arena_bytes / 1024,
+                    frame.stats.glyph_cache_misses,
+                )
+            }
+            None => String::from("no frames recorded yet"),
+        };
+
+        let present = gfx.presented_image()?;
+        let mut canvas = Canvas::from_image(gfx, present, None);
+        canvas.draw(&Mesh::from_data(gfx, builder.build()), DrawParam::new());
+        canvas.draw(
+            Text::new(label).set_scale(14.),
+            DrawParam::new().dest([8., bottom + 4.]).color(Color::WHITE),
+        );
+        canvas.finish(gfx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::test_context;
+    use crate::graphics::Image;
+
+    #[test]
+    fn frames_and_trace() {
+        let mut ctx = test_context("ggez_profiler_test");
+        ctx.profiler.set_overlay(true);
+        ctx.profiler.set_capacity(2);
+
+        for _ in 0..3 {
+            ctx.profiler.begin_frame();
+            ctx.profiler.begin_scope("outer");
+            ctx.profiler.begin_scope("inner");
+            ctx.profiler.end_scope();
+            ctx.profiler.end_scope();
+            ctx.profiler.mark(Phase::Update);
+            ctx.gfx.begin_frame().unwrap();
+            let mut canvas = Canvas::from_frame(&ctx, Color::BLACK);
+            canvas.draw(&Text::new("hello"), [0., 0.]);
+            canvas.finish(&mut ctx).unwrap();
+            ctx.profiler.mark(Phase::Draw);
+            ctx.profiler.draw_overlay(&mut ctx.gfx).unwrap();
+            ctx.gfx.end_frame().unwrap();
+            ctx.profiler.mark(Phase::Present);
+            ctx.profiler.end_frame(&ctx.gfx);
+        }
+
+        assert_eq!(ctx.profiler.frames().len(), 2);
+        let frame = ctx.profiler.last_frame().unwrap();
+        assert_eq!(frame.frame, 2);
+        assert_eq!(frame.total, frame.update + frame.draw + frame.present);
+        // the text, and the overlay's mesh and text
+        assert!(frame.stats.draw_calls >= 3);
+        assert!(frame.stats.pipeline_switches >= 1);
+        assert!(frame.stats.vertex_bytes > 0 && frame.stats.text_bytes > 0);
+        let depths: Vec<_> = frame
+            .scopes
+            .iter()
+            .map(|s| (s.name.as_str(), s.depth))
+            .collect();
+        assert_eq!(depths, [("inner", 1), ("outer", 0)]);
+
+        let mut trace = Vec::new();
+        ctx.profiler.write_chrome_trace(&mut trace).unwrap();
+        let trace: serde_json::Value = serde_json::from_slice(&trace).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+        assert!(events
+            .iter()
+            .any(|e| e["name"] == "frame 2" && e["ph"] == "X"));
+        assert!(events.iter().any(|e| e["name"] == "inner"));
+    }
+
+    #[test]
+    fn overlay_on_presented_image() {
+        let mut ctx = test_context("ggez_profiler_test");
+        ctx.profiler.set_overlay(true);
+        let image = Image::new_canvas_image(&ctx, ctx.gfx.surface_format(), 64, 64, 1);
+
+        ctx.gfx.begin_frame().unwrap();
+        Canvas::from_image(&ctx, image.clone(), Color::BLACK)
+            .finish(&mut ctx)
+            .unwrap();
+        ctx.gfx.present(&image).unwrap();
+        ctx.profiler.draw_overlay(&mut ctx.gfx).unwrap();
+        ctx.gfx.end_frame().unwrap();
+
+        let pixels = image.to_pixels(&ctx).unwrap();
+        assert!(pixels.chunks(4).any(|pixel| pixel[..3] != [0, 0, 0]));
+    }
+}
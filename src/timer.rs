@@ -90,6 +90,9 @@ pub struct TimeContext {
     last_instant: time::Instant,
     frame_durations: LogBuffer<time::Duration>,
     residual_update_dt: time::Duration,
+    // separate from `residual_update_dt`, so `check_update_time` can still be used
+    residual_fixed_dt: time::Duration,
+    fixed_delta: time::Duration,
     frame_count: usize,
 }
 
@@ -105,6 +108,8 @@ impl TimeContext {
             last_instant: time::Instant::now(),
             frame_durations: LogBuffer::new(TIME_LOG_FRAMES, initial_dt),
             residual_update_dt: time::Duration::from_secs(0),
+            residual_fixed_dt: time::Duration::ZERO,
+            fixed_delta: time::Duration::ZERO,
             frame_count: 0,
         }
     }
@@ -195,6 +200,58 @@ impl TimeContext {
         self.residual_update_dt
     }
 
+    /// Returns how many fixed updates at `rate` updates per second are due, and consumes
+    /// the time they account for, like calling [`check_update_time()`](#method.check_update_time)
+    /// in a loop would. The time is tracked separately from `check_update_time()`, so both can
+    /// be used in the same game.
+    ///
+    /// A `rate` of 0 is treated as 1.
+    ///
+    /// At most `max_steps` updates are returned; if more are due the game can't keep up,
+    /// so the excess time is dropped to avoid falling further and further behind.
+    ///
+    /// [`event::run()`](../event/fn.run.html) calls this every frame when the fixed timestep
+    /// mode is enabled in [`Conf`](../conf/struct.FixedTimestep.html); you only need to call it
+    /// yourself if you're writing your own event loop.
+    pub fn fixed_update_steps(&mut self, rate: u32, max_steps: u32) -> u32 {
+        let step = fps_as_duration(rate);
+        self.fixed_delta = step;
+        let mut steps = 0;
+        while self.residual_fixed_dt >= step {
+            if steps == max_steps {
+                let excess = self.residual_fixed_dt.as_secs_f64() % step.as_secs_f64();
+                self.residual_fixed_dt = time::Duration::from_secs_f64(excess);
+                break;
+            }
+            self.residual_fixed_dt -= step;
+            steps += 1;
+        }
+        steps
+    }
+
+    /// Returns the length of a fixed update step, as of the last call to
+    /// [`fixed_update_steps()`](#method.fixed_update_steps), or zero if it wasn't called yet.
+    ///
+    /// Use this as the time step in
+    /// [`EventHandler::fixed_update()`](../event/trait.EventHandler.html#method.fixed_update).
+    pub fn fixed_delta(&self) -> time::Duration {
+        self.fixed_delta
+    }
+
+    /// Returns how far the game is between the last fixed update and the next one,
+    /// from 0.0 (just updated) to 1.0 (about to update).
+    ///
+    /// The intention is for it to be called in your
+    /// [`draw()`](../event/trait.EventHandler.html#tymethod.draw) callback
+    /// to interpolate between the previous and the current physics state
+    /// when using a fixed timestep.
+    pub fn interpolation_alpha(&self) -> f32 {
+        if self.fixed_delta.is_zero() {
+            return 0.0;
+        }
+        (self.residual_fixed_dt.as_secs_f64() / self.fixed_delta.as_secs_f64()).min(1.0) as f32
+    }
+
     /// Update the state of the `TimeContext` to record that
     /// another frame has taken place.  Necessary for the FPS
     /// tracking and [`check_update_time()`](fn.check_update_time.html)
@@ -212,6 +269,18 @@ impl TimeContext {
         self.frame_count += 1;
 
         self.residual_update_dt += time_since_last;
+        self.residual_fixed_dt += time_since_last;
+    }
+
+    /// Like [`TimeContext::tick`], but records a frame of the given duration
+    /// instead of measuring it, as done when replaying an input recording.
+    pub(crate) fn tick_by(&mut self, dt: time::Duration) {
+        self.frame_durations.push(dt);
+        self.last_instant = time::Instant::now();
+        self.frame_count += 1;
+
+        self.residual_update_dt += dt;
+        self.residual_fixed_dt += dt;
     }
 }
 
@@ -236,11 +305,11 @@ pub fn average_delta(ctx: &Context) -> time::Duration {
 }
 
 /// Returns a `Duration` representing how long each
-/// frame should be to match the given fps.
+/// frame should be to match the given fps, which is at least 1.
 ///
 /// Approximately.
 fn fps_as_duration(fps: u32) -> time::Duration {
-    let target_dt_seconds = 1.0 / f64::from(fps);
+    let target_dt_seconds = 1.0 / f64::from(fps.max(1));
     time::Duration::from_secs_f64(target_dt_seconds)
 }
 
@@ -329,3 +398,33 @@ pub fn yield_now() {
 pub fn ticks(ctx: &Context) -> usize {
     ctx.time.frame_count
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_update_steps() {
+        let mut time = TimeContext::new();
+        let step = fps_as_duration(50);
+
+        time.tick_by(step * 2 + step / 2);
+        assert_eq!(time.fixed_update_steps(50, 5), 2);
+        assert_eq!(time.fixed_delta(), step);
+        assert!((time.interpolation_alpha() - 0.5).abs() < 1e-3);
+        // the time isn't consumed twice when `check_update_time` is used as well
+        assert!(time.check_update_time(50));
+        assert!((time.interpolation_alpha() - 0.5).abs() < 1e-3);
+
+        // falling too far behind drops the excess instead of catching up
+        time.residual_fixed_dt = step * 20 + step / 4;
+        assert_eq!(time.fixed_update_steps(50, 5), 5);
+        assert!(time.residual_fixed_dt < step);
+        assert!((time.interpolation_alpha() - 0.25).abs() < 1e-3);
+
+        // a rate of 0 is one update per second, rather than a division by zero
+        time.residual_fixed_dt = time::Duration::from_millis(1500);
+        assert_eq!(time.fixed_update_steps(0, 5), 1);
+        assert_eq!(time.fixed_delta(), time::Duration::from_secs(1));
+    }
+}
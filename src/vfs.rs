@@ -15,6 +15,7 @@ use std::fmt::{self, Debug};
 use std::fs;
 use std::io::{self, Read, Seek, Write};
 use std::path::{self, Path, PathBuf};
+use std::time::SystemTime;
 
 use crate::error::{GameError, GameResult};
 
@@ -25,9 +26,9 @@ fn convenient_path_to_str(path: &path::Path) -> GameResult<&str> {
     })
 }
 
-pub trait VFile: Read + Write + Seek + Debug {}
+pub trait VFile: Read + Write + Seek + Debug + Send {}
 
-impl<T> VFile for T where T: Read + Write + Seek + Debug {}
+impl<T> VFile for T where T: Read + Write + Seek + Debug + Send {}
 
 /// Options for opening files
 ///
@@ -147,6 +148,8 @@ pub trait VMetadata {
     /// Returns the length of the thing.  If it is a directory,
     /// the result of this is undefined/platform dependent.
     fn len(&self) -> u64;
+    /// Returns the last modification time, if available.
+    fn modified(&self) -> Option<SystemTime>;
 }
 
 /// A VFS that points to a directory and uses it as the root of its
@@ -174,6 +177,9 @@ impl VMetadata for PhysicalMetadata {
     fn len(&self) -> u64 {
         self.0.len()
     }
+    fn modified(&self) -> Option<SystemTime> {
+        self.0.modified().ok()
+    }
 }
 
 /// This takes an absolute path and returns either a sanitized relative
@@ -728,6 +734,9 @@ impl VMetadata for ZipMetadata {
     fn len(&self) -> u64 {
         self.len
     }
+    fn modified(&self) -> Option<SystemTime> {
+        None
+    }
 }
 
 impl VFS for ZipFS {